@@ -0,0 +1,67 @@
+use crate::{is_destructive, Decision};
+use regex::Regex;
+use std::fmt;
+
+/// Policy consulted for every meet statement before an object is met (not in dry run).
+///
+/// If any statement of an object is decided to be skipped or aborted then none of the object
+/// statements are executed.
+pub trait ApprovalPolicy: fmt::Debug {
+    fn decide(&self, object: &str, statement: &str) -> Decision;
+}
+
+/// Approves all statements.
+#[derive(Debug, Default, Clone)]
+pub struct AutoApprove;
+
+impl ApprovalPolicy for AutoApprove {
+    fn decide(&self, _object: &str, _statement: &str) -> Decision {
+        Decision::Apply
+    }
+}
+
+/// Aborts the run on destructive statements (see `is_destructive`).
+#[derive(Debug, Default, Clone)]
+pub struct DenyDestructive;
+
+impl ApprovalPolicy for DenyDestructive {
+    fn decide(&self, _object: &str, statement: &str) -> Decision {
+        if is_destructive(statement) {
+            Decision::Abort
+        } else {
+            Decision::Apply
+        }
+    }
+}
+
+/// Aborts the run on statements not matching any of the patterns.
+#[derive(Debug, Clone)]
+pub struct Allowlist {
+    patterns: Vec<Regex>,
+}
+
+impl Allowlist {
+    /// Creates `Allowlist` given list of regular expressions statements are matched against.
+    pub fn new(patterns: &[&str]) -> Result<Allowlist, regex::Error> {
+        Ok(Allowlist {
+            patterns: patterns
+                .iter()
+                .map(|pattern| Regex::new(pattern))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+impl ApprovalPolicy for Allowlist {
+    fn decide(&self, _object: &str, statement: &str) -> Decision {
+        if self
+            .patterns
+            .iter()
+            .any(|pattern| pattern.is_match(statement))
+        {
+            Decision::Apply
+        } else {
+            Decision::Abort
+        }
+    }
+}
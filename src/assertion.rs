@@ -0,0 +1,110 @@
+use crate::state::Scalar;
+use crate::{Ensure, EnsureSchema, Sql};
+use odbc_iter::Handle;
+use problem::prelude::*;
+use std::cell::Cell;
+use std::fmt;
+
+/// Step verifying data invariant (e.g. no orphan rows or no `NULL`s in a column) without meeting
+/// anything: its query counts rows violating the invariant and the step fails with
+/// `SchemaStateError::AssertionError` (reported as `ObjectState::AssertionFailed`) if the count is
+/// not zero, also in dry run.
+///
+/// Use it as requirement of structural changes that depend on the invariant so that they are not
+/// attempted on data that would break them.
+pub struct AssertStep {
+    name: String,
+    query: Sql,
+    description: String,
+    violations: Cell<Option<i64>>,
+}
+
+impl fmt::Debug for AssertStep {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("AssertStep")
+            .field("name", &self.name)
+            .field("query", &self.query)
+            .field("description", &self.description)
+            .finish()
+    }
+}
+
+impl AssertStep {
+    /// Creates `AssertStep` given name and query producing single row with count of rows
+    /// violating the invariant.
+    pub fn new(name: impl Into<String>, query: impl Into<Sql>) -> AssertStep {
+        let name = name.into();
+        AssertStep {
+            description: name.clone(),
+            name,
+            query: query.into(),
+            violations: Cell::new(None),
+        }
+    }
+
+    /// Asserts that column of the table has no `NULL` values.
+    pub fn no_nulls(table: &str, column: &str) -> AssertStep {
+        AssertStep::new(
+            format!("no NULLs in {}.{}", table, column),
+            format!("SELECT COUNT(*) FROM {} WHERE {} IS NULL", table, column),
+        )
+    }
+
+    /// Asserts that every non-`NULL` value of the column has referenced row.
+    pub fn no_orphans(
+        table: &str,
+        column: &str,
+        referenced_table: &str,
+        referenced_column: &str,
+    ) -> AssertStep {
+        AssertStep::new(
+            format!(
+                "no orphans in {}.{} referencing {}.{}",
+                table, column, referenced_table, referenced_column
+            ),
+            format!(
+                "SELECT COUNT(*) FROM {} c WHERE c.{} IS NOT NULL AND NOT EXISTS (SELECT 1 FROM {} p WHERE p.{} = c.{})",
+                table, column, referenced_table, referenced_column, column
+            ),
+        )
+    }
+
+    /// Sets description of the invariant used in the failure message; defaults to the name.
+    pub fn with_description(mut self, description: impl Into<String>) -> AssertStep {
+        self.description = description.into();
+        self
+    }
+}
+
+impl Ensure for AssertStep {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn check(&self, database: &mut Handle<'_>) -> Result<bool, Problem> {
+        let violations = database
+            .query::<Scalar>(&self.query)?
+            .single()?
+            .0
+            .ok_or_problem("Assertion query returned NULL")?;
+        self.violations.set(Some(violations));
+        Ok(violations == 0)
+    }
+
+    fn meet(&self, _database: &mut Handle<'_>) -> Result<(), Problem> {
+        problem!("Assertion {} cannot be met", self.name)
+    }
+
+    fn assertion(&self) -> Option<String> {
+        Some(match self.violations.get() {
+            Some(violations) => format!("{} rows violate {}", violations, self.description),
+            None => self.description.clone(),
+        })
+    }
+}
+
+impl From<AssertStep> for EnsureSchema {
+    fn from(step: AssertStep) -> EnsureSchema {
+        EnsureSchema::step(step)
+    }
+}
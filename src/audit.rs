@@ -0,0 +1,135 @@
+use crate::hash::Fnv64;
+use crate::state::{quote_literal, table_schema};
+use crate::{EnsureReport, EnsureSchema, ObjectState, SchemaStateError};
+use odbc_iter::Handle;
+use problem::prelude::*;
+
+/// Table maintained by this crate recording every ensure run that was not a dry run.
+///
+/// One row per executed statement is written with its hash (and optionally text) followed by a
+/// summary row with no object name carrying the outcome of the run.
+#[derive(Debug, Clone)]
+pub struct AuditTable {
+    table: String,
+    statement_text: bool,
+}
+
+impl AuditTable {
+    /// Creates `AuditTable` given table name.
+    pub fn new(table: impl Into<String>) -> AuditTable {
+        AuditTable {
+            table: table.into(),
+            statement_text: false,
+        }
+    }
+
+    /// If set to `true` statement text (truncated to 4000 characters) is recorded next to its hash.
+    pub fn with_statement_text(mut self, statement_text: bool) -> AuditTable {
+        self.statement_text = statement_text;
+        self
+    }
+
+    /// Name of the table.
+    pub fn table(&self) -> &str {
+        &self.table
+    }
+
+    /// `EnsureSchema` that creates the table if it does not exist.
+    pub fn ensure_schema(&self) -> EnsureSchema {
+        let create = format!(
+            "CREATE TABLE {} (run_id VARCHAR(64) NOT NULL, started_at BIGINT NOT NULL, finished_at BIGINT NOT NULL, \
+            host VARCHAR(255), pid BIGINT, os_user VARCHAR(255), initiator VARCHAR(255), object_name VARCHAR(255), statement_index INTEGER, \
+            statement_hash VARCHAR(16), statement_text VARCHAR(4000), outcome VARCHAR(4000))",
+            self.table
+        );
+        table_schema(format!("audit table {}", self.table), &self.table, create)
+    }
+
+    /// Writes audit rows for given run report and error the run has failed with, if any.
+    pub fn record(
+        &self,
+        database: &mut Handle<'_>,
+        report: &EnsureReport,
+        error: Option<&SchemaStateError>,
+    ) -> Result<(), Problem> {
+        let metadata = &report.metadata;
+        let host = optional_literal(metadata.host.as_ref());
+        let os_user = optional_literal(metadata.os_user.as_ref());
+        let initiator = optional_literal(metadata.initiator.as_ref());
+        let started_at = report.started_at;
+        let finished_at = started_at + report.duration.as_secs() as i64;
+        let insert = |object_name: String,
+                      statement_index: String,
+                      statement_hash: String,
+                      statement_text: String,
+                      outcome: String| {
+            format!(
+                "INSERT INTO {} (run_id, started_at, finished_at, host, pid, os_user, initiator, object_name, \
+                statement_index, statement_hash, statement_text, outcome) VALUES ({}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {})",
+                self.table,
+                quote_literal(&report.run_id),
+                started_at,
+                finished_at,
+                host,
+                metadata.pid,
+                os_user,
+                initiator,
+                object_name,
+                statement_index,
+                statement_hash,
+                statement_text,
+                outcome
+            )
+        };
+
+        let mut queries = Vec::new();
+        let outcome = match error {
+            Some(err) => format!("failed: {}", err),
+            None => "ok".to_string(),
+        };
+        for object in &report.objects {
+            for (index, statement) in object.statements.iter().enumerate() {
+                let mut hasher = Fnv64::new();
+                hasher.write(statement.as_bytes());
+                queries.push(insert(
+                    quote_literal(&object.name),
+                    index.to_string(),
+                    quote_literal(&hasher.finish_hex()),
+                    if self.statement_text {
+                        quote_literal(&truncate(statement.redacted(), 4000))
+                    } else {
+                        "NULL".to_string()
+                    },
+                    quote_literal(match &object.state {
+                        ObjectState::Failed(_)
+                        | ObjectState::AssertionFailed(_)
+                        | ObjectState::VerificationFailed(_) => "failed",
+                        _ => "ok",
+                    }),
+                ));
+            }
+        }
+        queries.push(insert(
+            "NULL".to_string(),
+            "NULL".to_string(),
+            "NULL".to_string(),
+            "NULL".to_string(),
+            quote_literal(&truncate(&outcome, 4000)),
+        ));
+
+        for query in queries {
+            database.query::<()>(&query)?.no_result()?;
+        }
+        Ok(())
+    }
+}
+
+fn truncate(value: &str, chars: usize) -> String {
+    value.chars().take(chars).collect()
+}
+
+fn optional_literal(value: Option<&String>) -> String {
+    value
+        .map(|value| quote_literal(value))
+        .unwrap_or_else(|| "NULL".to_string())
+}
@@ -0,0 +1,139 @@
+use crate::{EnsureReport, ObjectState};
+use std::fmt::Write;
+use std::time::Duration;
+
+/// Format of changelog generated from `EnsureReport`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangelogFormat {
+    Markdown,
+    Text,
+}
+
+pub(crate) fn format_duration(duration: Duration) -> String {
+    format!("{:.3}s", duration.as_secs_f64())
+}
+
+impl EnsureReport {
+    /// Renders human-readable changelog listing objects that were not met (changed, failed,
+    /// skipped or not attempted) with statements run and durations, suitable for pasting into a
+    /// deployment ticket; groups are neither counted nor listed as their members are.
+    pub fn changelog(&self, format: ChangelogFormat) -> String {
+        let mut out = String::new();
+        self.write_changelog(&mut out, format)
+            .expect("writing to String failed");
+        out
+    }
+
+    fn write_changelog(&self, out: &mut String, format: ChangelogFormat) -> std::fmt::Result {
+        let markdown = format == ChangelogFormat::Markdown;
+        let objects = self.objects.iter().filter(|object| !object.group);
+        let listed = objects
+            .clone()
+            .filter_map(|object| {
+                if object.state == ObjectState::Met {
+                    return None;
+                }
+                Some((object, object.state.to_string()))
+            })
+            .collect::<Vec<_>>();
+        let count =
+            |f: fn(&ObjectState) -> bool| objects.clone().filter(|object| f(&object.state)).count();
+
+        let title = if self.dry_run {
+            "Schema changes planned"
+        } else {
+            "Schema changes"
+        };
+        if markdown {
+            writeln!(out, "## {} (run {})", title, self.run_id)?;
+        } else {
+            writeln!(out, "{} (run {})", title, self.run_id)?;
+        }
+        writeln!(out)?;
+        writeln!(
+            out,
+            "{} object(s) changed, {} failed, {} skipped, {} not attempted, {} already met, took {}.",
+            count(ObjectState::is_change),
+            count(ObjectState::is_failure),
+            count(|state| *state == ObjectState::Skipped),
+            count(|state| *state == ObjectState::NotAttempted),
+            count(|state| *state == ObjectState::Met),
+            format_duration(self.duration)
+        )?;
+
+        for (object, state) in listed {
+            writeln!(out)?;
+            if markdown {
+                writeln!(
+                    out,
+                    "### {}\n\n{} in {}",
+                    object.name,
+                    state,
+                    format_duration(object.duration)
+                )?;
+                if !object.statements.is_empty() {
+                    writeln!(out, "\n```sql")?;
+                    for statement in &object.statements {
+                        writeln!(out, "{};", statement)?;
+                    }
+                    writeln!(out, "```")?;
+                }
+            } else {
+                writeln!(
+                    out,
+                    "* {}: {} in {}",
+                    object.name,
+                    state,
+                    format_duration(object.duration)
+                )?;
+                for statement in &object.statements {
+                    writeln!(out, "    {};", statement.redacted().replace('\n', "\n    "))?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ObjectReport;
+
+    fn object(name: &str, state: ObjectState, group: bool) -> ObjectReport {
+        ObjectReport {
+            name: name.to_string(),
+            depth: 0,
+            state,
+            group,
+            statements: Vec::new(),
+            affected_rows: Vec::new(),
+            check_duration: None,
+            statement_durations: Vec::new(),
+            verify_duration: None,
+            duration: Duration::default(),
+        }
+    }
+
+    #[test]
+    fn groups_are_not_counted() {
+        let report = EnsureReport {
+            run_id: "run".to_string(),
+            objects: vec![
+                object("table foo", ObjectState::Changed, false),
+                object("table bar", ObjectState::Met, false),
+                object("tables", ObjectState::Changed, true),
+            ],
+            ..EnsureReport::default()
+        };
+        let changelog = report.changelog(ChangelogFormat::Text);
+        assert!(
+            changelog.contains(
+                "1 object(s) changed, 0 failed, 0 skipped, 0 not attempted, 1 already met"
+            ),
+            "{}",
+            changelog
+        );
+        assert!(!changelog.contains("* tables:"), "{}", changelog);
+    }
+}
@@ -0,0 +1,205 @@
+use crate::state::Scalar;
+use crate::{CancellationToken, Ensure, EnsureSchema, Sql, SqlTemplate, TemplateContext};
+use log::*;
+use odbc_iter::Handle;
+use problem::prelude::*;
+use std::fmt;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+/// Progress of `ChunkedMeet` reported after each executed chunk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkProgress {
+    /// Number of chunks executed so far.
+    pub chunks: usize,
+    /// Rows affected by the last chunk if reported by the driver.
+    pub affected_rows: Option<i64>,
+    /// Rows affected by all chunks so far as reported by the driver.
+    pub total_rows: i64,
+    pub elapsed: Duration,
+}
+
+/// Function receiving progress of `ChunkedMeet`.
+type ProgressFn = dyn Fn(&str, &ChunkProgress);
+
+/// Step migrating data in chunks: chunk statement (e.g. `UPDATE` or `INSERT .. SELECT` limited to
+/// `{{batch_size}}` rows) is executed repeatedly until it affects no rows, so that large data
+/// migrations do not lock tables or fill the transaction log in one statement.
+///
+/// The step is met when its check query returns zero (e.g. count of rows still to be migrated).
+/// If the driver does not report affected rows the check is run after each chunk instead.
+pub struct ChunkedMeet {
+    name: String,
+    check_query: Sql,
+    chunk: SqlTemplate,
+    batch_size: usize,
+    max_chunks: Option<usize>,
+    pause: Duration,
+    cancellation: Option<CancellationToken>,
+    progress: Option<Box<ProgressFn>>,
+}
+
+impl fmt::Debug for ChunkedMeet {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("ChunkedMeet")
+            .field("name", &self.name)
+            .field("check_query", &self.check_query)
+            .field("chunk", &self.chunk)
+            .field("batch_size", &self.batch_size)
+            .field("max_chunks", &self.max_chunks)
+            .field("pause", &self.pause)
+            .finish()
+    }
+}
+
+impl ChunkedMeet {
+    /// Creates `ChunkedMeet` given name, check query producing one row with number of rows still
+    /// to be migrated and chunk statement template; chunks of 10000 rows are used by default.
+    pub fn new(
+        name: impl Into<String>,
+        check_query: impl Into<Sql>,
+        chunk: impl Into<String>,
+    ) -> ChunkedMeet {
+        ChunkedMeet {
+            name: name.into(),
+            check_query: check_query.into(),
+            chunk: SqlTemplate::new(chunk),
+            batch_size: 10_000,
+            max_chunks: None,
+            pause: Duration::default(),
+            cancellation: None,
+            progress: None,
+        }
+    }
+
+    /// Sets value of `{{batch_size}}` placeholder of the chunk statement.
+    pub fn with_batch_size(mut self, batch_size: usize) -> ChunkedMeet {
+        self.batch_size = batch_size;
+        self
+    }
+
+    /// Fails meet if the migration is not done after given number of chunks; this guards against
+    /// a chunk statement that never stops affecting rows.
+    pub fn with_max_chunks(mut self, max_chunks: usize) -> ChunkedMeet {
+        self.max_chunks = Some(max_chunks);
+        self
+    }
+
+    /// Sleeps given time between chunks to leave room for other workload (e.g. replication).
+    pub fn with_pause(mut self, pause: Duration) -> ChunkedMeet {
+        self.pause = pause;
+        self
+    }
+
+    /// Stops meet with error before next chunk once given token is cancelled; chunks already
+    /// executed stay applied and the migration continues on next run.
+    pub fn with_cancellation(mut self, cancellation: CancellationToken) -> ChunkedMeet {
+        self.cancellation = Some(cancellation);
+        self
+    }
+
+    /// Calls given function with the step name and progress after each chunk.
+    pub fn with_progress(
+        mut self,
+        progress: impl Fn(&str, &ChunkProgress) + 'static,
+    ) -> ChunkedMeet {
+        self.progress = Some(Box::new(progress));
+        self
+    }
+
+    fn remaining(&self, database: &mut Handle<'_>) -> Result<i64, Problem> {
+        database
+            .query::<Scalar>(&self.check_query)?
+            .single()?
+            .0
+            .ok_or_problem("Chunked meet check query returned NULL")
+    }
+}
+
+impl Ensure for ChunkedMeet {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn check(&self, database: &mut Handle<'_>) -> Result<bool, Problem> {
+        Ok(self.remaining(database)? == 0)
+    }
+
+    fn statements(&self) -> Vec<Sql> {
+        let mut context = TemplateContext::new();
+        context.insert("batch_size".to_string(), self.batch_size.to_string());
+        self.chunk.render(&context).into_iter().collect()
+    }
+
+    fn meet(&self, database: &mut Handle<'_>) -> Result<(), Problem> {
+        let mut context = TemplateContext::new();
+        context.insert("batch_size".to_string(), self.batch_size.to_string());
+        let chunk = self.chunk.render(&context)?;
+
+        let started = Instant::now();
+        let mut progress = ChunkProgress {
+            chunks: 0,
+            affected_rows: None,
+            total_rows: 0,
+            elapsed: Duration::default(),
+        };
+        loop {
+            if let Some(max_chunks) = self.max_chunks {
+                if progress.chunks >= max_chunks {
+                    return problem!(
+                        "Chunked meet {} is not done after {} chunks",
+                        self.name,
+                        max_chunks
+                    );
+                }
+            }
+            if self
+                .cancellation
+                .as_ref()
+                .is_some_and(CancellationToken::is_cancelled)
+            {
+                return problem!(
+                    "Chunked meet {} was cancelled after {} chunks",
+                    self.name,
+                    progress.chunks
+                );
+            }
+            if progress.chunks > 0 && self.pause > Duration::default() {
+                sleep(self.pause);
+            }
+
+            let affected_rows = database
+                .query::<()>(&chunk)?
+                .affected_rows()
+                .problem_while_with(|| format!("executing chunk of {}", self.name))?;
+            progress.chunks += 1;
+            progress.affected_rows = affected_rows;
+            progress.total_rows += affected_rows.unwrap_or(0);
+            progress.elapsed = started.elapsed();
+            info!(
+                "[=] Chunk {} of {}: {} rows affected ({} total)",
+                progress.chunks,
+                self.name,
+                affected_rows.map_or_else(|| "unknown".to_string(), |rows| rows.to_string()),
+                progress.total_rows
+            );
+            if let Some(report) = &self.progress {
+                report(&self.name, &progress);
+            }
+
+            let done = match affected_rows {
+                Some(affected_rows) => affected_rows == 0,
+                None => self.remaining(database)? == 0,
+            };
+            if done {
+                return Ok(());
+            }
+        }
+    }
+}
+
+impl From<ChunkedMeet> for EnsureSchema {
+    fn from(step: ChunkedMeet) -> EnsureSchema {
+        EnsureSchema::step(step)
+    }
+}
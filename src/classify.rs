@@ -0,0 +1,139 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::fmt;
+
+lazy_static! {
+    static ref LEADING_COMMENTS: Regex = Regex::new(r"^(?s:\s*(?:--[^\n]*(?:\n|$)|/\*.*?\*/))*\s*").unwrap();
+    static ref DESTRUCTIVE: Regex = Regex::new(
+        r"(?is)^(?:DROP\s|TRUNCATE\s|ALTER\s+TABLE\s+\S+\s+DROP\s|DELETE\s+(?:FROM\s+)?\S+\s*;?\s*$)"
+    )
+    .unwrap();
+    static ref FIRST_KEYWORD: Regex = Regex::new(r"^[A-Za-z]+").unwrap();
+    static ref MUTATING: Regex = Regex::new(
+        r"(?i)\b(?:INSERT|UPDATE|DELETE|MERGE|UPSERT|CREATE|ALTER|DROP|TRUNCATE|GRANT|REVOKE|INTO|NEXTVAL)\b"
+    )
+    .unwrap();
+}
+
+/// Kind of SQL statement as classified by its leading keyword.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StatementKind {
+    /// Data definition: `CREATE`, `ALTER`, `DROP`, `TRUNCATE`, `RENAME`, `COMMENT`.
+    Ddl,
+    /// Data manipulation: `INSERT`, `UPDATE`, `DELETE`, `MERGE`, `UPSERT`, `REPLACE`.
+    Dml,
+    /// Read only query: `SELECT`, `WITH`, `VALUES`, `SHOW`, `DESCRIBE`, `EXPLAIN`.
+    Query,
+    /// Anything else (e.g. `GRANT`, `SET`, `CALL`).
+    Other,
+}
+
+impl fmt::Display for StatementKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StatementKind::Ddl => write!(f, "DDL"),
+            StatementKind::Dml => write!(f, "DML"),
+            StatementKind::Query => write!(f, "query"),
+            StatementKind::Other => write!(f, "other"),
+        }
+    }
+}
+
+/// Statement text without leading whitespace and comments.
+fn statement_body(statement: &str) -> &str {
+    LEADING_COMMENTS
+        .find(statement)
+        .map(|comments| &statement[comments.end()..])
+        .unwrap_or(statement)
+}
+
+/// Returns `true` if statement is `DROP`, `TRUNCATE`, `ALTER TABLE ... DROP` or `DELETE`
+/// without `WHERE` clause.
+pub fn is_destructive(statement: &str) -> bool {
+    DESTRUCTIVE.is_match(statement_body(statement))
+}
+
+/// Classifies statement by its first keyword ignoring leading comments.
+pub fn statement_kind(statement: &str) -> StatementKind {
+    let keyword = FIRST_KEYWORD
+        .find(statement_body(statement))
+        .map(|keyword| keyword.as_str().to_ascii_uppercase());
+    match keyword.as_deref() {
+        Some("CREATE") | Some("ALTER") | Some("DROP") | Some("TRUNCATE") | Some("RENAME")
+        | Some("COMMENT") => StatementKind::Ddl,
+        Some("INSERT") | Some("UPDATE") | Some("DELETE") | Some("MERGE") | Some("UPSERT")
+        | Some("REPLACE") => StatementKind::Dml,
+        Some("SELECT") | Some("WITH") | Some("VALUES") | Some("SHOW") | Some("DESCRIBE")
+        | Some("EXPLAIN") => StatementKind::Query,
+        _ => StatementKind::Other,
+    }
+}
+
+/// Heuristically determines if statement only reads: it has to be a query (see
+/// `statement_kind`) that does not mention data changing keywords anywhere (e.g. data modifying
+/// CTE, `SELECT ... INTO` or `FOR UPDATE`).
+///
+/// Identifiers or literals containing such keywords make the statement not read only.
+pub fn is_read_only(statement: &str) -> bool {
+    statement_kind(statement) == StatementKind::Query && !MUTATING.is_match(statement)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn destructive() {
+        assert!(is_destructive("DROP TABLE foo"));
+        assert!(is_destructive("drop table foo"));
+        assert!(is_destructive("truncate table foo"));
+        assert!(is_destructive("ALTER TABLE foo DROP COLUMN bar"));
+        assert!(is_destructive("DELETE FROM foo;"));
+        assert!(is_destructive("delete foo"));
+        assert!(is_destructive("-- cleanup\n/* old */ DROP VIEW v"));
+
+        assert!(!is_destructive("DELETE FROM foo WHERE id = 1"));
+        assert!(!is_destructive("ALTER TABLE foo ADD bar INT"));
+        assert!(!is_destructive("CREATE TABLE dropped (id INT)"));
+        assert!(!is_destructive("SELECT 'DROP TABLE foo'"));
+        assert!(!is_destructive("-- DROP TABLE foo\nSELECT 1"));
+    }
+
+    #[test]
+    fn kind() {
+        assert_eq!(
+            statement_kind("CREATE TABLE foo (id INT)"),
+            StatementKind::Ddl
+        );
+        assert_eq!(
+            statement_kind("alter table foo add bar INT"),
+            StatementKind::Ddl
+        );
+        assert_eq!(
+            statement_kind("Insert INTO foo VALUES (1)"),
+            StatementKind::Dml
+        );
+        assert_eq!(
+            statement_kind("  with x AS (SELECT 1) SELECT * FROM x"),
+            StatementKind::Query
+        );
+        assert_eq!(
+            statement_kind("GRANT SELECT ON foo TO bar"),
+            StatementKind::Other
+        );
+        assert_eq!(statement_kind(""), StatementKind::Other);
+    }
+
+    #[test]
+    fn kind_ignores_leading_comments() {
+        assert_eq!(
+            statement_kind("-- create table\nSELECT 1"),
+            StatementKind::Query
+        );
+        assert_eq!(
+            statement_kind("/* SELECT */\n/* multi\nline */ UPDATE foo SET bar = 1"),
+            StatementKind::Dml
+        );
+        assert_eq!(statement_kind("-- only comment"), StatementKind::Other);
+    }
+}
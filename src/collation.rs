@@ -0,0 +1,185 @@
+use crate::compare::table_condition;
+use crate::state::quote_literal;
+use crate::{Dialect, EnsureSchema, Sql};
+use odbc_iter::ValueRow;
+use problem::prelude::*;
+
+/// Object whose collation is verified.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CollationTarget {
+    /// Default collation of the current database (SQL Server and MySQL).
+    Database,
+    /// Default collation of a table (MySQL only).
+    Table(String),
+    /// Collation of a column; its type definition (e.g. `VARCHAR(100) NOT NULL`) is needed to
+    /// alter it.
+    Column {
+        table: String,
+        column: String,
+        definition: String,
+    },
+}
+
+/// Verifies that database, table or column uses expected collation (and character set it implies
+/// in MySQL) and alters it if it does not.
+#[derive(Debug, Clone)]
+pub struct CollationCheck {
+    dialect: Dialect,
+    target: CollationTarget,
+    collation: String,
+}
+
+impl CollationCheck {
+    /// Creates `CollationCheck` given dialect, target and expected collation name (e.g.
+    /// `utf8mb4_unicode_ci` or `Latin1_General_CI_AS`).
+    pub fn new(
+        dialect: Dialect,
+        target: CollationTarget,
+        collation: impl Into<String>,
+    ) -> CollationCheck {
+        CollationCheck {
+            dialect,
+            target,
+            collation: collation.into(),
+        }
+    }
+
+    /// Character set implied by MySQL collation name.
+    fn charset(&self) -> &str {
+        self.collation.split('_').next().unwrap_or(&self.collation)
+    }
+
+    /// Query producing single row with current collation; `None` if not supported in the
+    /// dialect.
+    ///
+    /// Postgres database has no default collation name to compare with (`datcollate` is a libc
+    /// locale like `en_US.UTF-8`) so only its columns can be checked.
+    pub fn check_query(&self) -> Option<Sql> {
+        let query = match (&self.target, self.dialect) {
+            (CollationTarget::Database, Dialect::SqlServer) => {
+                "SELECT CONVERT(VARCHAR(128), DATABASEPROPERTYEX(DB_NAME(), 'Collation'))".to_string()
+            }
+            (CollationTarget::Database, Dialect::MySql) => {
+                "SELECT default_collation_name FROM information_schema.schemata WHERE schema_name = DATABASE()".to_string()
+            }
+            (CollationTarget::Table(table), Dialect::MySql) => format!(
+                "SELECT table_collation FROM information_schema.tables WHERE table_schema = DATABASE() AND table_name = {}",
+                quote_literal(table)
+            ),
+            (
+                CollationTarget::Column { table, column, .. },
+                Dialect::SqlServer | Dialect::MySql | Dialect::Postgres | Dialect::Snowflake,
+            ) => format!(
+                "SELECT collation_name FROM information_schema.columns WHERE {} AND UPPER(column_name) = UPPER({})",
+                table_condition(table, self.dialect),
+                quote_literal(column)
+            ),
+            _ => return None,
+        };
+        Some(query.into())
+    }
+
+    /// Statement changing the collation; `None` if not supported in the dialect.
+    pub fn alter_query(&self) -> Option<Sql> {
+        let query = match (&self.target, self.dialect) {
+            (CollationTarget::Database, Dialect::SqlServer) => {
+                format!("ALTER DATABASE CURRENT COLLATE {}", self.collation)
+            }
+            (CollationTarget::Database, Dialect::MySql) => format!(
+                "ALTER DATABASE CHARACTER SET {} COLLATE {}",
+                self.charset(),
+                self.collation
+            ),
+            (CollationTarget::Table(table), Dialect::MySql) => format!(
+                "ALTER TABLE {} CONVERT TO CHARACTER SET {} COLLATE {}",
+                table,
+                self.charset(),
+                self.collation
+            ),
+            (
+                CollationTarget::Column {
+                    table,
+                    column,
+                    definition,
+                },
+                Dialect::SqlServer,
+            ) => format!(
+                "ALTER TABLE {} ALTER COLUMN {} {} COLLATE {}",
+                table, column, definition, self.collation
+            ),
+            (
+                CollationTarget::Column {
+                    table,
+                    column,
+                    definition,
+                },
+                Dialect::MySql,
+            ) => format!(
+                "ALTER TABLE {} MODIFY {} {} CHARACTER SET {} COLLATE {}",
+                table,
+                column,
+                definition,
+                self.charset(),
+                self.collation
+            ),
+            (
+                CollationTarget::Column {
+                    table,
+                    column,
+                    definition,
+                },
+                Dialect::Postgres,
+            ) => format!(
+                "ALTER TABLE {} ALTER COLUMN {} TYPE {} COLLATE {}",
+                table,
+                column,
+                definition,
+                self.dialect.quote_identifier(&self.collation)
+            ),
+            _ => return None,
+        };
+        Some(query.into())
+    }
+
+    /// `EnsureSchema` altering the collation if it differs from expected one (compared case
+    /// insensitively); if it cannot be altered in the dialect the check fails instead.
+    pub fn ensure_schema(&self) -> Result<EnsureSchema, Problem> {
+        let check_query = self.check_query().ok_or_else(|| {
+            Problem::from_error(format!(
+                "Collation of {:?} cannot be checked in {:?} dialect",
+                self.target, self.dialect
+            ))
+        })?;
+        let check = self.clone();
+        let alter = self.alter_query();
+        Ok(EnsureSchema::new(
+            format!("collation {} of {:?}", self.collation, self.target),
+            check_query,
+            move |rows| {
+                let row: ValueRow = rows.single()?;
+                let current = row
+                    .into_iter()
+                    .next()
+                    .flatten()
+                    .map(|value| value.to_string());
+                if current
+                    .as_deref()
+                    .is_some_and(|current| current.eq_ignore_ascii_case(&check.collation))
+                {
+                    return Ok(vec![]);
+                }
+                match &alter {
+                    Some(alter) => Ok(vec![alter.clone()]),
+                    None => problem!(
+                        "Collation of {:?} is {} but {} is expected and it cannot be altered in {:?} dialect",
+                        check.target,
+                        current.as_deref().unwrap_or("default"),
+                        check.collation,
+                        check.dialect
+                    ),
+                }
+            },
+        )
+        .with_declared_meet_queries(self.alter_query().into_iter().collect()))
+    }
+}
@@ -0,0 +1,203 @@
+use crate::state::quote_literal;
+use crate::{Dialect, EnsureSchema, Sql};
+use lazy_static::lazy_static;
+use problem::prelude::*;
+use regex::Regex;
+
+lazy_static! {
+    static ref TRAILING_CAST: Regex = Regex::new(r"::[\w\s\[\]]+$").unwrap();
+}
+
+/// Default value of a column checked against the catalog and set when it has drifted; existence
+/// checks do not notice drifted defaults while they break application inserts.
+///
+/// In SQL Server defaults are named constraints: the constraint is dropped and added again (named
+/// `DF_<table>_<column>` unless a name is given), which requires
+/// `EnsureOptions::allow_destructive` as any `ALTER TABLE .. DROP` does. Constraint of different
+/// name than the given one is replaced too.
+///
+/// Catalog representations are normalized before comparison (enclosing parentheses, Postgres
+/// casts and quotes of literals are removed) so the default should be written as simply as
+/// possible (e.g. `0` or `'new'`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DefaultDef {
+    pub table: String,
+    pub column: String,
+    /// Default expression; `None` if the column should have no default.
+    pub default: Option<String>,
+    /// SQL Server constraint name.
+    pub constraint_name: Option<String>,
+}
+
+impl DefaultDef {
+    /// Creates `DefaultDef` given table, column and default expression.
+    pub fn new(
+        table: impl Into<String>,
+        column: impl Into<String>,
+        default: impl Into<String>,
+    ) -> DefaultDef {
+        DefaultDef {
+            table: table.into(),
+            column: column.into(),
+            default: Some(default.into()),
+            constraint_name: None,
+        }
+    }
+
+    /// Creates `DefaultDef` ensuring the column has no default.
+    pub fn none(table: impl Into<String>, column: impl Into<String>) -> DefaultDef {
+        DefaultDef {
+            default: None,
+            ..DefaultDef::new(table, column, "")
+        }
+    }
+
+    /// Sets name of SQL Server default constraint.
+    pub fn with_constraint_name(mut self, name: impl Into<String>) -> DefaultDef {
+        self.constraint_name = Some(name.into());
+        self
+    }
+
+    fn constraint(&self) -> String {
+        self.constraint_name
+            .clone()
+            .unwrap_or_else(|| format!("DF_{}_{}", self.table, self.column).replace('.', "_"))
+    }
+
+    /// Query producing single row with constraint name (SQL Server only) and current default of
+    /// the column; no rows if the column (or SQL Server default constraint) does not exist.
+    pub fn check_query(&self, dialect: Dialect) -> Sql {
+        match dialect {
+            Dialect::SqlServer => format!(
+                "SELECT dc.name, dc.definition FROM sys.default_constraints dc JOIN sys.columns c ON c.object_id = dc.parent_object_id AND c.column_id = dc.parent_column_id WHERE dc.parent_object_id = OBJECT_ID({}) AND c.name = {}",
+                quote_literal(&self.table),
+                quote_literal(&self.column)
+            ),
+            _ => format!(
+                "SELECT NULL, column_default FROM information_schema.columns WHERE UPPER(table_name) = UPPER({}) AND UPPER(column_name) = UPPER({})",
+                quote_literal(&self.table),
+                quote_literal(&self.column)
+            ),
+        }
+        .into()
+    }
+
+    /// Statements changing default of the column given name of the existing SQL Server default
+    /// constraint.
+    pub fn set_queries(&self, dialect: Dialect, existing_constraint: Option<&str>) -> Vec<Sql> {
+        let mut queries = Vec::new();
+        match dialect {
+            Dialect::SqlServer => {
+                if let Some(constraint) = existing_constraint {
+                    queries.push(format!(
+                        "ALTER TABLE {} DROP CONSTRAINT {}",
+                        self.table, constraint
+                    ));
+                }
+                if let Some(default) = &self.default {
+                    queries.push(format!(
+                        "ALTER TABLE {} ADD CONSTRAINT {} DEFAULT {} FOR {}",
+                        self.table,
+                        self.constraint(),
+                        default,
+                        self.column
+                    ));
+                }
+            }
+            _ => queries.push(match &self.default {
+                Some(default) => format!(
+                    "ALTER TABLE {} ALTER COLUMN {} SET DEFAULT {}",
+                    self.table, self.column, default
+                ),
+                None => format!(
+                    "ALTER TABLE {} ALTER COLUMN {} DROP DEFAULT",
+                    self.table, self.column
+                ),
+            }),
+        }
+        queries.into_iter().map(Sql::from).collect()
+    }
+
+    /// `EnsureSchema` setting default of the column if it differs from the expected one.
+    pub fn ensure_schema(&self, dialect: Dialect) -> EnsureSchema {
+        let default = self.clone();
+        EnsureSchema::with_inspection(
+            format!("default of {}.{}", self.table, self.column),
+            self.check_query(dialect),
+            move |rows, _database| {
+                let (constraint, current) = match rows.into_iter().next() {
+                    Some(row) => {
+                        let mut row = row
+                            .into_iter()
+                            .map(|value| value.map(|value| value.to_string()));
+                        (row.next().flatten(), row.next().flatten())
+                    }
+                    None if dialect == Dialect::SqlServer => (None, None),
+                    None => {
+                        return problem!(
+                            "Column {} of {} does not exist",
+                            default.column,
+                            default.table
+                        )
+                    }
+                };
+                let same_default = current.as_deref().map(normalize_default)
+                    == default.default.as_deref().map(normalize_default);
+                let same_constraint = dialect != Dialect::SqlServer
+                    || default.default.is_none()
+                    || default.constraint_name.is_none()
+                    || constraint.as_deref() == default.constraint_name.as_deref();
+                if same_default && same_constraint {
+                    Ok(vec![])
+                } else {
+                    Ok(default.set_queries(dialect, constraint.as_deref()))
+                }
+            },
+        )
+        .with_declared_meet_queries(self.set_queries(dialect, None))
+    }
+}
+
+/// Default expression without enclosing parentheses, trailing casts, quotes and whitespace in
+/// lower case for comparison.
+fn normalize_default(default: &str) -> String {
+    let mut default = default.trim().to_string();
+    loop {
+        let stripped = TRAILING_CAST.replace(&default, "").trim().to_string();
+        let next = stripped
+            .strip_prefix('(')
+            .and_then(|inner| inner.strip_suffix(')'))
+            .filter(|inner| is_balanced(inner))
+            .map(|inner| inner.trim().to_string())
+            .unwrap_or(stripped);
+        if next == default {
+            break;
+        }
+        default = next;
+    }
+    let default = default
+        .strip_prefix('\'')
+        .and_then(|inner| inner.strip_suffix('\''))
+        .unwrap_or(&default);
+    default
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_lowercase()
+}
+
+/// Returns `true` if parentheses of the expression are balanced so that outer ones can be removed.
+fn is_balanced(expression: &str) -> bool {
+    let mut depth = 0i32;
+    for c in expression.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ => (),
+        }
+        if depth < 0 {
+            return false;
+        }
+    }
+    depth == 0
+}
@@ -0,0 +1,235 @@
+use crate::state::quote_literal;
+use crate::Dialect;
+use odbc_iter::{Handle, ValueRow};
+use problem::prelude::*;
+use std::fmt;
+
+/// Column of a live table as listed by `information_schema.columns`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnInfo {
+    pub name: String,
+    pub data_type: String,
+    pub max_length: Option<String>,
+    pub nullable: bool,
+}
+
+impl fmt::Display for ColumnInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.name, self.data_type)?;
+        if let Some(max_length) = &self.max_length {
+            write!(f, "({})", max_length)?;
+        }
+        if !self.nullable {
+            write!(f, " NOT NULL")?;
+        }
+        Ok(())
+    }
+}
+
+/// `information_schema` condition selecting given table; table qualified with schema (e.g.
+/// `dbo.foo`) is looked up in that schema, otherwise in the current schema of the dialect (any
+/// schema if not known).
+pub(crate) fn table_condition(table: &str, dialect: Dialect) -> String {
+    let (schema, table) = match table.rsplit_once('.') {
+        Some((schema, table)) => (Some(schema), table),
+        None => (None, table),
+    };
+    let mut condition = format!("UPPER(table_name) = UPPER({})", quote_literal(table));
+    match (schema, dialect.current_schema()) {
+        (Some(schema), _) => condition.push_str(&format!(
+            " AND UPPER(table_schema) = UPPER({})",
+            quote_literal(schema)
+        )),
+        (None, Some(current)) => condition.push_str(&format!(" AND table_schema = {}", current)),
+        (None, None) => (),
+    }
+    condition
+}
+
+/// Query listing columns of given table in ordinal order as read by `column_from_row`.
+pub(crate) fn columns_query(table: &str, dialect: Dialect) -> String {
+    format!(
+        "SELECT column_name, data_type, character_maximum_length, is_nullable FROM information_schema.columns WHERE {} ORDER BY ordinal_position",
+        table_condition(table, dialect)
+    )
+}
+
+pub(crate) fn column_from_row(row: ValueRow) -> Result<ColumnInfo, Problem> {
+    let mut row = row
+        .into_iter()
+        .map(|value| value.map(|value| value.to_string()));
+    Ok(ColumnInfo {
+        name: row.next().flatten().ok_or_problem("Column name is NULL")?,
+        data_type: row.next().flatten().unwrap_or_default(),
+        max_length: row.next().flatten(),
+        nullable: row
+            .next()
+            .flatten()
+            .is_some_and(|nullable| nullable.eq_ignore_ascii_case("YES")),
+    })
+}
+
+/// Lists columns of given table in ordinal order; `None` if the table does not exist.
+///
+/// Unqualified table is looked up in the current schema of the dialect.
+pub fn table_columns(
+    database: &mut Handle<'_>,
+    dialect: Dialect,
+    table: &str,
+) -> Result<Option<Vec<ColumnInfo>>, Problem> {
+    let columns = database
+        .query::<ValueRow>(&columns_query(table, dialect))?
+        .map(|row| column_from_row(row?))
+        .collect::<Result<Vec<_>, Problem>>()?;
+    Ok(if columns.is_empty() {
+        None
+    } else {
+        Some(columns)
+    })
+}
+
+/// Difference of a table between two databases.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TableDiff {
+    /// Table exists only in the right database.
+    MissingLeft(String),
+    /// Table exists only in the left database.
+    MissingRight(String),
+    /// Table exists in both databases but its columns differ.
+    Columns {
+        table: String,
+        only_left: Vec<ColumnInfo>,
+        only_right: Vec<ColumnInfo>,
+        /// Columns with the same name but different type or nullability (left, right).
+        differing: Vec<(ColumnInfo, ColumnInfo)>,
+    },
+}
+
+/// Structured difference of live tables between two databases (e.g. staging and production).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SchemaDiff {
+    pub tables: Vec<TableDiff>,
+}
+
+impl SchemaDiff {
+    /// Returns `true` if no differences were found.
+    pub fn is_empty(&self) -> bool {
+        self.tables.is_empty()
+    }
+}
+
+impl fmt::Display for SchemaDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for diff in &self.tables {
+            match diff {
+                TableDiff::MissingLeft(table) => writeln!(f, "{}: missing in left", table)?,
+                TableDiff::MissingRight(table) => writeln!(f, "{}: missing in right", table)?,
+                TableDiff::Columns {
+                    table,
+                    only_left,
+                    only_right,
+                    differing,
+                } => {
+                    writeln!(f, "{}:", table)?;
+                    for column in only_left {
+                        writeln!(f, "  - {} (left only)", column)?;
+                    }
+                    for column in only_right {
+                        writeln!(f, "  + {} (right only)", column)?;
+                    }
+                    for (left, right) in differing {
+                        writeln!(f, "  ~ {} -> {}", left, right)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Compares columns of given tables between two databases.
+///
+/// Only information available in `information_schema.columns` is compared; tables missing in
+/// both databases are not reported; unqualified tables are looked up in the current schema of
+/// the dialect.
+pub fn compare_tables(
+    left: &mut Handle<'_>,
+    right: &mut Handle<'_>,
+    dialect: Dialect,
+    tables: &[&str],
+) -> Result<SchemaDiff, Problem> {
+    let mut diff = SchemaDiff::default();
+    for table in tables {
+        let left_columns = table_columns(left, dialect, table)
+            .problem_while_with(|| format!("listing columns of {} in left database", table))?;
+        let right_columns = table_columns(right, dialect, table)
+            .problem_while_with(|| format!("listing columns of {} in right database", table))?;
+        match (left_columns, right_columns) {
+            (None, None) => (),
+            (None, Some(_)) => diff.tables.push(TableDiff::MissingLeft(table.to_string())),
+            (Some(_), None) => diff.tables.push(TableDiff::MissingRight(table.to_string())),
+            (Some(left_columns), Some(right_columns)) => {
+                let find = |columns: &[ColumnInfo], name: &str| {
+                    columns
+                        .iter()
+                        .find(|column| column.name.eq_ignore_ascii_case(name))
+                        .cloned()
+                };
+                let only_left: Vec<_> = left_columns
+                    .iter()
+                    .filter(|column| find(&right_columns, &column.name).is_none())
+                    .cloned()
+                    .collect();
+                let only_right: Vec<_> = right_columns
+                    .iter()
+                    .filter(|column| find(&left_columns, &column.name).is_none())
+                    .cloned()
+                    .collect();
+                let differing: Vec<_> = left_columns
+                    .iter()
+                    .filter_map(|column| {
+                        let other = find(&right_columns, &column.name)?;
+                        if other.data_type.eq_ignore_ascii_case(&column.data_type)
+                            && other.max_length == column.max_length
+                            && other.nullable == column.nullable
+                        {
+                            None
+                        } else {
+                            Some((column.clone(), other))
+                        }
+                    })
+                    .collect();
+                if !(only_left.is_empty() && only_right.is_empty() && differing.is_empty()) {
+                    diff.tables.push(TableDiff::Columns {
+                        table: table.to_string(),
+                        only_left,
+                        only_right,
+                        differing,
+                    });
+                }
+            }
+        }
+    }
+    Ok(diff)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn table_condition_filters_schema() {
+        assert_eq!(
+            table_condition("foo", Dialect::MySql),
+            "UPPER(table_name) = UPPER('foo') AND table_schema = DATABASE()"
+        );
+        assert_eq!(
+            table_condition("dbo.foo", Dialect::SqlServer),
+            "UPPER(table_name) = UPPER('foo') AND UPPER(table_schema) = UPPER('dbo')"
+        );
+        assert_eq!(
+            table_condition("foo", Dialect::Generic),
+            "UPPER(table_name) = UPPER('foo')"
+        );
+    }
+}
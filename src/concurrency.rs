@@ -0,0 +1,59 @@
+use crate::{EnsureSchema, Run};
+use log::*;
+use problem::prelude::*;
+
+/// If meet statements of an object can run while application is using the database.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Concurrency {
+    /// Safe to meet with application traffic (e.g. adding nullable column or creating view).
+    Online,
+    /// Requires exclusive access (e.g. table rebuild or long blocking lock).
+    Exclusive,
+}
+
+impl EnsureSchema {
+    /// Declares if meet statements of this object are safe to run with application traffic;
+    /// objects are `Concurrency::Online` by default.
+    ///
+    /// Exclusive meets are logged with warning unless `EnsureOptions::maintenance_window` is set
+    /// (or refused with `EnsureOptions::require_maintenance_window`) and exclusive members of
+    /// independent groups are ensured before the other members. Concurrency is part of the
+    /// fingerprint.
+    pub fn with_concurrency(mut self, concurrency: Concurrency) -> EnsureSchema {
+        self.concurrency = concurrency;
+        self
+    }
+
+    /// Same as `with_concurrency(Concurrency::Exclusive)`.
+    pub fn exclusive(self) -> EnsureSchema {
+        self.with_concurrency(Concurrency::Exclusive)
+    }
+
+    pub fn concurrency(&self) -> Concurrency {
+        self.concurrency
+    }
+}
+
+impl Run<'_, '_> {
+    /// Fails or warns if object requiring exclusive access is met outside maintenance window.
+    pub(crate) fn check_concurrency(
+        &self,
+        name: &str,
+        concurrency: Concurrency,
+    ) -> Result<(), Problem> {
+        if concurrency == Concurrency::Online || self.options.maintenance_window {
+            return Ok(());
+        }
+        if self.options.require_maintenance_window {
+            return problem!(
+                "Meeting '{}' requires exclusive access and maintenance_window option",
+                name
+            );
+        }
+        warn!(
+            "[exclusive] Meeting '{}' outside of maintenance window",
+            name
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,80 @@
+use crate::state::{first_row, value_to_i64};
+use crate::{EnsureSchema, Sql};
+use problem::prelude::*;
+
+impl EnsureSchema {
+    /// Creates `EnsureSchema` given name, check query and meet queries that are run when the
+    /// check query produces no rows.
+    pub fn if_missing(
+        name: impl Into<String>,
+        check_query: impl Into<Sql>,
+        meet_queries: impl IntoIterator<Item = impl Into<Sql>>,
+    ) -> EnsureSchema {
+        let meet_queries = meet_queries
+            .into_iter()
+            .map(Into::into)
+            .collect::<Vec<Sql>>();
+        let declared = meet_queries.clone();
+        EnsureSchema::new(name, check_query, move |rows| {
+            Ok(if first_row(rows)?.is_none() {
+                meet_queries.clone()
+            } else {
+                vec![]
+            })
+        })
+        .with_declared_meet_queries(declared)
+    }
+
+    /// Creates `EnsureSchema` given name, check query and meet queries that are run when first
+    /// column of the first row produced by the check query (e.g. schema version or UNIX
+    /// timestamp) is lower than `current`, `NULL` or there are no rows.
+    pub fn if_outdated(
+        name: impl Into<String>,
+        check_query: impl Into<Sql>,
+        current: i64,
+        meet_queries: impl IntoIterator<Item = impl Into<Sql>>,
+    ) -> EnsureSchema {
+        let meet_queries = meet_queries
+            .into_iter()
+            .map(Into::into)
+            .collect::<Vec<Sql>>();
+        let declared = meet_queries.clone();
+        EnsureSchema::new(name, check_query, move |rows| {
+            let value = first_row(rows)?.and_then(|row| row.into_iter().next().flatten());
+            let version = match value {
+                Some(value) => Some(value_to_i64(Some(value.clone())).ok_or_else(|| {
+                    Problem::from_error(format!("Not an integer version: {}", value))
+                })?),
+                None => None,
+            };
+            Ok(if version.is_some_and(|version| version >= current) {
+                vec![]
+            } else {
+                meet_queries.clone()
+            })
+        })
+        .with_declared_meet_queries(declared)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn meet_queries_are_declared() {
+        let schema = EnsureSchema::if_missing(
+            "table foo",
+            "SELECT 1 FROM information_schema.tables WHERE table_name = 'foo'",
+            vec!["CREATE TABLE foo (id INT)"],
+        );
+        assert_eq!(
+            schema
+                .declared_meet_queries()
+                .iter()
+                .map(Sql::as_str)
+                .collect::<Vec<_>>(),
+            vec!["CREATE TABLE foo (id INT)"]
+        );
+    }
+}
@@ -0,0 +1,134 @@
+use crate::state::quote_literal;
+use crate::{
+    Dialect, EnsureOptions, EnsureReport, EnsureSchema, RetryPolicy, SchemaState, SchemaStateError,
+    Sql,
+};
+use log::*;
+use problem::prelude::*;
+
+/// Database created if it does not exist; this has to be checked and met from server level
+/// database (e.g. `master` or `postgres`) as the target database cannot be connected to before
+/// it exists (see `EnsureSchema::ensure_with_bootstrap`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DatabaseDef {
+    pub name: String,
+    /// Options following database name in `CREATE DATABASE` (e.g. `COLLATE Latin1_General_CI_AS`
+    /// or `ENCODING 'UTF8'`).
+    pub options: Option<String>,
+}
+
+impl DatabaseDef {
+    pub fn new(name: impl Into<String>) -> DatabaseDef {
+        DatabaseDef {
+            name: name.into(),
+            options: None,
+        }
+    }
+
+    /// Sets options of `CREATE DATABASE`.
+    pub fn with_options(mut self, options: impl Into<String>) -> DatabaseDef {
+        self.options = Some(options.into());
+        self
+    }
+
+    /// Query producing single row with count of databases of this name; `None` if not supported
+    /// in the dialect.
+    pub fn check_query(&self, dialect: Dialect) -> Option<Sql> {
+        let name = quote_literal(&self.name);
+        let query = match dialect {
+            Dialect::SqlServer => {
+                format!("SELECT COUNT(*) FROM sys.databases WHERE name = {}", name)
+            }
+            Dialect::Postgres => {
+                format!("SELECT COUNT(*) FROM pg_database WHERE datname = {}", name)
+            }
+            Dialect::MySql => format!(
+                "SELECT COUNT(*) FROM information_schema.schemata WHERE schema_name = {}",
+                name
+            ),
+            _ => return None,
+        };
+        Some(query.into())
+    }
+
+    /// `CREATE DATABASE` statement.
+    pub fn create_query(&self) -> Sql {
+        match &self.options {
+            Some(options) => format!("CREATE DATABASE {} {}", self.name, options),
+            None => format!("CREATE DATABASE {}", self.name),
+        }
+        .into()
+    }
+
+    /// `EnsureSchema` creating the database if it does not exist; it needs to be ensured with
+    /// connection to server level database.
+    pub fn ensure_schema(&self, dialect: Dialect) -> Result<EnsureSchema, Problem> {
+        let check_query = self.check_query(dialect).ok_or_else(|| {
+            Problem::from_error(format!(
+                "Database {} cannot be checked in {:?} dialect",
+                self.name, dialect
+            ))
+        })?;
+        Ok(EnsureSchema::with_count_check(
+            format!("database {}", self.name),
+            check_query,
+            vec![self.create_query()],
+        ))
+    }
+}
+
+impl EnsureSchema {
+    /// Ensures given database exists connecting with `server_connection_string` to server level
+    /// database and then connects with `connection_string` to ensure the object (see
+    /// `ensure_with_connection_string`).
+    ///
+    /// State, audit, report and lock tables, required privileges, keep-alive connection and
+    /// approved plan of `options` only apply to the target database. In dry run the object is not
+    /// checked if the database would be created. Bootstrap objects are reported before the object
+    /// ones.
+    pub fn ensure_with_bootstrap(
+        &self,
+        database: &DatabaseDef,
+        server_connection_string: &str,
+        connection_string: &str,
+        options: &EnsureOptions,
+        retry: &RetryPolicy,
+        report: &mut EnsureReport,
+    ) -> Result<SchemaState, SchemaStateError> {
+        let bootstrap = database
+            .ensure_schema(options.dialect)
+            .map_err(|err| SchemaStateError::CheckError(database.name.clone(), err))?;
+        let mut bootstrap_options = options.clone();
+        bootstrap_options.state_table = None;
+        bootstrap_options.audit_table = None;
+        bootstrap_options.report_table = None;
+        bootstrap_options.lock_table = None;
+        bootstrap_options.required_privileges = Vec::new();
+        bootstrap_options.keep_alive = None;
+        bootstrap_options.approved_plan = None;
+        bootstrap_options.resume = None;
+
+        let mut bootstrap_report = EnsureReport::default();
+        let bootstrapped = bootstrap.ensure_with_connection_string(
+            server_connection_string,
+            &bootstrap_options,
+            retry,
+            &mut bootstrap_report,
+        )?;
+        if options.dry_run && bootstrap_report.has_changes() {
+            warn!(
+                "Database {} does not exist; not checking {} in dry run",
+                database.name, self.name
+            );
+            *report = bootstrap_report;
+            return Ok(bootstrapped);
+        }
+
+        let result = self.ensure_with_connection_string(connection_string, options, retry, report);
+        report.objects.splice(0..0, bootstrap_report.objects);
+        match result? {
+            SchemaState::Ok if bootstrapped == SchemaState::Ok => Ok(SchemaState::Ok),
+            _ => Ok(SchemaState::Changed),
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use crate::state::quote_literal;
+use crate::Sql;
+use lazy_static::lazy_static;
+use regex::Regex;
+
+lazy_static! {
+    /// Statements SQL Server requires to be the first in a batch.
+    static ref SQL_SERVER_BATCH_FIRST: Regex =
+        Regex::new(r"(?i)^\s*(?:CREATE|ALTER|CREATE\s+OR\s+ALTER)\s+(?:VIEW|PROC|PROCEDURE|FUNCTION|TRIGGER|SCHEMA|DEFAULT|RULE)\b").unwrap();
+    /// Statements Postgres refuses to execute in the implicit transaction of a batch.
+    static ref POSTGRES_NO_TRANSACTION: Regex =
+        Regex::new(r"(?i)^\s*(?:(?:CREATE|DROP)\s+(?:DATABASE|TABLESPACE)|VACUUM|CREATE\s+(?:UNIQUE\s+)?INDEX\s+CONCURRENTLY|REINDEX\b.*\bCONCURRENTLY)\b").unwrap();
+}
+
+/// SQL dialect of the connected database used where portable SQL is not available.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Dialect {
+    /// Only portable SQL is used; dialect specific features are not available.
+    #[default]
+    Generic,
+    SqlServer,
+    Postgres,
+    MySql,
+    Hive,
+    MonetDb,
+    Snowflake,
+}
+
+impl Dialect {
+    /// Quotes identifier (e.g. table or column name) so it is used verbatim.
+    pub fn quote_identifier(&self, identifier: &str) -> String {
+        match self {
+            Dialect::SqlServer => format!("[{}]", identifier.replace(']', "]]")),
+            Dialect::MySql | Dialect::Hive => format!("`{}`", identifier.replace('`', "``")),
+            _ => format!("\"{}\"", identifier.replace('"', "\"\"")),
+        }
+    }
+
+    /// SQL expression evaluating to the current schema (database in MySQL); `None` if not known
+    /// in the dialect.
+    pub fn current_schema(&self) -> Option<&'static str> {
+        match self {
+            Dialect::SqlServer => Some("SCHEMA_NAME()"),
+            Dialect::Postgres => Some("current_schema()"),
+            Dialect::MySql => Some("DATABASE()"),
+            Dialect::Snowflake => Some("CURRENT_SCHEMA()"),
+            Dialect::MonetDb => Some("CURRENT_SCHEMA"),
+            Dialect::Generic | Dialect::Hive => None,
+        }
+    }
+
+    /// Returns `true` if views and procedures can be redefined with `CREATE OR REPLACE`.
+    pub fn supports_create_or_replace(&self) -> bool {
+        match self {
+            Dialect::Postgres | Dialect::MySql | Dialect::Hive | Dialect::Snowflake => true,
+            Dialect::Generic | Dialect::SqlServer | Dialect::MonetDb => false,
+        }
+    }
+
+    /// Concatenates statements into a single multi-statement batch or returns `None` if dialect
+    /// does not support batches or some of the statements cannot be executed in one.
+    pub fn batch(&self, statements: &[Sql]) -> Option<Sql> {
+        let restricted: &Regex = match self {
+            Dialect::SqlServer => &SQL_SERVER_BATCH_FIRST,
+            Dialect::Postgres => &POSTGRES_NO_TRANSACTION,
+            Dialect::MonetDb => return Some(join_batch(statements)),
+            Dialect::Generic | Dialect::MySql | Dialect::Hive | Dialect::Snowflake => return None,
+        };
+        if statements
+            .iter()
+            .any(|statement| restricted.is_match(statement))
+        {
+            return None;
+        }
+        Some(join_batch(statements))
+    }
+
+    /// Statement as written to SQL script with terminator that keeps statements containing `;`
+    /// (e.g. trigger or procedure bodies) intact: MySQL uses `DELIMITER` and SQL Server `GO`
+    /// batch separators.
+    pub fn script_statement(&self, statement: &Sql) -> String {
+        match self {
+            Dialect::MySql if statement.contains(';') => {
+                format!("DELIMITER $$\n{}$$\nDELIMITER ;", statement)
+            }
+            Dialect::SqlServer => format!("{}\nGO", statement),
+            _ => format!("{};", statement),
+        }
+    }
+
+    /// Statement refreshing optimizer statistics of given table; `None` if statistics are
+    /// maintained automatically or cannot be refreshed in the dialect.
+    pub fn analyze_query(&self, table: &str) -> Option<Sql> {
+        let query = match self {
+            Dialect::SqlServer => format!("UPDATE STATISTICS {}", table),
+            Dialect::Postgres | Dialect::MonetDb => format!("ANALYZE {}", table),
+            Dialect::MySql => format!("ANALYZE TABLE {}", table),
+            Dialect::Hive => format!("ANALYZE TABLE {} COMPUTE STATISTICS", table),
+            Dialect::Generic | Dialect::Snowflake => return None,
+        };
+        Some(query.into())
+    }
+
+    /// Column type for unbounded text (e.g. serialized documents).
+    pub fn text_type(&self) -> &'static str {
+        match self {
+            Dialect::SqlServer => "NVARCHAR(MAX)",
+            Dialect::Postgres => "TEXT",
+            Dialect::MySql => "LONGTEXT",
+            Dialect::Hive => "STRING",
+            Dialect::Snowflake => "VARCHAR",
+            Dialect::Generic | Dialect::MonetDb => "CLOB",
+        }
+    }
+
+    /// Query producing a row for each table (or view) of given name; unquoted names are matched
+    /// case insensitively.
+    pub fn table_exists_query(&self, table: &str) -> Sql {
+        match self {
+            Dialect::Hive => format!("SHOW TABLES LIKE {}", quote_literal(table)),
+            Dialect::Snowflake => format!("SHOW TERSE TABLES LIKE {}", quote_literal(table)),
+            _ => format!(
+                "SELECT table_name FROM information_schema.tables WHERE UPPER(table_name) = UPPER({})",
+                quote_literal(table)
+            ),
+        }
+        .into()
+    }
+
+    /// Query producing a row for each view of given name.
+    pub fn view_exists_query(&self, view: &str) -> Sql {
+        match self {
+            Dialect::Snowflake => format!("SHOW TERSE VIEWS LIKE {}", quote_literal(view)),
+            Dialect::Hive => format!("SHOW VIEWS LIKE {}", quote_literal(view)),
+            _ => format!(
+                "SELECT table_name FROM information_schema.views WHERE UPPER(table_name) = UPPER({})",
+                quote_literal(view)
+            ),
+        }
+        .into()
+    }
+
+    /// Query producing a row for each procedure of given name.
+    pub fn procedure_exists_query(&self, procedure: &str) -> Sql {
+        match self {
+            Dialect::Snowflake => format!("SHOW PROCEDURES LIKE {}", quote_literal(procedure)),
+            _ => format!(
+                "SELECT routine_name FROM information_schema.routines WHERE routine_type = 'PROCEDURE' AND UPPER(routine_name) = UPPER({})",
+                quote_literal(procedure)
+            ),
+        }
+        .into()
+    }
+}
+
+fn join_batch(statements: &[Sql]) -> Sql {
+    let text = statements
+        .iter()
+        .map(|statement| statement.as_str())
+        .collect::<Vec<_>>()
+        .join(";\n");
+    let batch = Sql::from(text);
+    if statements.iter().any(Sql::is_sensitive) {
+        batch.sensitive()
+    } else {
+        batch
+    }
+}
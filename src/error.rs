@@ -0,0 +1,67 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+
+lazy_static! {
+    static ref SQLSTATE: Regex = Regex::new(r"\bState: ([0-9A-Z]{5})\b").unwrap();
+}
+
+/// Category of failure used to decide between retrying, alerting and aborting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    /// Connection to the database failed or was lost (including timeouts).
+    Connectivity,
+    /// Connected principal lacks permission.
+    Permission,
+    /// Statement was rejected by the database as invalid (syntax or unknown object).
+    Syntax,
+    /// Value could not be converted (e.g. unexpected check query result).
+    Conversion,
+    /// Object was not met after its meet queries were executed.
+    VerificationFailed,
+    /// Requirement of the object has failed.
+    DependencyFailed,
+    /// Lock could not be acquired or was lost.
+    Lock,
+    /// Data invariant checked by `AssertStep` does not hold.
+    AssertionFailed,
+    /// Time budget of the run was exceeded.
+    BudgetExceeded,
+    Other,
+}
+
+/// Extracts first SQLSTATE from ODBC diagnostic record included in error message.
+pub fn sqlstate(message: &str) -> Option<&str> {
+    SQLSTATE
+        .captures(message)
+        .and_then(|captures| captures.get(1))
+        .map(|state| state.as_str())
+}
+
+/// Classifies failure given its message.
+pub(crate) fn classify(message: &str) -> ErrorKind {
+    let lower = message.to_lowercase();
+    if let Some(state) = sqlstate(message) {
+        let class = &state[..2];
+        if class == "08" || state.starts_with("HYT") {
+            return ErrorKind::Connectivity;
+        }
+        if class == "28" || state == "42501" || (class == "42" && lower.contains("permission")) {
+            return ErrorKind::Permission;
+        }
+        if class == "42" || state == "37000" {
+            return ErrorKind::Syntax;
+        }
+        if class == "22" || state == "07006" {
+            return ErrorKind::Conversion;
+        }
+    }
+    if lower.contains("verification failed") {
+        ErrorKind::VerificationFailed
+    } else if lower.contains("permission") || lower.contains("missing privileges") {
+        ErrorKind::Permission
+    } else if lower.contains("convert") || lower.contains("conversion") {
+        ErrorKind::Conversion
+    } else {
+        ErrorKind::Other
+    }
+}
@@ -0,0 +1,195 @@
+use crate::state::{quote_literal, value_to_i64};
+use crate::{split_script, Dialect, EnsureSchema, Sql};
+use odbc_iter::{Handle, ValueRow};
+use problem::prelude::*;
+
+/// Default name of Flyway schema history table.
+pub const FLYWAY_HISTORY_TABLE: &str = "flyway_schema_history";
+
+/// Checksum of migration script as computed by Flyway: CRC32 of UTF-8 bytes of all lines without
+/// line terminators (and byte order mark) stored as signed integer.
+pub fn flyway_checksum(script: &str) -> i32 {
+    let mut crc = !0u32;
+    for line in script.trim_start_matches('\u{feff}').lines() {
+        for byte in line.as_bytes() {
+            crc ^= u32::from(*byte);
+            for _ in 0..8 {
+                crc = if crc & 1 == 1 {
+                    (crc >> 1) ^ 0xedb8_8320
+                } else {
+                    crc >> 1
+                };
+            }
+        }
+    }
+    !crc as i32
+}
+
+/// Migration recorded in Flyway schema history table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlywayEntry {
+    pub installed_rank: i64,
+    /// `None` for repeatable migrations.
+    pub version: Option<String>,
+    pub description: String,
+    pub script: String,
+    pub checksum: Option<i64>,
+    pub success: bool,
+}
+
+/// Reads and writes `flyway_schema_history` compatible table so that databases migrated with
+/// Flyway can be migrated further without re-baselining.
+#[derive(Debug, Clone)]
+pub struct FlywayHistory {
+    table: String,
+    dialect: Dialect,
+}
+
+impl Default for FlywayHistory {
+    fn default() -> FlywayHistory {
+        FlywayHistory::new(FLYWAY_HISTORY_TABLE)
+    }
+}
+
+impl FlywayHistory {
+    /// Creates `FlywayHistory` given table name.
+    pub fn new(table: impl Into<String>) -> FlywayHistory {
+        FlywayHistory {
+            table: table.into(),
+            dialect: Dialect::Generic,
+        }
+    }
+
+    /// Sets SQL dialect used for column types and boolean values.
+    pub fn with_dialect(mut self, dialect: Dialect) -> FlywayHistory {
+        self.dialect = dialect;
+        self
+    }
+
+    /// Name of the table.
+    pub fn table(&self) -> &str {
+        &self.table
+    }
+
+    fn types(&self) -> (&'static str, &'static str) {
+        match self.dialect {
+            Dialect::SqlServer => ("BIT", "DATETIME"),
+            Dialect::MySql => ("TINYINT(1)", "TIMESTAMP"),
+            _ => ("BOOLEAN", "TIMESTAMP"),
+        }
+    }
+
+    fn success(&self) -> &'static str {
+        match self.dialect {
+            Dialect::Postgres | Dialect::Snowflake | Dialect::Hive => "TRUE",
+            _ => "1",
+        }
+    }
+
+    /// `EnsureSchema` that creates the table with Flyway columns if it does not exist.
+    pub fn ensure_schema(&self) -> EnsureSchema {
+        let (boolean, timestamp) = self.types();
+        crate::state::table_schema(
+            format!("Flyway history table {}", self.table),
+            &self.table,
+            format!(
+                "CREATE TABLE {} (installed_rank INT NOT NULL PRIMARY KEY, version VARCHAR(50), description VARCHAR(200) NOT NULL, \
+                type VARCHAR(20) NOT NULL, script VARCHAR(1000) NOT NULL, checksum INT, installed_by VARCHAR(100) NOT NULL, \
+                installed_on {} DEFAULT CURRENT_TIMESTAMP NOT NULL, execution_time INT NOT NULL, success {} NOT NULL)",
+                self.table, timestamp, boolean
+            ),
+        )
+    }
+
+    /// Loads recorded migrations ordered by installed rank.
+    pub fn entries(&self, database: &mut Handle<'_>) -> Result<Vec<FlywayEntry>, Problem> {
+        let query = format!(
+            "SELECT installed_rank, version, description, script, checksum, success FROM {} ORDER BY installed_rank",
+            self.table
+        );
+        database
+            .query::<ValueRow>(&query)?
+            .map(|row| {
+                let mut row = row?.into_iter();
+                let installed_rank = value_to_i64(row.next().flatten())
+                    .ok_or_problem("Flyway installed_rank is not an integer")?;
+                let mut next_string = || row.next().flatten().map(|value| value.to_string());
+                let version = next_string();
+                let description = next_string().unwrap_or_default();
+                let script = next_string().unwrap_or_default();
+                let checksum = next_string().and_then(|checksum| checksum.parse().ok());
+                let success = next_string()
+                    .is_some_and(|success| success == "1" || success.eq_ignore_ascii_case("true"));
+                Ok(FlywayEntry {
+                    installed_rank,
+                    version,
+                    description,
+                    script,
+                    checksum,
+                    success,
+                })
+            })
+            .collect()
+    }
+
+    /// `EnsureSchema` applying versioned migration script (split into statements) and recording
+    /// it in the history the way Flyway does (script named `V<version>__<description>.sql`).
+    ///
+    /// Migration is met if the version was recorded as successful; check fails if recorded checksum
+    /// differs from checksum of the script. The history table is created first if needed.
+    pub fn migration(
+        &self,
+        version: &str,
+        description: &str,
+        script: &str,
+    ) -> Result<EnsureSchema, Problem> {
+        let checksum = flyway_checksum(script);
+        let script_name = format!("V{}__{}.sql", version, description.replace(' ', "_"));
+        let mut meet_queries = split_script(script)?;
+        meet_queries.push(
+            format!(
+                "INSERT INTO {table} (installed_rank, version, description, type, script, checksum, installed_by, execution_time, success) \
+                SELECT COALESCE(MAX(installed_rank), 0) + 1, {}, {}, 'SQL', {}, {}, CURRENT_USER, 0, {} FROM {table}",
+                quote_literal(version),
+                quote_literal(description),
+                quote_literal(&script_name),
+                checksum,
+                self.success(),
+                table = self.table
+            )
+            .into(),
+        );
+        let declared: Vec<Sql> = meet_queries.clone();
+        let version_name = version.to_string();
+        let apply = EnsureSchema::new(
+            format!("apply {}", script_name),
+            format!(
+                "SELECT checksum FROM {} WHERE version = {} AND success = {}",
+                self.table,
+                quote_literal(version),
+                self.success()
+            ),
+            move |rows| {
+                let recorded = rows
+                    .map(|row| Ok(value_to_i64(row?.into_iter().next().flatten())))
+                    .collect::<Result<Vec<_>, Problem>>()?;
+                match recorded.first() {
+                    None => Ok(meet_queries.clone()),
+                    Some(Some(recorded)) if *recorded != i64::from(checksum) => problem!(
+                        "Checksum mismatch for migration {}: recorded {} but script has {}",
+                        version_name,
+                        recorded,
+                        checksum
+                    ),
+                    Some(_) => Ok(vec![]),
+                }
+            },
+        )
+        .with_declared_meet_queries(declared);
+        // History table needs to exist before the migration check
+        Ok(EnsureSchema::sequence(
+            format!("Flyway migration {}", script_name),
+            vec![self.ensure_schema(), apply],
+        ))
+    }
+}
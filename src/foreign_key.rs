@@ -0,0 +1,209 @@
+use crate::state::{quote_literal, value_to_i64};
+use crate::{Dialect, EnsureSchema, Sql};
+use odbc_iter::{Handle, ValueRow};
+use problem::prelude::*;
+use std::fmt;
+
+/// Action taken on referencing rows when referenced row is deleted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnDelete {
+    NoAction,
+    Restrict,
+    Cascade,
+    SetNull,
+    SetDefault,
+}
+
+impl fmt::Display for OnDelete {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OnDelete::NoAction => write!(f, "NO ACTION"),
+            OnDelete::Restrict => write!(f, "RESTRICT"),
+            OnDelete::Cascade => write!(f, "CASCADE"),
+            OnDelete::SetNull => write!(f, "SET NULL"),
+            OnDelete::SetDefault => write!(f, "SET DEFAULT"),
+        }
+    }
+}
+
+impl OnDelete {
+    /// Returns `true` if `delete_rule` of `information_schema.referential_constraints` matches;
+    /// `NO ACTION` and `RESTRICT` are considered equal as databases differ in which one they
+    /// report for the default.
+    fn matches(&self, delete_rule: &str) -> bool {
+        let delete_rule = delete_rule.trim();
+        match self {
+            OnDelete::NoAction | OnDelete::Restrict => {
+                delete_rule.eq_ignore_ascii_case("NO ACTION")
+                    || delete_rule.eq_ignore_ascii_case("RESTRICT")
+            }
+            _ => delete_rule.eq_ignore_ascii_case(&self.to_string()),
+        }
+    }
+}
+
+/// Foreign key between two tables added when missing and recreated when its columns or
+/// `ON DELETE` action differ.
+///
+/// Recreating drops the constraint first which requires `EnsureOptions::allow_destructive`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForeignKeyDef {
+    pub name: String,
+    pub table: String,
+    pub columns: Vec<String>,
+    pub referenced_table: String,
+    pub referenced_columns: Vec<String>,
+    pub on_delete: OnDelete,
+    /// Fail before adding the constraint if existing rows do not have referenced rows.
+    pub validate: bool,
+}
+
+impl ForeignKeyDef {
+    /// Creates `ForeignKeyDef` given constraint name, referencing table and columns and
+    /// referenced table and columns.
+    pub fn new(
+        name: impl Into<String>,
+        table: impl Into<String>,
+        columns: &[&str],
+        referenced_table: impl Into<String>,
+        referenced_columns: &[&str],
+    ) -> ForeignKeyDef {
+        ForeignKeyDef {
+            name: name.into(),
+            table: table.into(),
+            columns: columns.iter().map(|column| column.to_string()).collect(),
+            referenced_table: referenced_table.into(),
+            referenced_columns: referenced_columns
+                .iter()
+                .map(|column| column.to_string())
+                .collect(),
+            on_delete: OnDelete::NoAction,
+            validate: false,
+        }
+    }
+
+    pub fn on_delete(mut self, on_delete: OnDelete) -> ForeignKeyDef {
+        self.on_delete = on_delete;
+        self
+    }
+
+    /// Counts orphaned rows before adding the constraint and fails with their count instead of
+    /// letting the `ALTER` fail part way through.
+    pub fn with_validation(mut self) -> ForeignKeyDef {
+        self.validate = true;
+        self
+    }
+
+    /// Query producing row with delete rule and column name for each column of the constraint in
+    /// order; no rows if the constraint does not exist.
+    pub fn check_query(&self) -> Sql {
+        format!(
+            "SELECT rc.delete_rule, kcu.column_name FROM information_schema.referential_constraints rc JOIN information_schema.key_column_usage kcu ON kcu.constraint_name = rc.constraint_name AND kcu.constraint_schema = rc.constraint_schema WHERE UPPER(rc.constraint_name) = UPPER({}) AND UPPER(kcu.table_name) = UPPER({}) ORDER BY kcu.ordinal_position",
+            quote_literal(&self.name),
+            quote_literal(&self.table)
+        )
+        .into()
+    }
+
+    /// Statement adding the constraint.
+    pub fn create_query(&self) -> Sql {
+        format!(
+            "ALTER TABLE {} ADD CONSTRAINT {} FOREIGN KEY ({}) REFERENCES {} ({}) ON DELETE {}",
+            self.table,
+            self.name,
+            self.columns.join(", "),
+            self.referenced_table,
+            self.referenced_columns.join(", "),
+            self.on_delete
+        )
+        .into()
+    }
+
+    /// Statement dropping the constraint.
+    pub fn drop_query(&self, dialect: Dialect) -> Sql {
+        match dialect {
+            Dialect::MySql => format!("ALTER TABLE {} DROP FOREIGN KEY {}", self.table, self.name),
+            _ => format!("ALTER TABLE {} DROP CONSTRAINT {}", self.table, self.name),
+        }
+        .into()
+    }
+
+    /// Query producing single row with count of rows with all referencing columns set that have
+    /// no referenced row.
+    pub fn orphans_query(&self) -> Sql {
+        let not_null = self
+            .columns
+            .iter()
+            .map(|column| format!("c.{} IS NOT NULL", column))
+            .collect::<Vec<_>>()
+            .join(" AND ");
+        let join = self
+            .columns
+            .iter()
+            .zip(&self.referenced_columns)
+            .map(|(column, referenced)| format!("p.{} = c.{}", referenced, column))
+            .collect::<Vec<_>>()
+            .join(" AND ");
+        format!(
+            "SELECT COUNT(*) FROM {} c WHERE {} AND NOT EXISTS (SELECT 1 FROM {} p WHERE {})",
+            self.table, not_null, self.referenced_table, join
+        )
+        .into()
+    }
+
+    /// Fails if there are rows that would violate the constraint.
+    pub fn validate_data(&self, database: &mut Handle<'_>) -> Result<(), Problem> {
+        let row = database
+            .query::<ValueRow>(&self.orphans_query())?
+            .single()?;
+        match value_to_i64(row.into_iter().next().flatten()) {
+            Some(0) => Ok(()),
+            Some(orphans) => problem!(
+                "Cannot add foreign key {}: {} rows of {} have no referenced row in {}",
+                self.name,
+                orphans,
+                self.table,
+                self.referenced_table
+            ),
+            None => problem!("Orphaned rows query of {} did not return count", self.name),
+        }
+    }
+
+    /// `EnsureSchema` adding the constraint if it is missing or recreating it if it differs.
+    pub fn ensure_schema(&self, dialect: Dialect) -> EnsureSchema {
+        let foreign_key = self.clone();
+        EnsureSchema::with_inspection(
+            format!("foreign key {}", self.name),
+            self.check_query(),
+            move |rows, database| {
+                let mut delete_rule = None;
+                let mut columns = Vec::new();
+                for row in rows {
+                    let mut row = row
+                        .into_iter()
+                        .map(|value| value.map(|value| value.to_string()));
+                    delete_rule = row.next().flatten();
+                    columns.extend(row.next().flatten());
+                }
+                let mut queries = Vec::new();
+                if let Some(delete_rule) = delete_rule {
+                    let same_columns = columns.len() == foreign_key.columns.len()
+                        && columns
+                            .iter()
+                            .zip(&foreign_key.columns)
+                            .all(|(current, column)| current.eq_ignore_ascii_case(column));
+                    if same_columns && foreign_key.on_delete.matches(&delete_rule) {
+                        return Ok(queries);
+                    }
+                    queries.push(foreign_key.drop_query(dialect));
+                }
+                if foreign_key.validate {
+                    foreign_key.validate_data(database)?;
+                }
+                queries.push(foreign_key.create_query());
+                Ok(queries)
+            },
+        )
+        .with_declared_meet_queries(vec![self.create_query()])
+    }
+}
@@ -0,0 +1,103 @@
+use crate::hash::definition_marker;
+use crate::state::quote_literal;
+use crate::trigger::{definition_schema, mysql_marked_body};
+use crate::{Dialect, EnsureSchema, Sql};
+use problem::prelude::*;
+
+/// User-defined scalar or table function (re)deployed when missing or when its body has changed
+/// since it was deployed (detected as with `TriggerDef`).
+///
+/// MySQL cannot replace functions so redeploying drops the function first which requires
+/// `EnsureOptions::allow_destructive`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionDef {
+    pub name: String,
+    /// Everything between the function name and its body: parameters, return type and options
+    /// (e.g. `(a INT) RETURNS INT DETERMINISTIC` or `(a int) RETURNS int LANGUAGE sql`).
+    pub signature: String,
+    pub body: String,
+}
+
+impl FunctionDef {
+    pub fn new(
+        name: impl Into<String>,
+        signature: impl Into<String>,
+        body: impl Into<String>,
+    ) -> FunctionDef {
+        FunctionDef {
+            name: name.into(),
+            signature: signature.into(),
+            body: body.into(),
+        }
+    }
+
+    /// Query producing deployed definition of the function (no rows or `NULL` if it does not
+    /// exist); `None` if not supported in the dialect.
+    pub fn check_query(&self, dialect: Dialect) -> Option<Sql> {
+        let query = match dialect {
+            Dialect::SqlServer => format!(
+                "SELECT OBJECT_DEFINITION(OBJECT_ID({}))",
+                quote_literal(&self.name)
+            ),
+            Dialect::MySql => format!(
+                "SELECT routine_definition FROM information_schema.routines WHERE routine_schema = DATABASE() AND routine_type = 'FUNCTION' AND routine_name = {}",
+                quote_literal(&self.name)
+            ),
+            Dialect::Postgres => format!(
+                "SELECT prosrc FROM pg_proc WHERE UPPER(proname) = UPPER({})",
+                quote_literal(&self.name)
+            ),
+            _ => return None,
+        };
+        Some(query.into())
+    }
+
+    /// Statements (re)creating the function.
+    pub fn create_queries(&self, dialect: Dialect) -> Result<Vec<Sql>, Problem> {
+        let marker = definition_marker(&self.body);
+        let queries = match dialect {
+            Dialect::SqlServer => vec![format!(
+                "CREATE OR ALTER FUNCTION {}{} AS\n{}\n{}",
+                self.name, self.signature, marker, self.body
+            )],
+            Dialect::MySql => vec![
+                format!("DROP FUNCTION IF EXISTS {}", self.name),
+                format!(
+                    "CREATE FUNCTION {}{} {}",
+                    self.name,
+                    self.signature,
+                    mysql_marked_body(&self.body, &marker)
+                ),
+            ],
+            Dialect::Postgres => vec![format!(
+                "CREATE OR REPLACE FUNCTION {}{} AS $body$\n{}\n{}\n$body$",
+                self.name, self.signature, marker, self.body
+            )],
+            _ => {
+                return problem!(
+                    "Function {} cannot be created in {:?} dialect",
+                    self.name,
+                    dialect
+                )
+            }
+        };
+        Ok(queries.into_iter().map(Sql::from).collect())
+    }
+
+    /// `EnsureSchema` (re)creating the function if it is missing or its deployed definition does
+    /// not carry hash of the current body.
+    pub fn ensure_schema(&self, dialect: Dialect) -> Result<EnsureSchema, Problem> {
+        let check_query = self.check_query(dialect).ok_or_else(|| {
+            Problem::from_error(format!(
+                "Function {} cannot be checked in {:?} dialect",
+                self.name, dialect
+            ))
+        })?;
+        Ok(definition_schema(
+            format!("function {}", self.name),
+            check_query,
+            definition_marker(&self.body),
+            self.create_queries(dialect)?,
+        ))
+    }
+}
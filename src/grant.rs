@@ -0,0 +1,186 @@
+use crate::state::quote_literal;
+use crate::{Dialect, EnsureSchema, Sql};
+use odbc_iter::{Handle, ValueRow};
+use problem::prelude::*;
+
+/// Permissions of a principal (user or role) on an object converged with `GRANT` and, if
+/// exclusive, `REVOKE` statements.
+///
+/// Granted permissions are listed from the dialect permission catalog; only permissions granted
+/// directly to the principal (not through role membership) are considered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrantDef {
+    /// Principal as used in `GRANT` (e.g. `app_reader` or `'app'@'%'` in MySQL).
+    pub principal: String,
+    /// Table or view.
+    pub object: String,
+    /// Permissions (e.g. `SELECT`, `INSERT`).
+    pub permissions: Vec<String>,
+    /// Revoke permissions on the object that are granted but not listed.
+    pub exclusive: bool,
+}
+
+impl GrantDef {
+    pub fn new(
+        principal: impl Into<String>,
+        object: impl Into<String>,
+        permissions: &[&str],
+    ) -> GrantDef {
+        GrantDef {
+            principal: principal.into(),
+            object: object.into(),
+            permissions: permissions
+                .iter()
+                .map(|permission| permission.to_ascii_uppercase())
+                .collect(),
+            exclusive: false,
+        }
+    }
+
+    /// Revokes permissions on the object granted to the principal that are not listed.
+    pub fn exclusive(mut self) -> GrantDef {
+        self.exclusive = true;
+        self
+    }
+
+    /// Query producing one row with name of each permission the principal was granted on the
+    /// object; `None` if not supported in the dialect.
+    pub fn check_query(&self, dialect: Dialect) -> Option<Sql> {
+        let principal = quote_literal(&self.principal);
+        let (schema, table) = match self.object.rsplit_once('.') {
+            Some((schema, table)) => (Some(quote_literal(schema)), quote_literal(table)),
+            None => (None, quote_literal(&self.object)),
+        };
+        let query = match dialect {
+            Dialect::SqlServer => format!(
+                "SELECT p.permission_name FROM sys.database_permissions p JOIN sys.database_principals u ON u.principal_id = p.grantee_principal_id WHERE u.name = {} AND p.class = 1 AND p.major_id = OBJECT_ID({}) AND p.minor_id = 0 AND p.state IN ('G', 'W')",
+                principal,
+                quote_literal(&self.object)
+            ),
+            Dialect::Postgres => format!(
+                "SELECT privilege_type FROM information_schema.table_privileges WHERE grantee = {} AND UPPER(table_name) = UPPER({}) AND table_schema = {}",
+                principal,
+                table,
+                schema.unwrap_or_else(|| "current_schema()".to_string())
+            ),
+            Dialect::MySql => format!(
+                "SELECT privilege_type FROM information_schema.table_privileges WHERE REPLACE(grantee, '''', '') = REPLACE({}, '''', '') AND table_name = {} AND table_schema = {}",
+                principal,
+                table,
+                schema.unwrap_or_else(|| "DATABASE()".to_string())
+            ),
+            _ => return None,
+        };
+        Some(query.into())
+    }
+
+    /// Statements converging permissions given the currently granted ones.
+    pub fn converge_queries(&self, granted: &[String]) -> Vec<Sql> {
+        let is_granted = |permission: &String| {
+            granted
+                .iter()
+                .any(|granted| granted.eq_ignore_ascii_case(permission))
+        };
+        let missing = self
+            .permissions
+            .iter()
+            .filter(|permission| !is_granted(permission))
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut queries = Vec::new();
+        if !missing.is_empty() {
+            queries.push(
+                format!(
+                    "GRANT {} ON {} TO {}",
+                    missing.join(", "),
+                    self.object,
+                    self.principal
+                )
+                .into(),
+            );
+        }
+        if self.exclusive {
+            let mut extra = granted
+                .iter()
+                .filter(|granted| {
+                    !self
+                        .permissions
+                        .iter()
+                        .any(|permission| permission.eq_ignore_ascii_case(granted))
+                })
+                .map(|granted| granted.to_ascii_uppercase())
+                .collect::<Vec<_>>();
+            extra.sort();
+            extra.dedup();
+            if !extra.is_empty() {
+                queries.push(
+                    format!(
+                        "REVOKE {} ON {} FROM {}",
+                        extra.join(", "),
+                        self.object,
+                        self.principal
+                    )
+                    .into(),
+                );
+            }
+        }
+        queries
+    }
+
+    /// Lists permissions the principal was granted on the object.
+    pub fn granted(
+        &self,
+        database: &mut Handle<'_>,
+        dialect: Dialect,
+    ) -> Result<Vec<String>, Problem> {
+        let check_query = self.checked_query(dialect)?;
+        database
+            .query::<ValueRow>(&check_query)?
+            .map(|row| Ok(granted_permission(row?)))
+            .collect::<Result<Vec<_>, Problem>>()
+            .map(|granted| granted.into_iter().flatten().collect())
+    }
+
+    /// Statements that would be run to converge the permissions without running them.
+    pub fn preview(
+        &self,
+        database: &mut Handle<'_>,
+        dialect: Dialect,
+    ) -> Result<Vec<Sql>, Problem> {
+        Ok(self.converge_queries(&self.granted(database, dialect)?))
+    }
+
+    /// `EnsureSchema` granting (and revoking if exclusive) permissions to match the definition.
+    pub fn ensure_schema(&self, dialect: Dialect) -> Result<EnsureSchema, Problem> {
+        let grant = self.clone();
+        let declared = self.converge_queries(&[]);
+        Ok(EnsureSchema::with_inspection(
+            format!("grant {} on {}", self.principal, self.object),
+            self.checked_query(dialect)?,
+            move |rows, _database| {
+                let granted = rows
+                    .into_iter()
+                    .filter_map(granted_permission)
+                    .collect::<Vec<_>>();
+                Ok(grant.converge_queries(&granted))
+            },
+        )
+        .with_declared_meet_queries(declared))
+    }
+
+    fn checked_query(&self, dialect: Dialect) -> Result<Sql, Problem> {
+        self.check_query(dialect).ok_or_else(|| {
+            Problem::from_error(format!(
+                "Permissions of {} on {} cannot be checked in {:?} dialect",
+                self.principal, self.object, dialect
+            ))
+        })
+    }
+}
+
+fn granted_permission(row: ValueRow) -> Option<String> {
+    row.into_iter()
+        .next()
+        .flatten()
+        .map(|permission| permission.to_string().trim().to_string())
+}
@@ -0,0 +1,205 @@
+use crate::{EnsureReport, EnsureSchema, Node, ObjectState, Require};
+use std::fmt::Write;
+
+/// Kind of dependency between objects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EdgeKind {
+    /// Object requires the other one (see `EnsureSchema::with_meet_require`).
+    Requires,
+    /// Group has the other object as member.
+    Member,
+    /// Member of a sequence is ensured after the other one.
+    Next,
+}
+
+/// Object of the graph; lazy requirements have no name.
+struct GraphNode<'s> {
+    name: Option<&'s str>,
+}
+
+/// Objects with edges between their indices in depth-first order.
+struct Graph<'s> {
+    nodes: Vec<GraphNode<'s>>,
+    edges: Vec<(usize, usize, EdgeKind)>,
+}
+
+impl<'s> Graph<'s> {
+    fn new(schema: &'s EnsureSchema) -> Graph<'s> {
+        fn visit<'s>(graph: &mut Graph<'s>, schema: &'s EnsureSchema) -> usize {
+            let index = graph.nodes.len();
+            graph.nodes.push(GraphNode {
+                name: Some(&schema.name),
+            });
+            for required in &schema.meet_require {
+                let required = match required {
+                    Require::Schema(required) => visit(graph, required),
+                    Require::Lazy(_) => {
+                        graph.nodes.push(GraphNode { name: None });
+                        graph.nodes.len() - 1
+                    }
+                };
+                graph.edges.push((index, required, EdgeKind::Requires));
+            }
+            if let Node::Group {
+                members,
+                independent,
+            } = &schema.node
+            {
+                let mut previous = None;
+                for member in members {
+                    let member = visit(graph, member);
+                    graph.edges.push((index, member, EdgeKind::Member));
+                    if let Some(previous) = previous.filter(|_| !independent) {
+                        graph.edges.push((previous, member, EdgeKind::Next));
+                    }
+                    previous = Some(member);
+                }
+            }
+            index
+        }
+
+        let mut graph = Graph {
+            nodes: Vec::new(),
+            edges: Vec::new(),
+        };
+        visit(&mut graph, schema);
+        graph
+    }
+}
+
+/// State of named object in the report; last reported state wins.
+fn reported_state<'r>(report: Option<&'r EnsureReport>, name: &str) -> Option<&'r ObjectState> {
+    report?
+        .objects
+        .iter()
+        .rev()
+        .find(|object| object.name == name)
+        .map(|object| &object.state)
+}
+
+/// Fill colors of state classes matching colors used in DOT output.
+const STATE_FILLS: &[(&str, &str)] = &[
+    ("met", "#98fb98"),
+    ("changed", "#add8e6"),
+    ("would_change", "#f0e68c"),
+    ("skipped", "#d3d3d3"),
+    ("failed", "#fa8072"),
+    ("assertion_failed", "#ffa500"),
+    ("verification_failed", "#ff6347"),
+    ("not_attempted", "#ffffff"),
+];
+
+/// Name of color class for reported state.
+fn state_class(state: &ObjectState) -> &'static str {
+    match state {
+        ObjectState::Met => "met",
+        ObjectState::Changed => "changed",
+        ObjectState::WouldChange => "would_change",
+        ObjectState::Skipped => "skipped",
+        ObjectState::Failed(_) => "failed",
+        ObjectState::AssertionFailed(_) => "assertion_failed",
+        ObjectState::VerificationFailed(_) => "verification_failed",
+        ObjectState::NotAttempted => "not_attempted",
+    }
+}
+
+fn dot_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+impl EnsureSchema {
+    /// Renders requirement graph in Graphviz DOT format; requirements point from object to
+    /// required one, group members are connected with dashed edges and members of a sequence
+    /// (e.g. migrations) with dotted edges in order.
+    ///
+    /// If report is given objects are colored by their reported state.
+    pub fn to_dot(&self, report: Option<&EnsureReport>) -> String {
+        let graph = Graph::new(self);
+        let mut dot = String::from("digraph schema {\n  rankdir=LR;\n  node [shape=box];\n");
+        for (index, node) in graph.nodes.iter().enumerate() {
+            let name = match node.name {
+                Some(name) => name,
+                None => {
+                    writeln!(dot, "  n{} [label=\"lazy\", style=dotted];", index).unwrap();
+                    continue;
+                }
+            };
+            let color = match reported_state(report, name) {
+                Some(ObjectState::Met) => Some("palegreen"),
+                Some(ObjectState::Changed) => Some("lightblue"),
+                Some(ObjectState::WouldChange) => Some("khaki"),
+                Some(ObjectState::Skipped) => Some("lightgrey"),
+                Some(ObjectState::Failed(_)) => Some("salmon"),
+                Some(ObjectState::AssertionFailed(_)) => Some("orange"),
+                Some(ObjectState::VerificationFailed(_)) => Some("tomato"),
+                Some(ObjectState::NotAttempted) => Some("white"),
+                None => None,
+            };
+            match color {
+                Some(color) => writeln!(
+                    dot,
+                    "  n{} [label={}, style=filled, fillcolor={}];",
+                    index,
+                    dot_string(name),
+                    color
+                ),
+                None => writeln!(dot, "  n{} [label={}];", index, dot_string(name)),
+            }
+            .unwrap();
+        }
+        for (from, to, kind) in &graph.edges {
+            match kind {
+                EdgeKind::Requires => writeln!(dot, "  n{} -> n{};", from, to),
+                EdgeKind::Member => writeln!(dot, "  n{} -> n{} [style=dashed];", from, to),
+                EdgeKind::Next => writeln!(
+                    dot,
+                    "  n{} -> n{} [style=dotted, label=\"then\"];",
+                    from, to
+                ),
+            }
+            .unwrap();
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Renders requirement graph as Mermaid flowchart with the same edges as `to_dot`; it can be
+    /// embedded in Markdown documents (e.g. wiki pages or pull request descriptions).
+    ///
+    /// If report is given objects are colored by their reported state.
+    pub fn to_mermaid(&self, report: Option<&EnsureReport>) -> String {
+        let graph = Graph::new(self);
+        let mut mermaid = String::from("flowchart LR\n");
+        let mut classes = Vec::new();
+        for (index, node) in graph.nodes.iter().enumerate() {
+            let name = match node.name {
+                Some(name) => name,
+                None => {
+                    writeln!(mermaid, "  n{}([lazy])", index).unwrap();
+                    continue;
+                }
+            };
+            writeln!(mermaid, "  n{}[\"{}\"]", index, name.replace('"', "#quot;")).unwrap();
+            if let Some(state) = reported_state(report, name) {
+                classes.push((index, state_class(state)));
+            }
+        }
+        for (from, to, kind) in &graph.edges {
+            match kind {
+                EdgeKind::Requires => writeln!(mermaid, "  n{} --> n{}", from, to),
+                EdgeKind::Member => writeln!(mermaid, "  n{} -.-> n{}", from, to),
+                EdgeKind::Next => writeln!(mermaid, "  n{} -- then --> n{}", from, to),
+            }
+            .unwrap();
+        }
+        if !classes.is_empty() {
+            for (class, fill) in STATE_FILLS {
+                writeln!(mermaid, "  classDef {} fill:{}", class, fill).unwrap();
+            }
+            for (index, class) in classes {
+                writeln!(mermaid, "  class n{} {}", index, class).unwrap();
+            }
+        }
+        mermaid
+    }
+}
@@ -0,0 +1,70 @@
+use crate::{is_destructive, Sql};
+use problem::prelude::*;
+use regex::Regex;
+
+/// Protects databases whose connection target (connection string or DSN) matches any of the
+/// patterns from being changed unless run options carry expected confirmation token.
+///
+/// This prevents accidentally pointing a development tool at production.
+#[derive(Debug, Clone)]
+pub struct ProductionGuard {
+    patterns: Vec<Regex>,
+    token: String,
+    destructive_only: bool,
+}
+
+impl ProductionGuard {
+    /// Creates `ProductionGuard` given list of regular expressions connection target is matched
+    /// against and confirmation token that needs to be passed with
+    /// `EnsureOptions::confirmation_token` to meet protected database.
+    pub fn new(
+        patterns: &[&str],
+        token: impl Into<String>,
+    ) -> Result<ProductionGuard, regex::Error> {
+        Ok(ProductionGuard {
+            patterns: patterns
+                .iter()
+                .map(|pattern| Regex::new(pattern))
+                .collect::<Result<_, _>>()?,
+            token: token.into(),
+            destructive_only: false,
+        })
+    }
+
+    /// If set to `true` the token is only required for destructive meet statements (see
+    /// `is_destructive`).
+    pub fn with_destructive_only(mut self, destructive_only: bool) -> ProductionGuard {
+        self.destructive_only = destructive_only;
+        self
+    }
+
+    /// Returns `true` if the connection target matches any of the patterns.
+    pub fn is_protected(&self, target: &str) -> bool {
+        self.patterns.iter().any(|pattern| pattern.is_match(target))
+    }
+
+    /// Fails if meeting object with given statements on the target requires the token and it was
+    /// not provided; unknown target is considered protected.
+    pub(crate) fn check(
+        &self,
+        target: Option<&str>,
+        token: Option<&str>,
+        name: &str,
+        meet_queries: &[Sql],
+    ) -> Result<(), Problem> {
+        if !target.is_none_or(|target| self.is_protected(target)) {
+            return Ok(());
+        }
+        if self.destructive_only && !meet_queries.iter().any(|query| is_destructive(query)) {
+            return Ok(());
+        }
+        if token == Some(self.token.as_str()) {
+            return Ok(());
+        }
+        // Target is not included as connection string may contain credentials
+        problem!(
+            "Meeting '{}' on protected database requires confirmation token",
+            name
+        )
+    }
+}
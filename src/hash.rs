@@ -0,0 +1,37 @@
+use crate::normalize_sql;
+
+/// Stable 64-bit FNV-1a hasher used for fingerprints that are persisted or compared between
+/// runs; unlike `DefaultHasher` its output does not change between Rust releases.
+#[derive(Debug, Clone)]
+pub(crate) struct Fnv64(u64);
+
+impl Fnv64 {
+    pub(crate) fn new() -> Fnv64 {
+        Fnv64(0xcbf2_9ce4_8422_2325)
+    }
+
+    pub(crate) fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    /// Writes string followed by a separator so that concatenations do not collide.
+    pub(crate) fn write_str(&mut self, value: &str) {
+        self.write(value.as_bytes());
+        self.write(&[0xff]);
+    }
+
+    pub(crate) fn finish_hex(&self) -> String {
+        format!("{:016x}", self.0)
+    }
+}
+
+/// Comment embedded in deployed routine (e.g. trigger or function) definitions carrying hash of
+/// its normalized body so that drift can be detected by searching the catalog definition for it.
+pub(crate) fn definition_marker(body: &str) -> String {
+    let mut hasher = Fnv64::new();
+    hasher.write_str(&normalize_sql(body));
+    format!("/* schema hash {} */", hasher.finish_hex())
+}
@@ -0,0 +1,89 @@
+use crate::state::quote_literal;
+use crate::view::exists_schema;
+use crate::{Ensure, EnsureSchema, Sql};
+use odbc_iter::Handle;
+use problem::prelude::*;
+use std::cell::Cell;
+
+/// Formats partition specification (without parentheses) given column and value pairs.
+pub fn partition_spec(partition: &[(&str, &str)]) -> String {
+    partition
+        .iter()
+        .map(|(column, value)| format!("{}={}", column, quote_literal(value)))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Statement adding partition to Hive/Impala table if it does not exist.
+pub fn add_partition_query(table: &str, partition: &[(&str, &str)]) -> Sql {
+    format!(
+        "ALTER TABLE {} ADD IF NOT EXISTS PARTITION ({})",
+        table,
+        partition_spec(partition)
+    )
+    .into()
+}
+
+/// `EnsureSchema` that adds partition to Hive table if `SHOW PARTITIONS` does not list it.
+pub fn hive_partition(table: &str, partition: &[(&str, &str)]) -> EnsureSchema {
+    let spec = partition_spec(partition);
+    exists_schema(
+        format!("partition {} of {}", spec, table),
+        format!("SHOW PARTITIONS {} PARTITION ({})", table, spec).into(),
+        add_partition_query(table, partition),
+    )
+}
+
+/// Step running `MSCK REPAIR TABLE` on every run so that partitions added directly to storage
+/// are registered in the metastore.
+#[derive(Debug)]
+pub struct MsckRepair {
+    name: String,
+    table: String,
+    /// Set by meet so that verification of this run passes; reset by the next check.
+    repaired: Cell<bool>,
+}
+
+impl MsckRepair {
+    /// Creates `MsckRepair` for given table.
+    pub fn new(table: impl Into<String>) -> MsckRepair {
+        let table = table.into();
+        MsckRepair {
+            name: format!("repair of {}", table),
+            table,
+            repaired: Cell::new(false),
+        }
+    }
+
+    /// The repair statement.
+    pub fn query(&self) -> Sql {
+        format!("MSCK REPAIR TABLE {}", self.table).into()
+    }
+}
+
+impl Ensure for MsckRepair {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn check(&self, _database: &mut Handle<'_>) -> Result<bool, Problem> {
+        // only verification right after the repair is met; every other check repairs again
+        Ok(self.repaired.replace(false))
+    }
+
+    fn statements(&self) -> Vec<Sql> {
+        vec![self.query()]
+    }
+
+    fn meet(&self, database: &mut Handle<'_>) -> Result<(), Problem> {
+        database.query::<()>(&self.query())?.no_result()?;
+        self.repaired.set(true);
+        Ok(())
+    }
+}
+
+impl From<MsckRepair> for EnsureSchema {
+    fn from(step: MsckRepair) -> EnsureSchema {
+        EnsureSchema::step(step)
+    }
+}
@@ -0,0 +1,160 @@
+use crate::state::quote_literal;
+use crate::{Dialect, EnsureSchema, Sql};
+use log::*;
+use problem::prelude::*;
+
+/// Declarative definition of an index from which its existence check and `CREATE INDEX` are
+/// generated.
+///
+/// Only existence of the index is checked; to change its definition drop it or use a new name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexDef {
+    pub name: String,
+    pub table: String,
+    /// Indexed columns or expressions, optionally with `ASC` or `DESC`.
+    pub columns: Vec<String>,
+    pub unique: bool,
+    /// Non-key columns stored in the index (SQL Server and Postgres).
+    pub include: Vec<String>,
+    /// Predicate of partial (filtered) index (SQL Server and Postgres).
+    pub filter: Option<String>,
+    /// Build the index without blocking writes where supported.
+    pub online: bool,
+}
+
+impl IndexDef {
+    /// Creates `IndexDef` given index name, table and indexed columns.
+    pub fn new(name: impl Into<String>, table: impl Into<String>, columns: &[&str]) -> IndexDef {
+        IndexDef {
+            name: name.into(),
+            table: table.into(),
+            columns: columns.iter().map(|column| column.to_string()).collect(),
+            unique: false,
+            include: Vec::new(),
+            filter: None,
+            online: false,
+        }
+    }
+
+    /// Makes the index unique.
+    pub fn unique(mut self) -> IndexDef {
+        self.unique = true;
+        self
+    }
+
+    /// Sets non-key columns stored in the index.
+    pub fn with_include(mut self, columns: &[&str]) -> IndexDef {
+        self.include = columns.iter().map(|column| column.to_string()).collect();
+        self
+    }
+
+    /// Sets predicate (e.g. `deleted_at IS NULL`) so that only matching rows are indexed.
+    pub fn with_filter(mut self, filter: impl Into<String>) -> IndexDef {
+        self.filter = Some(filter.into());
+        self
+    }
+
+    /// Builds the index online (`WITH (ONLINE = ON)` in SQL Server, `CONCURRENTLY` in Postgres and
+    /// `LOCK=NONE` in MySQL); ignored with warning in other dialects.
+    pub fn online(mut self) -> IndexDef {
+        self.online = true;
+        self
+    }
+
+    /// Query producing single row with count of indexes of this name on the table; `None` if not
+    /// supported in the dialect.
+    pub fn check_query(&self, dialect: Dialect) -> Option<Sql> {
+        let query = match dialect {
+            Dialect::SqlServer => format!(
+                "SELECT COUNT(*) FROM sys.indexes WHERE name = {} AND object_id = OBJECT_ID({})",
+                quote_literal(&self.name),
+                quote_literal(&self.table)
+            ),
+            Dialect::Postgres => format!(
+                "SELECT COUNT(*) FROM pg_indexes WHERE UPPER(indexname) = UPPER({}) AND UPPER(tablename) = UPPER({})",
+                quote_literal(&self.name),
+                quote_literal(&self.table)
+            ),
+            Dialect::MySql => format!(
+                "SELECT COUNT(*) FROM information_schema.statistics WHERE table_schema = DATABASE() AND table_name = {} AND index_name = {}",
+                quote_literal(&self.table),
+                quote_literal(&self.name)
+            ),
+            Dialect::MonetDb => format!(
+                "SELECT COUNT(*) FROM sys.idxs WHERE name = {}",
+                quote_literal(&self.name)
+            ),
+            Dialect::Generic | Dialect::Hive | Dialect::Snowflake => return None,
+        };
+        Some(query.into())
+    }
+
+    /// `CREATE INDEX` statement; fails if included columns or filter are not supported in the
+    /// dialect.
+    pub fn create_query(&self, dialect: Dialect) -> Result<Sql, Problem> {
+        let supports_extensions = matches!(dialect, Dialect::SqlServer | Dialect::Postgres);
+        if !supports_extensions && !self.include.is_empty() {
+            return problem!(
+                "Included columns of index {} are not supported in {:?} dialect",
+                self.name,
+                dialect
+            );
+        }
+        if !supports_extensions && self.filter.is_some() {
+            return problem!(
+                "Filter of index {} is not supported in {:?} dialect",
+                self.name,
+                dialect
+            );
+        }
+
+        let mut query = String::from("CREATE ");
+        if self.unique {
+            query.push_str("UNIQUE ");
+        }
+        query.push_str("INDEX ");
+        if self.online && dialect == Dialect::Postgres {
+            query.push_str("CONCURRENTLY ");
+        }
+        query.push_str(&format!(
+            "{} ON {} ({})",
+            self.name,
+            self.table,
+            self.columns.join(", ")
+        ));
+        if !self.include.is_empty() {
+            query.push_str(&format!(" INCLUDE ({})", self.include.join(", ")));
+        }
+        if let Some(filter) = &self.filter {
+            query.push_str(" WHERE ");
+            query.push_str(filter);
+        }
+        if self.online {
+            match dialect {
+                Dialect::SqlServer => query.push_str(" WITH (ONLINE = ON)"),
+                Dialect::MySql => query.push_str(" ALGORITHM=INPLACE LOCK=NONE"),
+                Dialect::Postgres => (),
+                _ => warn!(
+                    "Online index build is not supported in {:?} dialect: {}",
+                    dialect, self.name
+                ),
+            }
+        }
+        Ok(query.into())
+    }
+
+    /// `EnsureSchema` creating the index if it does not exist.
+    pub fn ensure_schema(&self, dialect: Dialect) -> Result<EnsureSchema, Problem> {
+        let check_query = self.check_query(dialect).ok_or_else(|| {
+            Problem::from_error(format!(
+                "Index {} cannot be checked in {:?} dialect",
+                self.name, dialect
+            ))
+        })?;
+        Ok(EnsureSchema::with_count_check(
+            format!("index {}", self.name),
+            check_query,
+            vec![self.create_query(dialect)?],
+        ))
+    }
+}
@@ -1,14 +1,139 @@
-use ensure::ensure;
 use ensure::CheckEnsureResult::*;
 use log::*;
-use odbc_iter::{DefaultConfiguration, Executed, Handle, ResultSet, TryFromValueRow, ValueRow};
+use odbc_iter::{Connection, DefaultConfiguration, Executed, Handle, ResultSet, ValueRow};
 use problem::prelude::*;
+use regex::Regex;
+use std::any::{type_name, Any};
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
 use std::error::Error;
 use std::fmt;
+use std::io;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread::{self, sleep};
+use std::time::{Duration, Instant};
 
-pub type Sql = String;
+mod approval;
+pub use approval::*;
+mod assertion;
+pub use assertion::*;
+mod audit;
+pub use audit::*;
+mod changelog;
+pub use changelog::*;
+mod cancel;
+pub use cancel::*;
+mod chunk;
+pub use chunk::*;
+mod classify;
+pub use classify::*;
+mod collation;
+pub use collation::*;
+mod column_default;
+pub use column_default::*;
+mod compare;
+mod concurrency;
+mod condition;
+pub use compare::*;
+pub use concurrency::*;
+mod database;
+pub use database::*;
+mod dialect;
+pub use dialect::*;
+mod error;
+pub use error::*;
+mod flyway;
+pub use flyway::*;
+mod foreign_key;
+pub use foreign_key::*;
+mod function;
+pub use function::*;
+mod grant;
+pub use grant::*;
+mod graph;
+mod guard;
+pub use guard::*;
+mod hash;
+mod hive;
+pub use hive::*;
+mod index;
+pub use index::*;
+mod liquibase;
+pub use liquibase::*;
+mod lock;
+pub use lock::*;
+mod maintenance;
+mod metadata;
+use hash::Fnv64;
+use metadata::is_valid_run_id;
+pub use metadata::*;
+mod mssql;
+pub use mssql::*;
+mod namespace;
+mod observer;
+pub use observer::*;
+mod partition;
+pub use partition::*;
+mod plan;
+pub use plan::*;
+mod preflight;
+pub use preflight::*;
+mod principal;
+pub use principal::*;
+mod privilege;
+pub use privilege::*;
+mod refinery;
+pub use refinery::*;
+mod registry;
+pub use registry::*;
+mod render;
+pub use render::*;
+mod report;
+pub use report::*;
+mod report_table;
+pub use report_table::*;
+mod retention;
+pub use retention::*;
+mod reverse;
+pub use reverse::*;
+mod rollback;
+pub use rollback::*;
+#[cfg(feature = "scheduler")]
+mod scheduler;
+#[cfg(feature = "scheduler")]
+pub use scheduler::*;
+mod sequence;
+pub use sequence::*;
+mod smoke;
+pub use smoke::*;
+mod sql;
+pub use sql::*;
+mod state;
+mod step;
+pub use state::*;
+use state::{now_epoch, Scalar};
+pub use step::*;
+mod table;
+pub use table::*;
+mod table_lock;
+pub use table_lock::*;
+mod template;
+pub use template::*;
+mod tenant;
+pub use tenant::*;
+mod tree;
+pub use tree::*;
+mod trigger;
+pub use trigger::*;
+mod validate;
+pub use validate::*;
+mod view;
+pub use view::*;
+mod watch;
+pub use watch::*;
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SchemaState {
     Ok,
     Changed,
@@ -18,6 +143,16 @@ pub enum SchemaState {
 pub enum SchemaStateError {
     CheckError(String, Problem),
     MeetError(String, Problem),
+    LockError(String, Problem),
+    /// Assertion of named step does not hold (see `AssertStep`).
+    AssertionError(String, Problem),
+    /// Named smoke test has failed after the object was changed (see `SmokeTest`).
+    VerificationError(String, Problem),
+    /// Time budget of the run was exceeded before named object was started (see
+    /// `EnsureOptions::time_budget`).
+    BudgetExceeded(String),
+    /// Requirement of named object has failed.
+    RequirementError(String, Box<SchemaStateError>),
 }
 
 impl fmt::Display for SchemaStateError {
@@ -29,142 +164,2480 @@ impl fmt::Display for SchemaStateError {
             SchemaStateError::MeetError(name, problem) => {
                 write!(f, "error meeting schema state for '{}': {}", name, problem)
             }
+            SchemaStateError::LockError(name, problem) => {
+                write!(f, "error locking schema state for '{}': {}", name, problem)
+            }
+            SchemaStateError::AssertionError(name, problem) => {
+                write!(f, "assertion '{}' failed: {}", name, problem)
+            }
+            SchemaStateError::VerificationError(name, problem) => {
+                write!(f, "verification '{}' failed: {}", name, problem)
+            }
+            SchemaStateError::BudgetExceeded(name) => {
+                write!(f, "time budget exceeded before '{}'", name)
+            }
+            SchemaStateError::RequirementError(name, error) => {
+                write!(f, "error ensuring requirement of '{}': {}", name, error)
+            }
+        }
+    }
+}
+
+impl Error for SchemaStateError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SchemaStateError::RequirementError(_, error) => Some(error.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl SchemaStateError {
+    /// Category of the failure; see `root_cause` for category of failed requirement.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            SchemaStateError::CheckError(_, problem) | SchemaStateError::MeetError(_, problem) => {
+                error::classify(&problem.to_string())
+            }
+            SchemaStateError::LockError(_, problem) => {
+                match error::classify(&problem.to_string()) {
+                    ErrorKind::Other => ErrorKind::Lock,
+                    kind => kind,
+                }
+            }
+            SchemaStateError::AssertionError(..) => ErrorKind::AssertionFailed,
+            SchemaStateError::VerificationError(..) => ErrorKind::VerificationFailed,
+            SchemaStateError::BudgetExceeded(..) => ErrorKind::BudgetExceeded,
+            SchemaStateError::RequirementError(..) => ErrorKind::DependencyFailed,
+        }
+    }
+
+    /// Error of the failed requirement if this is `RequirementError` or this error otherwise.
+    pub fn root_cause(&self) -> &SchemaStateError {
+        match self {
+            SchemaStateError::RequirementError(_, error) => error.root_cause(),
+            error => error,
+        }
+    }
+}
+
+/// Function deciding on queries to run given check query result rows.
+type EnsureFn = dyn for<'h, 'c> Fn(
+    ResultSet<'h, 'c, ValueRow, Executed, DefaultConfiguration>,
+) -> Result<Vec<Sql>, Problem>;
+
+/// Function deciding on queries to run given collected check query result rows and database
+/// handle for follow-up read queries.
+type InspectFn = dyn Fn(Vec<ValueRow>, &mut Handle<'_>) -> Result<Vec<Sql>, Problem>;
+
+/// Function deciding on queries to run given context set with `EnsureOptions::context` and check
+/// query result rows.
+type ContextFn = dyn for<'h, 'c> Fn(
+    Option<&dyn Any>,
+    ResultSet<'h, 'c, ValueRow, Executed, DefaultConfiguration>,
+) -> Result<Vec<Sql>, Problem>;
+
+/// Function deciding on queries to run given integer value of the single check row.
+type ScalarFn = dyn Fn(Option<i64>) -> Result<Vec<Sql>, Problem>;
+
+/// How meet queries are decided on given check query result.
+enum Decide {
+    Rows(Box<EnsureFn>),
+    Scalar(Box<ScalarFn>),
+    Inspect(Box<InspectFn>),
+    Context(Box<ContextFn>),
+}
+
+impl Decide {
+    /// Runs check query and decides on meet queries.
+    fn decide(
+        &self,
+        database: &mut Handle<'_>,
+        check_query: &Sql,
+        context: Option<&dyn Any>,
+        max_rows: Option<usize>,
+    ) -> Result<Vec<Sql>, Problem> {
+        check_query.validate()?;
+        match self {
+            Decide::Rows(ensure) => ensure(database.query(check_query)?),
+            Decide::Scalar(ensure) => ensure(database.query::<Scalar>(check_query)?.single()?.0),
+            Decide::Inspect(inspect) => {
+                let rows = match max_rows {
+                    Some(max_rows) => {
+                        let mut rows = database
+                            .query::<ValueRow>(check_query)?
+                            .take(max_rows + 1)
+                            .collect::<Result<Vec<_>, _>>()?;
+                        if rows.len() > max_rows {
+                            warn!(
+                                "Check query returned more than {} rows, ignoring the rest: {}",
+                                max_rows, check_query
+                            );
+                            rows.truncate(max_rows);
+                        }
+                        rows
+                    }
+                    None => state::collect_rows(database.query(check_query)?)?,
+                };
+                inspect(rows, database)
+            }
+            Decide::Context(ensure) => ensure(context, database.query(check_query)?),
+        }
+    }
+}
+
+/// Decision on whether to execute meet queries of an object.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    /// Execute the meet queries.
+    Apply,
+    /// Do not meet this object but continue with the run.
+    Skip,
+    /// Fail the run with `MeetError`.
+    Abort,
+}
+
+/// Function deciding if meet queries of named object should be executed.
+type ConfirmFn = dyn Fn(&str, &[Sql]) -> Decision;
+
+/// Function invoked with object name and its meet queries before destructive ones are executed.
+type BackupFn = dyn Fn(&mut Handle<'_>, &str, &[Sql]) -> Result<(), Problem>;
+
+/// Shared callback stored in `EnsureOptions`.
+struct Hook<F: ?Sized>(Arc<F>);
+
+impl<F: ?Sized> Clone for Hook<F> {
+    fn clone(&self) -> Hook<F> {
+        Hook(self.0.clone())
+    }
+}
+
+impl<F: ?Sized> fmt::Debug for Hook<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Hook")
+    }
+}
+
+/// Options controlling how `EnsureSchema` is ensured.
+#[derive(Debug, Default, Clone)]
+pub struct EnsureOptions {
+    dry_run: bool,
+    force: bool,
+    state_table: Option<StateTable>,
+    audit_table: Option<AuditTable>,
+    report_table: Option<ReportTable>,
+    lock_table: Option<LockTable>,
+    follow: Option<(Duration, Duration)>,
+    run_id: Option<String>,
+    initiator: Option<String>,
+    annotate_queries: bool,
+    confirm: Option<Hook<ConfirmFn>>,
+    approval_policy: Option<Hook<dyn ApprovalPolicy>>,
+    allow_destructive: bool,
+    validate_sql: bool,
+    full_check: bool,
+    forbidden_kinds: Vec<StatementKind>,
+    max_statements: Option<usize>,
+    observer: Option<Hook<dyn Observer>>,
+    statement_redaction: StatementRedaction,
+    cancellation: Option<CancellationToken>,
+    wait_for_database: Option<RetryPolicy>,
+    dialect: Dialect,
+    required_privileges: Vec<Privilege>,
+    approved_plan: Option<String>,
+    backup: Option<Hook<BackupFn>>,
+    production_guard: Option<ProductionGuard>,
+    profile: Option<String>,
+    context: Option<Hook<dyn Any>>,
+    connection_target: Option<String>,
+    confirmation_token: Option<String>,
+    strict_checks: bool,
+    allowed_checks: Vec<Regex>,
+    eager_requirements: bool,
+    verify_retry: Option<RetryPolicy>,
+    batch_meet_queries: bool,
+    keep_alive: Option<KeepAlive>,
+    max_check_rows: Option<usize>,
+    time_budget: Option<Duration>,
+    maintenance_window: bool,
+    require_maintenance_window: bool,
+    /// Objects visited before reconnecting.
+    resume: Option<Arc<BTreeMap<String, ObjectReport>>>,
+}
+
+impl EnsureOptions {
+    /// If set to `true` no actual initialization queries are executed.
+    pub fn dry_run(mut self, dry_run: bool) -> EnsureOptions {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// If set to `true` the check is bypassed and declared meet queries are executed
+    /// unconditionally (still honoring dry run); requirements are ensured normally.
+    ///
+    /// This is useful for repairing objects that exist but are broken.
+    pub fn force(mut self, force: bool) -> EnsureOptions {
+        self.force = force;
+        self
+    }
+
+    /// Uses given `StateTable` to skip all checks if the definition fingerprint matches the one
+    /// stored after last successful run and it is still fresh; not used in dry run.
+    ///
+    /// Every object verified to be met during the run is recorded in the table.
+    ///
+    /// Fingerprint covers the definition only, not the database: an object that was dropped or
+    /// has drifted since it was recorded is not noticed until the fingerprint is no longer fresh
+    /// (see `StateTable::with_freshness`) or checks are run with `full_check`.
+    pub fn state_table(mut self, state_table: StateTable) -> EnsureOptions {
+        self.state_table = Some(state_table);
+        self
+    }
+
+    /// If set to `true` all checks are run even if `state_table` fingerprint is fresh; objects
+    /// are still recorded. This is used by `EnsureSchema::watch` to notice drift.
+    pub fn full_check(mut self, full_check: bool) -> EnsureOptions {
+        self.full_check = full_check;
+        self
+    }
+
+    /// Records every run that is not a dry run in given `AuditTable`; the table is created
+    /// before the run if needed and the run fails if that is not possible.
+    pub fn audit_table(mut self, audit_table: AuditTable) -> EnsureOptions {
+        self.audit_table = Some(audit_table);
+        self
+    }
+
+    /// Stores report of every run that is not a dry run in given `ReportTable`; the table is
+    /// created before the run if needed and the run fails if that is not possible.
+    pub fn report_table(mut self, report_table: ReportTable) -> EnsureOptions {
+        self.report_table = Some(report_table);
+        self
+    }
+
+    /// Serializes runs not being dry run using given `LockTable`; lock named after the ensured
+    /// object is held for the duration of the run and renewed between statements.
+    ///
+    /// While a statement is running the lock is renewed over the `keep_alive` connection (which
+    /// needs to connect to the database holding the lock table); without it the lock TTL must
+    /// exceed the longest statement and the run fails if the lock was not renewed within it.
+    pub fn lock_table(mut self, lock_table: LockTable) -> EnsureOptions {
+        self.lock_table = Some(lock_table);
+        self
+    }
+
+    /// When the lock is held by another process wait for it to meet the schema by polling the
+    /// check every `poll_interval` up to `timeout` instead of failing; if the lock gets released
+    /// in the meantime this process takes over.
+    pub fn follow(mut self, timeout: Duration, poll_interval: Duration) -> EnsureOptions {
+        self.follow = Some((timeout, poll_interval));
+        self
+    }
+
+    /// Sets operator supplied description of who or what has initiated the run; it is recorded
+    /// in `RunMetadata` of the report and in the audit table.
+    pub fn initiator(mut self, initiator: impl Into<String>) -> EnsureOptions {
+        self.initiator = Some(initiator.into());
+        self
+    }
+
+    /// If set to `true` meet queries are prefixed with a comment identifying the run, host,
+    /// process and initiator so they can be attributed in database logs.
+    pub fn annotate_queries(mut self, annotate_queries: bool) -> EnsureOptions {
+        self.annotate_queries = annotate_queries;
+        self
+    }
+
+    /// Calls given function with object name and its meet queries before they are executed
+    /// (not in dry run) so the run can be confirmed interactively per object.
+    pub fn confirm(
+        mut self,
+        confirm: impl Fn(&str, &[Sql]) -> Decision + 'static,
+    ) -> EnsureOptions {
+        self.confirm = Some(Hook(Arc::new(confirm)));
+        self
+    }
+
+    /// Destructive meet statements (see `is_destructive`) are refused with `MeetError` unless
+    /// this is set to `true`; in dry run they are only logged.
+    ///
+    /// Steps that do not declare their statements (see `Ensure::statements`) may execute
+    /// anything so they are refused too.
+    pub fn allow_destructive(mut self, allow_destructive: bool) -> EnsureOptions {
+        self.allow_destructive = allow_destructive;
+        self
+    }
+
+    /// If set to `true` check and declared meet queries are validated with
+    /// `EnsureSchema::validate_sql` in configured dialect before the run touches the database.
+    ///
+    /// The validation is a heuristic that may reject valid statements (e.g. dollar quoted
+    /// strings) so it is not enabled by default.
+    pub fn validate_sql(mut self, validate_sql: bool) -> EnsureOptions {
+        self.validate_sql = validate_sql;
+        self
+    }
+
+    /// Declares that the run happens in maintenance window so objects declared
+    /// `Concurrency::Exclusive` are met without warning.
+    pub fn maintenance_window(mut self, maintenance_window: bool) -> EnsureOptions {
+        self.maintenance_window = maintenance_window;
+        self
+    }
+
+    /// Meets of objects declared `Concurrency::Exclusive` are refused with `MeetError` unless
+    /// `maintenance_window` is set; by default they are only logged with warning.
+    pub fn require_maintenance_window(mut self, require: bool) -> EnsureOptions {
+        self.require_maintenance_window = require;
+        self
+    }
+
+    /// Calls given function with object name and its meet queries after the object was approved
+    /// but before its meet queries are executed if any of them is destructive (not in dry run).
+    ///
+    /// This allows to copy or backup affected tables; if the function fails the object is not met
+    /// and the run fails with `MeetError`.
+    pub fn backup(
+        mut self,
+        backup: impl Fn(&mut Handle<'_>, &str, &[Sql]) -> Result<(), Problem> + 'static,
+    ) -> EnsureOptions {
+        self.backup = Some(Hook(Arc::new(backup)));
+        self
+    }
+
+    /// Refuses to meet objects with `MeetError` if the connection target matches patterns of
+    /// given `ProductionGuard` and its confirmation token was not provided; not used in dry run.
+    ///
+    /// If connection target is not set with `connection_target` the database is considered
+    /// protected.
+    pub fn production_guard(mut self, guard: ProductionGuard) -> EnsureOptions {
+        self.production_guard = Some(guard);
+        self
+    }
+
+    /// Sets connection string or DSN the handle is connected to as used by `ProductionGuard`.
+    pub fn connection_target(mut self, target: impl Into<String>) -> EnsureOptions {
+        self.connection_target = Some(target.into());
+        self
+    }
+
+    /// Sets confirmation token required by `ProductionGuard` to meet protected database.
+    pub fn confirmation_token(mut self, token: impl Into<String>) -> EnsureOptions {
+        self.confirmation_token = Some(token.into());
+        self
+    }
+
+    /// Refuses meet statements of given kind (see `statement_kind`) with `MeetError`; in dry run
+    /// they are only logged. Can be called multiple times to forbid more kinds.
+    ///
+    /// This is useful when the account may only seed data while structural changes are owned by
+    /// a separate process or vice versa.
+    pub fn forbid(mut self, kind: StatementKind) -> EnsureOptions {
+        if !self.forbidden_kinds.contains(&kind) {
+            self.forbidden_kinds.push(kind);
+        }
+        self
+    }
+
+    /// If set to `true` check queries that are not read only (see `is_read_only`) are refused
+    /// with `CheckError` before they are executed so that checks can be safely run in dry run.
+    ///
+    /// Use `allow_check` to permit checks that are misclassified.
+    pub fn strict_checks(mut self, strict_checks: bool) -> EnsureOptions {
+        self.strict_checks = strict_checks;
+        self
+    }
+
+    /// Stops reading check rows collected for inspection functions (see
+    /// `EnsureSchema::with_inspection`) after given number of rows; only that many rows are
+    /// passed to the function. This guards against a mistaken check streaming a huge table.
+    ///
+    /// Ensure functions receiving result set read as many rows as they consume.
+    pub fn max_check_rows(mut self, max_check_rows: usize) -> EnsureOptions {
+        self.max_check_rows = Some(max_check_rows);
+        self
+    }
+
+    /// Permits check queries matching given pattern in strict checks mode; can be called
+    /// multiple times.
+    pub fn allow_check(mut self, pattern: Regex) -> EnsureOptions {
+        self.allowed_checks.push(pattern);
+        self
+    }
+
+    /// If set to `true` requirements of every object are ensured before its check instead of only
+    /// when the object needs to be met, so that drifted requirements of met objects are repaired.
+    ///
+    /// Can be enabled for individual objects with `EnsureSchema::with_eager_requirements`.
+    pub fn eager_requirements(mut self, eager_requirements: bool) -> EnsureOptions {
+        self.eager_requirements = eager_requirements;
+        self
+    }
+
+    /// Retries verification of every met object accordingly to given `RetryPolicy` (e.g.
+    /// `RetryPolicy::new(5).with_fixed_delay(..)`) unless the object has its own
+    /// (see `EnsureSchema::with_verify_retry`) or skips verification.
+    ///
+    /// Some drivers need a moment before new objects are visible to catalog queries. Note that
+    /// failed verification has no SQLSTATE so policy should not restrict retryable SQLSTATEs.
+    pub fn verify_retry(mut self, retry: RetryPolicy) -> EnsureOptions {
+        self.verify_retry = Some(retry);
+        self
+    }
+
+    /// If set to `true` meet queries of an object are executed as a single batch where dialect
+    /// supports it (see `Dialect::batch`) to save roundtrips; affected rows are not reported and
+    /// statement durations are averaged.
+    ///
+    /// If the batch fails queries are executed one by one to pinpoint the failing one; this is
+    /// only safe if the batch is atomic (e.g. with Postgres) or the queries are idempotent.
+    pub fn batch_meet_queries(mut self, batch_meet_queries: bool) -> EnsureOptions {
+        self.batch_meet_queries = batch_meet_queries;
+        self
+    }
+
+    /// Pings secondary connection of given `KeepAlive` while meet statements and steps are
+    /// running and notifies `Observer::statement_running` every keep-alive interval so that long
+    /// running statements (e.g. index builds) can be told from a hang; the run lock (see
+    /// `lock_table`) is renewed over it as well.
+    pub fn keep_alive(mut self, keep_alive: KeepAlive) -> EnsureOptions {
+        self.keep_alive = Some(keep_alive);
+        self
+    }
+
+    /// Stops the run with `SchemaStateError::BudgetExceeded` before starting next object once
+    /// given wall-clock time has passed since the run started; statements are never interrupted.
+    ///
+    /// Objects that were not reached are reported as `ObjectState::NotAttempted`.
+    pub fn time_budget(mut self, time_budget: Duration) -> EnsureOptions {
+        self.time_budget = Some(time_budget);
+        self
+    }
+
+    /// Fails the run with `MeetError` before meeting an object would make the total number of
+    /// meet statements executed in the run exceed given limit.
+    ///
+    /// This guards against a broken check that always reports the object as not met.
+    pub fn max_statements(mut self, max_statements: usize) -> EnsureOptions {
+        self.max_statements = Some(max_statements);
+        self
+    }
+
+    /// Sets `ApprovalPolicy` consulted for every meet statement before it is executed (not in
+    /// dry run); it is consulted before the confirmation callback.
+    pub fn approval_policy(
+        mut self,
+        approval_policy: impl ApprovalPolicy + 'static,
+    ) -> EnsureOptions {
+        self.approval_policy = Some(Hook(Arc::new(approval_policy)));
+        self
+    }
+
+    /// Sets `Observer` notified about the run, each visited object and executed statement.
+    pub fn observer(mut self, observer: impl Observer + 'static) -> EnsureOptions {
+        self.observer = Some(Hook(Arc::new(observer)));
+        self
+    }
+
+    /// Sets how statement text is exposed to `Observer`; by default literals are redacted.
+    pub fn statement_redaction(mut self, statement_redaction: StatementRedaction) -> EnsureOptions {
+        self.statement_redaction = statement_redaction;
+        self
+    }
+
+    /// Stops the run with error before next check or meet statement once given token is
+    /// cancelled; objects already met stay met.
+    pub fn cancellation(mut self, cancellation: CancellationToken) -> EnsureOptions {
+        self.cancellation = Some(cancellation);
+        self
+    }
+
+    /// Before anything else is done, pings the database (see `wait_for_database`) retrying
+    /// accordingly to given `RetryPolicy` and fails with `CheckError` if it is not responding.
+    pub fn wait_for_database(mut self, retry: RetryPolicy) -> EnsureOptions {
+        self.wait_for_database = Some(retry);
+        self
+    }
+
+    /// Selects profile (e.g. `dev` or `prod`) for which objects use meet queries set with
+    /// `EnsureSchema::with_profile_meet_queries`.
+    pub fn profile(mut self, profile: impl Into<String>) -> EnsureOptions {
+        self.profile = Some(profile.into());
+        self
+    }
+
+    /// Sets SQL dialect of the database used for dialect specific features.
+    pub fn dialect(mut self, dialect: Dialect) -> EnsureOptions {
+        self.dialect = dialect;
+        self
+    }
+
+    /// Verifies that given `Privilege` is held before first meet query of the run is executed
+    /// (not in dry run) and fails with `MeetError` if it is not; can be called multiple times.
+    ///
+    /// Privileges that cannot be verified in configured dialect are skipped with a warning.
+    pub fn require_privilege(mut self, privilege: Privilege) -> EnsureOptions {
+        self.required_privileges.push(privilege);
+        self
+    }
+
+    /// Requires the run to apply exactly the plan with given hash (see `EnsureReport::plan_hash`
+    /// of a dry run); the plan is regenerated first and if it differs (e.g. definitions or the
+    /// database state have changed since it was reviewed) the run fails with `MeetError` before
+    /// anything is executed. Not used in dry run.
+    pub fn approved_plan(mut self, plan_hash: impl Into<String>) -> EnsureOptions {
+        self.approved_plan = Some(plan_hash.into());
+        self
+    }
+
+    /// Sets application context (e.g. configuration) passed to ensure functions of objects
+    /// created with `EnsureSchema::with_context`.
+    pub fn context<C: Any>(mut self, context: C) -> EnsureOptions {
+        self.context = Some(Hook(Arc::new(context)));
+        self
+    }
+
+    fn context_ref(&self) -> Option<&dyn Any> {
+        self.context.as_ref().map(|context| &*context.0)
+    }
+
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.cancellation
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled)
+    }
+
+    /// Sets identifier of the run recorded in the state table and report; by default one is
+    /// generated.
+    ///
+    /// Run fails with `CheckError` if it contains characters other than ASCII letters, digits,
+    /// `.`, `_` and `-`.
+    pub fn run_id(mut self, run_id: impl Into<String>) -> EnsureOptions {
+        self.run_id = Some(run_id.into());
+        self
+    }
+}
+
+/// Represents database operations needed to potentially initialize some schema object.
+pub struct EnsureSchema {
+    /// Reference name of the schema object.
+    pub name: String,
+    node: Node,
+    /// If there are queries to be run then this Schemas are ensured first.
+    meet_require: Vec<Require>,
+    /// Requirements are ensured even if this object is met.
+    eager_requirements: bool,
+    /// How to verify the object after meet.
+    verification: Verification,
+    /// Run after the object was changed.
+    smoke_tests: Vec<SmokeTest>,
+    /// Maintenance statements executed after the object was changed.
+    maintenance: Vec<Sql>,
+    /// If meet statements can run concurrently with application traffic.
+    concurrency: Concurrency,
+    /// Held while meet statements are executed.
+    table_locks: Vec<TableLock>,
+}
+
+/// Requirement of `EnsureSchema`.
+enum Require {
+    Schema(Box<EnsureSchema>),
+    /// Built only when the requirement needs to be ensured.
+    Lazy(Box<dyn Fn() -> EnsureSchema>),
+}
+
+impl fmt::Debug for Require {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Require::Schema(schema) => schema.fmt(fmt),
+            Require::Lazy(_) => write!(fmt, "Lazy"),
+        }
+    }
+}
+
+impl Require {
+    fn ensure_tree(
+        &self,
+        database: &mut Handle<'_>,
+        run: &Run<'_, '_>,
+        force: bool,
+    ) -> Result<SchemaState, SchemaStateError> {
+        match self {
+            Require::Schema(schema) => schema.ensure_tree(database, run, force),
+            Require::Lazy(build) => build().ensure_tree(database, run, force),
+        }
+    }
+}
+
+/// How `EnsureSchema` is verified after meet.
+#[derive(Debug)]
+enum Verification {
+    /// Check is run once and has to pass.
+    Once,
+    /// Check is retried until it passes.
+    Retry(RetryPolicy),
+    /// Meet queries are idempotent and not verified.
+    Skip,
+}
+
+/// What is checked and met by `EnsureSchema`.
+enum Node {
+    Query {
+        /// Query to run to see if we need to do anything; rows provided by this query are passed
+        /// to ensure function.
+        check_query: Sql,
+        /// This is run with output of check_query to determine what needs to be done; if empty
+        /// Vec is returned then nothing needs to be done otherwise each returned query is
+        /// executed.
+        ensure: Decide,
+        /// Meet queries known upfront; these are executed when check is bypassed with force
+        /// option.
+        meet_queries: Vec<Sql>,
+        /// Meet queries executed instead of the decided ones when run with given profile.
+        profiles: Vec<(String, Vec<Sql>)>,
+    },
+    /// Members ensured in order; if `independent` is set all members are ensured even if some
+    /// fail otherwise members are ensured only after previous one was met.
+    Group {
+        members: Vec<EnsureSchema>,
+        independent: bool,
+    },
+    /// Custom step.
+    Step(Box<dyn Ensure>),
+    /// Object of the same name defined elsewhere in the tree being ensured (left by
+    /// `EnsureSchema::merge` in place of overridden definition).
+    Reference,
+}
+
+impl fmt::Debug for EnsureSchema {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let mut debug = fmt.debug_struct("EnsureSchema");
+        debug.field("name", &self.name);
+        match &self.node {
+            Node::Query {
+                check_query,
+                meet_queries,
+                ..
+            } => debug
+                .field("check_query", check_query)
+                .field("meet_queries", meet_queries),
+            Node::Group {
+                members,
+                independent,
+            } => debug
+                .field("members", members)
+                .field("independent", independent),
+            Node::Step(step) => debug.field("step", step),
+            Node::Reference => debug.field("reference", &true),
+        };
+        debug
+            .field("meet_require", &self.meet_require)
+            .field("eager_requirements", &self.eager_requirements)
+            .field("verification", &self.verification)
+            .field("smoke_tests", &self.smoke_tests)
+            .field("maintenance", &self.maintenance)
+            .field("concurrency", &self.concurrency)
+            .field("table_locks", &self.table_locks)
+            .finish()
+    }
+}
+
+impl EnsureSchema {
+    /// Object of given name and node with no requirements and default options.
+    fn base(name: String, node: Node) -> EnsureSchema {
+        EnsureSchema {
+            name,
+            node,
+            meet_require: Vec::new(),
+            eager_requirements: false,
+            verification: Verification::Once,
+            smoke_tests: Vec::new(),
+            maintenance: Vec::new(),
+            concurrency: Concurrency::Online,
+            table_locks: Vec::new(),
+        }
+    }
+
+    /// Creates `EnsureSchema` given name, SQL query strings that will be run to verify if the object is
+    /// initialized and block of code that will get resulting check rows to
+    /// return one or more initialization SQL query strings if needed.
+    pub fn new(
+        name: impl Into<String>,
+        check_query: impl Into<Sql>,
+        ensure: impl for<'h, 'c> Fn(
+                ResultSet<'h, 'c, ValueRow, Executed, DefaultConfiguration>,
+            ) -> Result<Vec<Sql>, Problem>
+            + 'static,
+    ) -> EnsureSchema {
+        EnsureSchema::base(
+            name.into(),
+            Node::Query {
+                check_query: check_query.into(),
+                ensure: Decide::Rows(Box::new(ensure)),
+                meet_queries: Vec::new(),
+                profiles: Vec::new(),
+            },
+        )
+    }
+
+    /// Creates `EnsureSchema` given name, check query and block of code that will get all
+    /// resulting check rows together with the database handle so it can run follow-up queries
+    /// (e.g. inspect columns of a table found to exist) before deciding on initialization queries.
+    ///
+    /// Follow-up queries are also run in dry run and during verification so they should only read.
+    pub fn with_inspection(
+        name: impl Into<String>,
+        check_query: impl Into<Sql>,
+        inspect: impl Fn(Vec<ValueRow>, &mut Handle<'_>) -> Result<Vec<Sql>, Problem> + 'static,
+    ) -> EnsureSchema {
+        EnsureSchema::base(
+            name.into(),
+            Node::Query {
+                check_query: check_query.into(),
+                ensure: Decide::Inspect(Box::new(inspect)),
+                meet_queries: Vec::new(),
+                profiles: Vec::new(),
+            },
+        )
+    }
+
+    /// Creates `EnsureSchema` like `new` but the ensure function is also given reference to the
+    /// context set with `EnsureOptions::context` (e.g. tenant name or retention days) so that it
+    /// does not need to capture copies of application configuration.
+    ///
+    /// Check fails if the context was not set or has different type; `is_met` is not supported.
+    pub fn with_context<C: Any>(
+        name: impl Into<String>,
+        check_query: impl Into<Sql>,
+        ensure: impl for<'h, 'c> Fn(
+                &C,
+                ResultSet<'h, 'c, ValueRow, Executed, DefaultConfiguration>,
+            ) -> Result<Vec<Sql>, Problem>
+            + 'static,
+    ) -> EnsureSchema {
+        EnsureSchema::base(
+            name.into(),
+            Node::Query {
+                check_query: check_query.into(),
+                ensure: Decide::Context(Box::new(move |context, rows| {
+                    let context = context
+                        .and_then(|context| context.downcast_ref::<C>())
+                        .ok_or_else(|| {
+                            Problem::from_error(format!(
+                                "Ensure context of type {} was not set",
+                                type_name::<C>()
+                            ))
+                        })?;
+                    ensure(context, rows)
+                })),
+                meet_queries: Vec::new(),
+                profiles: Vec::new(),
+            },
+        )
+    }
+
+    /// Creates `EnsureSchema` given name, SQL query string that needs to produce one row with
+    /// BOOLEAN/BIT value indicating if initialisation is needed (0/false) or not (1/true) and list
+    /// of SQL query strings that need to be run to initialize the object.
+    ///
+    /// The value is fetched directly as integer so drivers reporting booleans as integers work too.
+    pub fn with_bool_check(
+        name: impl Into<String>,
+        check_query: impl Into<Sql>,
+        meet_queries: impl IntoIterator<Item = impl Into<Sql>>,
+    ) -> EnsureSchema {
+        EnsureSchema::with_scalar_check(name, check_query, meet_queries)
+    }
+
+    /// Creates `EnsureSchema` given name, SQL query string that needs to produce one row with
+    /// count of existing objects (e.g. `SELECT COUNT(*) FROM information_schema.tables WHERE ..`)
+    /// and list of SQL query strings that are run to initialize the object if the count is zero.
+    pub fn with_count_check(
+        name: impl Into<String>,
+        check_query: impl Into<Sql>,
+        meet_queries: impl IntoIterator<Item = impl Into<Sql>>,
+    ) -> EnsureSchema {
+        EnsureSchema::with_scalar_check(name, check_query, meet_queries)
+    }
+
+    /// Object met if first column of the single check row is not zero; the value is fetched
+    /// directly as integer without materializing `ValueRow`.
+    fn with_scalar_check(
+        name: impl Into<String>,
+        check_query: impl Into<Sql>,
+        meet_queries: impl IntoIterator<Item = impl Into<Sql>>,
+    ) -> EnsureSchema {
+        let meet_queries = meet_queries
+            .into_iter()
+            .map(Into::into)
+            .collect::<Arc<[Sql]>>();
+        let declared = meet_queries.to_vec();
+        EnsureSchema::base(
+            name.into(),
+            Node::Query {
+                check_query: check_query.into(),
+                ensure: Decide::Scalar(Box::new(move |value| match value {
+                    Some(0) => Ok(meet_queries.to_vec()),
+                    Some(_) => Ok(vec![]),
+                    None => problem!("Check query returned NULL"),
+                })),
+                meet_queries: declared,
+                profiles: Vec::new(),
+            },
+        )
+    }
+
+    /// Creates `EnsureSchema` like `with_bool_check` given SQL script that is split into meet
+    /// queries (see `split_script`); this allows existing initialization scripts to be used.
+    pub fn from_sql_script(
+        name: impl Into<String>,
+        check_query: impl Into<Sql>,
+        script: &str,
+    ) -> Result<EnsureSchema, Problem> {
+        let meet_queries = split_script(script)?;
+        if meet_queries.is_empty() {
+            return problem!("SQL script has no statements");
+        }
+        Ok(EnsureSchema::with_bool_check(
+            name,
+            check_query,
+            meet_queries,
+        ))
+    }
+
+    /// Creates `EnsureSchema` given name and idempotent meet queries (e.g. guarded with
+    /// `IF NOT EXISTS`) that are executed on every run without a check and not verified.
+    pub fn idempotent(
+        name: impl Into<String>,
+        meet_queries: impl IntoIterator<Item = impl Into<Sql>>,
+    ) -> EnsureSchema {
+        let meet_queries = meet_queries
+            .into_iter()
+            .map(Into::into)
+            .collect::<Vec<Sql>>();
+        let declared = meet_queries.clone();
+        let mut schema = Self::new(name, "SELECT 1", move |_rows| Ok(meet_queries.clone()))
+            .with_declared_meet_queries(declared);
+        schema.verification = Verification::Skip;
+        schema
+    }
+
+    /// Sets meet queries known upfront (used with force option and in fingerprint).
+    pub(crate) fn with_declared_meet_queries(mut self, declared: Vec<Sql>) -> EnsureSchema {
+        if let Node::Query { meet_queries, .. } = &mut self.node {
+            *meet_queries = declared;
+        }
+        self
+    }
+
+    /// Sets meet queries to be executed instead of the decided (or declared) ones when the object
+    /// is not met and ensured with given profile (see `EnsureOptions::profile`); e.g. to create
+    /// smaller tables in development environment.
+    ///
+    /// Has no effect on groups and steps.
+    pub fn with_profile_meet_queries(
+        mut self,
+        profile: impl Into<String>,
+        queries: impl IntoIterator<Item = impl Into<Sql>>,
+    ) -> EnsureSchema {
+        if let Node::Query { profiles, .. } = &mut self.node {
+            let profile = profile.into();
+            profiles.retain(|(name, _)| name != &profile);
+            profiles.push((profile, queries.into_iter().map(Into::into).collect()));
+        }
+        self
+    }
+
+    /// Creates named group of objects that are ensured in order, each only after previous one
+    /// was met; group is met when all its members are met.
+    ///
+    /// Requirements added to a group with `with_meet_require` are ensured before its members.
+    pub fn sequence(name: impl Into<String>, members: Vec<EnsureSchema>) -> EnsureSchema {
+        EnsureSchema::group(name.into(), members, false)
+    }
+
+    /// Creates named group of independent objects; all members are ensured even if some of them
+    /// fail in which case first error is returned.
+    pub fn all(name: impl Into<String>, members: Vec<EnsureSchema>) -> EnsureSchema {
+        EnsureSchema::group(name.into(), members, true)
+    }
+
+    fn group(name: String, members: Vec<EnsureSchema>, independent: bool) -> EnsureSchema {
+        EnsureSchema::base(
+            name,
+            Node::Group {
+                members,
+                independent,
+            },
+        )
+    }
+
+    /// Creates `EnsureSchema` from custom `Ensure` step so it can be combined with other objects
+    /// and have requirements.
+    pub fn step(step: impl Ensure + 'static) -> EnsureSchema {
+        EnsureSchema::base(step.name().to_string(), Node::Step(Box::new(step)))
+    }
+
+    /// Makes sure that custom `Ensure` step is met before this one if this one needs to be
+    /// initialized.
+    pub fn with_meet_require_step(self, step: impl Ensure + 'static) -> EnsureSchema {
+        self.with_meet_require(EnsureSchema::step(step))
+    }
+
+    /// Combines this object with another one that is ensured only after this one is met; the
+    /// result is a sequence (see `sequence`) that yields single `SchemaState`.
+    ///
+    /// Chaining more objects extends the sequence.
+    pub fn then(self, next: EnsureSchema) -> EnsureSchema {
+        self.combine(next, false, "then")
+    }
+
+    /// Combines this object with an independent sibling (see `all`).
+    ///
+    /// Combining more objects extends the group.
+    pub fn and(self, sibling: EnsureSchema) -> EnsureSchema {
+        self.combine(sibling, true, "and")
+    }
+
+    fn combine(mut self, other: EnsureSchema, independent: bool, word: &str) -> EnsureSchema {
+        if let Node::Group {
+            members,
+            independent: group_independent,
+        } = &mut self.node
+        {
+            if *group_independent == independent && self.meet_require.is_empty() {
+                self.name = format!("{} {} {}", self.name, word, other.name);
+                members.push(other);
+                return self;
+            }
+        }
+        EnsureSchema::group(
+            format!("{} {} {}", self.name, word, other.name),
+            vec![self, other],
+            independent,
+        )
+    }
+
+    /// Makes sure that another object is initialized before this one if this one needs to be
+    /// initialized.
+    pub fn with_meet_require(mut self, schema: EnsureSchema) -> EnsureSchema {
+        self.meet_require.push(Require::Schema(Box::new(schema)));
+        self
+    }
+
+    /// Like `with_meet_require` but the requirement is built with given function only when this
+    /// object needs to be initialized; this avoids expensive construction (e.g. loading files)
+    /// when everything is met.
+    ///
+    /// Lazy requirements are not part of the fingerprint.
+    pub fn with_meet_require_lazy(
+        mut self,
+        build: impl Fn() -> EnsureSchema + 'static,
+    ) -> EnsureSchema {
+        self.meet_require.push(Require::Lazy(Box::new(build)));
+        self
+    }
+
+    /// Ensures requirements before the check so that they are repaired even if this object is
+    /// met (see `EnsureOptions::eager_requirements`).
+    pub fn with_eager_requirements(mut self) -> EnsureSchema {
+        self.eager_requirements = true;
+        self
+    }
+
+    /// Retries verification after meet queries were executed accordingly to given `RetryPolicy`
+    /// instead of failing immediately.
+    ///
+    /// This is needed for backends where metadata changes propagate with a lag (e.g. Hive or data
+    /// warehouses).
+    pub fn with_verify_retry(mut self, retry: RetryPolicy) -> EnsureSchema {
+        self.verification = Verification::Retry(retry);
+        self
+    }
+
+    /// Computes fingerprint of the whole definition tree based on names and normalized SQL text
+    /// (see `normalize_sql`) so that cosmetic edits do not change it.
+    ///
+    /// Note that logic of ensure function is not covered as only its SQL queries are known.
+    pub fn fingerprint(&self) -> String {
+        let mut hasher = Fnv64::new();
+        self.hash_definition(&mut hasher);
+        hasher.finish_hex()
+    }
+
+    fn hash_definition(&self, hasher: &mut Fnv64) {
+        hasher.write_str(&self.name);
+        match &self.node {
+            Node::Query {
+                check_query,
+                meet_queries,
+                profiles,
+                ..
+            } => {
+                hasher.write_str(&check_query.normalized());
+                for meet_query in meet_queries {
+                    hasher.write_str(&meet_query.normalized());
+                }
+                for (profile, queries) in profiles {
+                    hasher.write_str(profile);
+                    for query in queries {
+                        hasher.write_str(&query.normalized());
+                    }
+                }
+            }
+            Node::Group {
+                members,
+                independent,
+            } => {
+                hasher.write_str(if *independent { "all" } else { "sequence" });
+                for member in members {
+                    member.hash_definition(hasher);
+                }
+            }
+            Node::Step(_) => hasher.write_str("step"),
+            Node::Reference => hasher.write_str("reference"),
+        }
+        for test in &self.smoke_tests {
+            hasher.write_str(&test.name);
+            hasher.write_str(&test.query.normalized());
+            hasher.write_str(&format!("{:?}", test.expectation));
+        }
+        for statement in &self.maintenance {
+            hasher.write_str(&statement.normalized());
+        }
+        if self.concurrency == Concurrency::Exclusive {
+            hasher.write_str("exclusive");
+        }
+        for lock in &self.table_locks {
+            hasher.write_str(&lock.table);
+            hasher.write_str(&format!("{:?}", lock.mode));
+        }
+        for required in &self.meet_require {
+            match required {
+                Require::Schema(schema) => schema.hash_definition(hasher),
+                Require::Lazy(_) => hasher.write_str("lazy"),
+            }
+        }
+        hasher.write_str("");
+    }
+
+    /// Makes sure that the object is initialized by performing a check and necessary actions to
+    /// initialize the object accordingly to check result.
+    pub fn ensure(&self, database: &mut Handle<'_>) -> Result<SchemaState, SchemaStateError> {
+        self.ensure_with_dry_run(database, false)
+    }
+
+    /// Same as `ensure` but if `dry_run` is set to `true` no actual initialization queries are
+    /// executed.
+    pub fn ensure_with_dry_run(
+        &self,
+        database: &mut Handle<'_>,
+        dry_run: bool,
+    ) -> Result<SchemaState, SchemaStateError> {
+        self.ensure_with_options(database, &EnsureOptions::default().dry_run(dry_run))
+    }
+
+    /// Same as `ensure` but with behaviour controlled by given `EnsureOptions`.
+    pub fn ensure_with_options(
+        &self,
+        database: &mut Handle<'_>,
+        options: &EnsureOptions,
+    ) -> Result<SchemaState, SchemaStateError> {
+        self.ensure_with_report(database, options, &mut EnsureReport::default())
+    }
+
+    /// Same as `ensure_with_options` but also fills given `EnsureReport` with outcome of each
+    /// visited object; report is filled also when error is returned.
+    pub fn ensure_with_report(
+        &self,
+        database: &mut Handle<'_>,
+        options: &EnsureOptions,
+        report: &mut EnsureReport,
+    ) -> Result<SchemaState, SchemaStateError> {
+        self.ensure_run(database, None, options, report)
+    }
+
+    /// Same as `ensure_with_options` but uses own `Handle` of given connection.
+    pub fn ensure_with_connection(
+        &self,
+        connection: &mut Connection,
+        options: &EnsureOptions,
+    ) -> Result<SchemaState, SchemaStateError> {
+        self.ensure_with_options(&mut connection.handle(), options)
+    }
+
+    /// Connects to the database with given connection string and ensures the object filling the
+    /// report (see `ensure_with_connector`).
+    pub fn ensure_with_connection_string(
+        &self,
+        connection_string: &str,
+        options: &EnsureOptions,
+        retry: &RetryPolicy,
+        report: &mut EnsureReport,
+    ) -> Result<SchemaState, SchemaStateError> {
+        self.ensure_with_connector(
+            || connect_with_retry(connection_string, retry),
+            options,
+            retry,
+            report,
+        )
+    }
+
+    /// Connects to the database with given function and ensures the object filling the report.
+    ///
+    /// If the run fails with `ErrorKind::Connectivity` the database is reconnected and the run
+    /// resumed, up to the number of attempts of given `RetryPolicy`: objects already met, changed
+    /// or skipped are reported as before without being checked again while the object that was
+    /// in flight is checked again and met if needed.
+    pub fn ensure_with_connector(
+        &self,
+        mut connect: impl FnMut() -> Result<Connection, Problem>,
+        options: &EnsureOptions,
+        retry: &RetryPolicy,
+        report: &mut EnsureReport,
+    ) -> Result<SchemaState, SchemaStateError> {
+        let mut options = options.clone();
+        let mut visited = BTreeMap::new();
+        let mut attempt = 1;
+        loop {
+            let mut connection =
+                connect().map_err(|err| SchemaStateError::CheckError(self.name.clone(), err))?;
+            match self.ensure_with_report(&mut connection.handle(), &options, report) {
+                Err(err)
+                    if err.root_cause().kind() == ErrorKind::Connectivity
+                        && attempt < retry.attempts() =>
+                {
+                    warn!(
+                        "Run failed due to connectivity (attempt {} of {}), reconnecting: {}",
+                        attempt,
+                        retry.attempts(),
+                        err
+                    );
+                    for object in &report.objects {
+                        if let ObjectState::Met | ObjectState::Changed | ObjectState::Skipped =
+                            object.state
+                        {
+                            visited.insert(object.name.clone(), object.clone());
+                        }
+                    }
+                    options.resume = Some(Arc::new(visited.clone()));
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Same as `ensure_with_report` but meet statements, steps, backup hook and privilege
+    /// verification use `meet_database` while checks and verification use `database`.
+    ///
+    /// This allows checks to run with low-privilege connection and elevated one to be used only
+    /// when something needs to be initialized. State, audit and lock tables are accessed with
+    /// `database`.
+    pub fn ensure_with_meet_database(
+        &self,
+        database: &mut Handle<'_>,
+        meet_database: &mut Handle<'_>,
+        options: &EnsureOptions,
+        report: &mut EnsureReport,
+    ) -> Result<SchemaState, SchemaStateError> {
+        self.ensure_run(database, Some(meet_database), options, report)
+    }
+
+    fn ensure_run(
+        &self,
+        database: &mut Handle<'_>,
+        meet_database: Option<&mut Handle<'_>>,
+        options: &EnsureOptions,
+        report: &mut EnsureReport,
+    ) -> Result<SchemaState, SchemaStateError> {
+        if let Some(run_id) = options.run_id.as_deref().filter(|id| !is_valid_run_id(id)) {
+            return Err(SchemaStateError::CheckError(
+                self.name.clone(),
+                Problem::from_error(format!(
+                    "Invalid run id {:?}: only ASCII letters, digits, '.', '_' and '-' are allowed",
+                    run_id
+                )),
+            ));
+        }
+        if options.validate_sql {
+            let dialect = Some(options.dialect).filter(|dialect| *dialect != Dialect::Generic);
+            self.validate_sql(dialect)
+                .map_err(|err| SchemaStateError::CheckError(self.name.clone(), err))?;
+        }
+        if let Some(retry) = &options.wait_for_database {
+            wait_for_database(database, retry)
+                .map_err(|err| SchemaStateError::CheckError(self.name.clone(), err))?;
+        }
+
+        if let Some(approved_plan) = options.approved_plan.as_ref().filter(|_| !options.dry_run) {
+            let mut plan_options = options.clone().dry_run(true);
+            plan_options.approved_plan = None;
+            plan_options.wait_for_database = None;
+            plan_options.observer = None;
+            let mut plan = EnsureReport::default();
+            self.ensure_with_report(database, &plan_options, &mut plan)?;
+            let plan_hash = plan.plan_hash();
+            if &plan_hash != approved_plan {
+                return Err(SchemaStateError::MeetError(
+                    self.name.clone(),
+                    Problem::from_error(format!(
+                        "Plan has changed since it was approved: approved {} but current plan is {}",
+                        approved_plan, plan_hash
+                    )),
+                ));
+            }
+        }
+
+        let run_id = options.run_id.clone().unwrap_or_else(new_run_id);
+        let metadata = RunMetadata::collect(options.initiator.clone());
+        let lock = options
+            .lock_table
+            .as_ref()
+            .filter(|_| !options.dry_run)
+            .map(|table| HeldLock {
+                table,
+                name: self.name.clone(),
+                owner: format!(
+                    "{} {}:{}",
+                    run_id,
+                    metadata.host.as_deref().unwrap_or("unknown"),
+                    metadata.pid
+                ),
+                held: Cell::new(false),
+                renewed: Cell::new(Instant::now()),
+            });
+        let run = Run {
+            options,
+            root: self,
+            ensured: RefCell::new(Vec::new()),
+            meet_database: meet_database.map(RefCell::new),
+            dry_run: options.dry_run,
+            annotation: if options.annotate_queries {
+                Some(metadata.annotation(&run_id))
+            } else {
+                None
+            },
+            state_table: options
+                .state_table
+                .as_ref()
+                .filter(|_| !options.dry_run && !options.force),
+            lock,
+            executed: Cell::new(0),
+            privileges_verified: Cell::new(false),
+            deadline: options.time_budget.map(|budget| Instant::now() + budget),
+            depth: Cell::new(0),
+            report: RefCell::new(EnsureReport {
+                run_id,
+                dry_run: options.dry_run,
+                started_at: now_epoch(),
+                metadata,
+                objects: Vec::new(),
+                duration: Duration::default(),
+            }),
+        };
+
+        let audit_table = options.audit_table.as_ref().filter(|_| !options.dry_run);
+        if let Some(audit_table) = audit_table {
+            audit_table.ensure_schema().ensure(database)?;
+        }
+        let report_table = options.report_table.as_ref().filter(|_| !options.dry_run);
+        if let Some(report_table) = report_table {
+            report_table
+                .ensure_schema(options.dialect)
+                .ensure(database)?;
+        }
+
+        if let Some(observer) = run.observer() {
+            observer.run_started(&run.report.borrow().run_id, &self.name);
+        }
+
+        let started = Instant::now();
+        let result = (|| {
+            if let Some(lock) = &run.lock {
+                lock.table.ensure_schema().ensure(database)?;
+                if let Err(err) = lock.table.acquire(database, &lock.name, &lock.owner) {
+                    match options.follow {
+                        Some((timeout, poll_interval)) => {
+                            if !self.follow_lock(database, lock, timeout, poll_interval)? {
+                                let duration = started.elapsed();
+                                run.report(&self.name, ObjectState::Met, None, duration);
+                                return Ok(SchemaState::Ok);
+                            }
+                        }
+                        None => return Err(SchemaStateError::LockError(lock.name.clone(), err)),
+                    }
+                }
+                lock.held.set(true);
+                lock.renewed.set(Instant::now());
+            }
+
+            if let Some(state_table) = run.state_table {
+                if !options.full_check {
+                    match state_table.load(database, &self.name) {
+                        Ok(Some(entry)) if state_table.is_fresh(&entry, &self.fingerprint()) => {
+                            debug!("[+] Schema state fingerprint is fresh for: {}", self.name);
+                            run.report(&self.name, ObjectState::Met, None, started.elapsed());
+                            return Ok(SchemaState::Ok);
+                        }
+                        Ok(_) => (),
+                        Err(err) => {
+                            debug!("Could not load schema state for {}: {}", self.name, err)
+                        }
+                    }
+                }
+                state_table.prepare(database);
+            }
+
+            self.ensure_tree(database, &run, options.force)
+        })();
+
+        if let Some(lock) = run.lock.as_ref().filter(|lock| lock.held.get()) {
+            if let Err(err) = lock.table.release(database, &lock.name, &lock.owner) {
+                warn!("Failed to release lock {}: {}", lock.name, err);
+            }
         }
-    }
-}
 
-impl Error for SchemaStateError {}
+        *report = run.report.into_inner();
+        report.duration = started.elapsed();
+        let budget_exceeded = options
+            .time_budget
+            .is_some_and(|budget| report.duration >= budget);
+        if result.is_err() && budget_exceeded {
+            report.not_attempted(self);
+        }
+        if let Some(observer) = &options.observer {
+            let error = result.as_ref().err().map(|err| err.to_string());
+            observer.0.run_finished(report, error.as_deref());
+        }
 
-/// Represents database operations needed to potentially initialize some schema object.
-pub struct EnsureSchema {
-    /// Reference name of the schema object.
-    pub name: String,
-    /// Query to run to see if we need to do anything; rows provided by this query are passed to
-    /// ensure function.
-    check_query: Sql,
-    /// This is run with output of check_query to determine what needs to be done; if empty Vec is
-    /// returned then nothing needs to be done otherwise each returned query is executed.
-    ensure: Box<
-        dyn for<'h, 'c> Fn(
-            ResultSet<'h, 'c, ValueRow, Executed, DefaultConfiguration>,
-        ) -> Result<Vec<Sql>, Problem>,
-    >,
-    /// If there are queries to be run then this Schemas are ensured first.
-    meet_require: Vec<EnsureSchema>,
-}
+        if let Some(audit_table) = audit_table {
+            if let Err(err) = audit_table.record(database, report, result.as_ref().err()) {
+                error!(
+                    "Failed to record run {} in audit table: {}",
+                    report.run_id, err
+                );
+            }
+        }
+        if let Some(report_table) = report_table {
+            if let Err(err) = report_table.record(database, report, result.as_ref().err()) {
+                error!(
+                    "Failed to record run {} in report table: {}",
+                    report.run_id, err
+                );
+            }
+        }
+        result
+    }
 
-impl fmt::Debug for EnsureSchema {
-    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        fmt.debug_struct("EnsureSchema")
-            .field("name", &self.name)
-            .field("check_query", &self.check_query)
-            .field("meet_require", &self.meet_require)
-            .finish()
+    /// Performs dry run with given `EnsureOptions` and writes statements that would be executed to
+    /// `out` as SQL script (see `EnsureReport::write_script`).
+    ///
+    /// Script is written also if the run fails (e.g. on a check) before the error is returned.
+    pub fn write_dry_run_script(
+        &self,
+        database: &mut Handle<'_>,
+        options: &EnsureOptions,
+        out: &mut dyn io::Write,
+    ) -> Result<SchemaState, Problem> {
+        let mut report = EnsureReport::default();
+        let result = self.ensure_with_report(database, &options.clone().dry_run(true), &mut report);
+        report.write_script(out)?;
+        result.map_err(Problem::from_error)
     }
-}
 
-impl EnsureSchema {
-    /// Creates `EnsureSchema` given name, SQL query strings that will be run to verify if the object is
-    /// initialized and block of code that will get resulting check rows to
-    /// return one or more initialization SQL query strings if needed.
-    pub fn new(
-        name: String,
-        check_query: Sql,
-        ensure: impl for<'h, 'c> Fn(
-                ResultSet<'h, 'c, ValueRow, Executed, DefaultConfiguration>,
-            ) -> Result<Vec<Sql>, Problem>
-            + 'static,
-    ) -> EnsureSchema {
-        EnsureSchema {
-            name,
-            check_query,
-            ensure: Box::new(ensure),
-            meet_require: Vec::new(),
+    /// Runs the check of this object (not its requirements) and returns `true` if no meet
+    /// queries are needed; group is met if all its members are met.
+    pub fn is_met(&self, database: &mut Handle<'_>) -> Result<bool, SchemaStateError> {
+        match &self.node {
+            Node::Query {
+                check_query,
+                ensure,
+                ..
+            } => ensure
+                .decide(database, check_query, None, None)
+                .map(|meet_queries| meet_queries.is_empty())
+                .map_err(|err| SchemaStateError::CheckError(self.name.clone(), err)),
+            Node::Group { members, .. } => {
+                for member in members {
+                    if !member.is_met(database)? {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+            Node::Step(step) => step
+                .check(database)
+                .map_err(|err| SchemaStateError::CheckError(self.name.clone(), err)),
+            Node::Reference => Err(SchemaStateError::CheckError(
+                self.name.clone(),
+                Problem::from_error("Referenced object can only be checked as part of its tree"),
+            )),
         }
     }
 
-    /// Creates `EnsureSchema` given name, SQL query string that needs to produce one row with
-    /// BOOLEAN/BIT value indicating if initialisation is needed (0/false) or not (1/true) and list
-    /// of SQL query strings that need to be run to initialize the object.
-    pub fn with_bool_check(name: String, check_query: Sql, meet_queries: Vec<Sql>) -> EnsureSchema {
-        Self::new(name, check_query, move |rows| {
-            let result: bool = TryFromValueRow::try_from_value_row(rows.single()?)?;
-            Ok(if result { vec![] } else { meet_queries.clone() })
-        })
+    /// Polls the check until the object becomes met (e.g. by another process) or `timeout`
+    /// elapses.
+    pub fn wait_until_met(
+        &self,
+        database: &mut Handle<'_>,
+        timeout: Duration,
+        poll_interval: Duration,
+    ) -> Result<(), SchemaStateError> {
+        let started = Instant::now();
+        while !self.is_met(database)? {
+            if started.elapsed() >= timeout {
+                return Err(SchemaStateError::CheckError(
+                    self.name.clone(),
+                    Problem::from_error("Timed out waiting for schema state to be met"),
+                ));
+            }
+            sleep(poll_interval);
+        }
+        Ok(())
     }
 
-    /// Makes sure that another object is initialized before this one if this one needs to be
-    /// initialized.
-    pub fn with_meet_require(mut self, schema: EnsureSchema) -> EnsureSchema {
-        self.meet_require.push(schema);
-        self
+    /// Waits for lock owner to meet the schema; returns `true` if the lock was acquired in the
+    /// meantime and this process should meet it instead.
+    fn follow_lock(
+        &self,
+        database: &mut Handle<'_>,
+        lock: &HeldLock<'_>,
+        timeout: Duration,
+        poll_interval: Duration,
+    ) -> Result<bool, SchemaStateError> {
+        info!(
+            "[~] Waiting for lock owner to meet schema state for: {}",
+            self.name
+        );
+        let started = Instant::now();
+        loop {
+            if self.is_met(database)? {
+                debug!("[+] Schema state was met by lock owner for: {}", self.name);
+                return Ok(false);
+            }
+            if lock
+                .table
+                .try_acquire(database, &lock.name, &lock.owner)
+                .map_err(|err| SchemaStateError::LockError(lock.name.clone(), err))?
+            {
+                return Ok(true);
+            }
+            if started.elapsed() >= timeout {
+                return Err(SchemaStateError::LockError(
+                    lock.name.clone(),
+                    Problem::from_error("Timed out waiting for lock owner to meet schema state"),
+                ));
+            }
+            sleep(poll_interval);
+        }
     }
 
-    /// Makes sure that the object is initialized by performing a check and necessary actions to
-    /// initialize the object accordingly to check result.
-    pub fn ensure(self, database: &mut Handle<'_>) -> Result<SchemaState, SchemaStateError> {
-        self.ensure_with_dry_run(database, false)
+    /// Ensures this object and runs its maintenance statements and smoke tests if it was changed.
+    fn ensure_tree(
+        &self,
+        database: &mut Handle<'_>,
+        run: &Run<'_, '_>,
+        force: bool,
+    ) -> Result<SchemaState, SchemaStateError> {
+        if run
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            warn!("Time budget exceeded, not starting: {}", self.name);
+            return Err(SchemaStateError::BudgetExceeded(self.name.clone()));
+        }
+        let dedupe = !self.is_group() && !self.is_reference();
+        if dedupe {
+            if let Some(state) = run.ensured(&self.name) {
+                debug!(
+                    "[+] Schema state was already ensured in this run: {}",
+                    self.name
+                );
+                return Ok(state);
+            }
+        }
+        let verified = Cell::new(false);
+        let state = self.ensure_node(database, run, force, &verified)?;
+        if state == SchemaState::Changed && !run.dry_run {
+            self.run_maintenance(database, run)?;
+            self.run_smoke_tests(database, run)?;
+        }
+        // Recorded only after maintenance and smoke tests so that their failure is retried
+        if let (Some(state_table), true) = (run.state_table, verified.get()) {
+            let run_id = run.report.borrow().run_id.clone();
+            state_table.record(database, &self.name, self.fingerprint(), &run_id);
+        }
+        if dedupe {
+            run.ensured.borrow_mut().push((self.name.clone(), state));
+        }
+        Ok(state)
     }
 
-    /// Same as `ensure` but if `dry_run` is set to `true` no actual initialization queries are
-    /// executed.
-    pub fn ensure_with_dry_run(
-        self,
+    fn ensure_node(
+        &self,
         database: &mut Handle<'_>,
-        dry_run: bool,
+        run: &Run<'_, '_>,
+        force: bool,
+        verified: &Cell<bool>,
     ) -> Result<SchemaState, SchemaStateError> {
-        ensure(move || {
-            let Self {
-                name,
+        let dry_run = run.dry_run;
+        // Marks the object to be recorded in the state table once ensured
+        let record = move || verified.set(true);
+
+        if let Some(object) = run.resumed(self) {
+            debug!(
+                "[+] Schema state was visited before reconnecting: {}",
+                self.name
+            );
+            let state = if object.state == ObjectState::Changed {
+                SchemaState::Changed
+            } else {
+                SchemaState::Ok
+            };
+            run.report_resumed(object);
+            return Ok(state);
+        }
+
+        let (check_query, ensure, meet_queries, profiles) = match &self.node {
+            Node::Query {
                 check_query,
                 ensure,
-                meet_require,
-            } = self;
+                meet_queries,
+                profiles,
+            } => (check_query, ensure, meet_queries, profiles),
+            Node::Group {
+                members,
+                independent,
+            } => {
+                let result = self.ensure_group(database, run, force, members, *independent);
+                if result.is_ok() {
+                    record();
+                }
+                return result;
+            }
+            Node::Step(step) => {
+                let result = self.ensure_step(database, run, step.as_ref());
+                if result.is_ok() {
+                    record();
+                }
+                return result;
+            }
+            Node::Reference => {
+                return match run.root.find(&self.name) {
+                    Some(definition) => definition.ensure_tree(database, run, force),
+                    None => Err(SchemaStateError::CheckError(
+                        self.name.clone(),
+                        Problem::from_error("Referenced object is not defined in the tree"),
+                    )),
+                };
+            }
+        };
+
+        let eager = self.is_eager(run);
+        if eager {
+            self.ensure_requirements(database, run)?;
+        }
+
+        ensure::ensure(move || {
+            let Self { name, .. } = self;
             debug!("[?] Ensuring schema state for: {}", name);
+            if let Some(observer) = run.observer() {
+                observer.object_started(&run.report.borrow().run_id, name);
+            }
+            let started = Instant::now();
 
             let meet_queries = (|| {
+                run.heartbeat(database)?;
+                if force {
+                    info!("[!] Forcing schema state for: {}", name);
+                    if meet_queries.is_empty() {
+                        return problem!("No meet queries declared to force for: {}", name);
+                    }
+                    return Ok(meet_queries.clone());
+                }
+
                 if dry_run {
                     info!("[check]: {}", check_query);
                 }
+                run.verify_read_only(check_query)?;
 
-                let check_rows = database.query(&check_query)?;
-                Ok(ensure(check_rows)?)
+                let meet_queries = ensure.decide(
+                    database,
+                    check_query,
+                    run.options.context_ref(),
+                    run.options.max_check_rows,
+                )?;
+                if meet_queries.iter().any(|meet_query| meet_query.is_empty()) {
+                    return problem!("Empty meet statement returned for: {}", name);
+                }
+                Ok(meet_queries)
             })()
-            .map_err(|err| SchemaStateError::CheckError(name.clone(), err))?;
+            .map(|meet_queries| {
+                let profile = run.options.profile.as_deref();
+                match profiles
+                    .iter()
+                    .find(|(name, _)| Some(name.as_str()) == profile)
+                {
+                    Some((profile, queries)) if !meet_queries.is_empty() => {
+                        debug!("Using meet queries of profile {} for: {}", profile, name);
+                        queries.clone()
+                    }
+                    _ => meet_queries,
+                }
+            })
+            .map_err(|err| {
+                let state = ObjectState::Failed(err.to_string());
+                let check_duration = if force { None } else { Some(started.elapsed()) };
+                run.report(name, state, check_duration, started.elapsed());
+                SchemaStateError::CheckError(name.clone(), err)
+            })?;
+            let check_duration = started.elapsed();
+            let check_timing = if force { None } else { Some(check_duration) };
 
             Ok(if meet_queries.is_empty() {
                 debug!("[+] Schema state is met for: {}", name);
+                record();
+                run.report(name, ObjectState::Met, check_timing, check_duration);
                 Met(SchemaState::Ok)
             } else {
                 EnsureAction(move || {
-                    for required in meet_require {
-                        required.ensure_with_dry_run(database, dry_run)?;
+                    if !eager {
+                        self.ensure_requirements(database, run)?;
                     }
 
                     info!("[!] Meeting schema state for: {}", name);
-                    || -> Result<_, Problem> {
+                    if !dry_run {
+                        match run
+                            .with_meet_database(database, |database| {
+                                run.verify_privileges(database)
+                            })
+                            .and_then(|()| run.check_concurrency(name, self.concurrency))
+                            .and_then(|()| run.approve(name, &meet_queries))
+                            .and_then(|decision| {
+                                if decision == Decision::Apply {
+                                    run.with_meet_database(database, |database| {
+                                        run.backup(database, name, &meet_queries)
+                                    })?;
+                                }
+                                Ok(decision)
+                            }) {
+                            Ok(Decision::Apply) => (),
+                            Ok(_) => {
+                                info!("[-] Skipped meeting schema state for: {}", name);
+                                run.report(
+                                    name,
+                                    ObjectState::Skipped,
+                                    check_timing,
+                                    check_duration,
+                                );
+                                return Ok(SchemaState::Ok);
+                            }
+                            Err(err) => {
+                                let state = ObjectState::Failed(err.to_string());
+                                run.report(name, state, check_timing, check_duration);
+                                return Err(SchemaStateError::MeetError(name.clone(), err));
+                            }
+                        }
+                    }
+                    let started = Instant::now();
+                    let mut applied = Applied {
+                        check_duration: check_timing,
+                        ..Applied::default()
+                    };
+                    let result = (|| -> Result<_, Problem> {
                         if !dry_run {
-                            for meet_query in meet_queries {
-                                database.query::<()>(&meet_query)?.no_result()?;
+                            for meet_query in &meet_queries {
+                                meet_query.validate()?;
                             }
+                            run.with_table_locks(database, name, &self.table_locks, |database| {
+                                if run.execute_batch(database, name, &meet_queries, &mut applied)? {
+                                    return Ok(());
+                                }
+                                for meet_query in &meet_queries {
+                                    run.heartbeat(database)?;
+                                    let statement_started = Instant::now();
+                                    let affected_rows = match &run.annotation {
+                                        Some(annotation) => run.execute(
+                                            database,
+                                            name,
+                                            &format!("{}\n{}", annotation, meet_query.as_str()),
+                                        )?,
+                                        None => run.execute(database, name, meet_query)?,
+                                    };
+                                    if let Some(affected_rows) = affected_rows {
+                                        info!(
+                                            "[=] {} rows affected by: {}",
+                                            affected_rows, meet_query
+                                        );
+                                    }
+                                    run.applied(
+                                        name,
+                                        &mut applied,
+                                        meet_query,
+                                        affected_rows,
+                                        statement_started.elapsed(),
+                                    );
+                                }
+                                Ok(())
+                            })?;
 
-                            let check_rows = database.query(&check_query)?;
-                            debug!("[~] Verifying schema state is met for: {}", name);
-                            if !ensure(check_rows)?.is_empty() {
-                                return problem!("Verification failed for schema state: {}", name);
+                            if let Verification::Skip = self.verification {
+                                record();
+                                return Ok(SchemaState::Changed);
                             }
+                            let verify_started = Instant::now();
+                            debug!("[~] Verifying schema state is met for: {}", name);
+                            let verified = self.verify(run, || {
+                                if !ensure
+                                    .decide(
+                                        database,
+                                        check_query,
+                                        run.options.context_ref(),
+                                        run.options.max_check_rows,
+                                    )?
+                                    .is_empty()
+                                {
+                                    return problem!(
+                                        "Verification failed for schema state: {}",
+                                        name
+                                    );
+                                }
+                                Ok(())
+                            });
+                            applied.verify_duration = Some(verify_started.elapsed());
+                            verified?;
 
+                            record();
                             Ok(SchemaState::Changed)
                         } else {
-                            for meet_query in meet_queries {
+                            for meet_query in &meet_queries {
                                 info!("[would meet]: {}", meet_query);
+                                if self.concurrency == Concurrency::Exclusive
+                                    && !run.options.maintenance_window
+                                {
+                                    warn!("[exclusive]: {}", meet_query);
+                                }
+                                if !run.options.allow_destructive && is_destructive(meet_query) {
+                                    warn!("[destructive]: {}", meet_query);
+                                }
+                                if run.is_forbidden(meet_query) {
+                                    warn!(
+                                        "[forbidden {}]: {}",
+                                        statement_kind(meet_query),
+                                        meet_query
+                                    );
+                                }
                             }
                             Ok(SchemaState::Ok)
                         }
-                    }()
-                    .map_err(|err| SchemaStateError::MeetError(name, err))
+                    })();
+
+                    let duration = check_duration + started.elapsed();
+                    match result {
+                        Ok(state) => {
+                            if dry_run {
+                                let state = ObjectState::WouldChange;
+                                applied.statements = meet_queries;
+                                run.report_applied(name, state, applied, duration);
+                            } else {
+                                run.report_applied(name, ObjectState::Changed, applied, duration);
+                            }
+                            Ok(state)
+                        }
+                        Err(err) => {
+                            let state = ObjectState::Failed(err.to_string());
+                            run.report_applied(name, state, applied, duration);
+                            Err(SchemaStateError::MeetError(name.clone(), err))
+                        }
+                    }
+                })
+            })
+        })
+    }
+
+    fn ensure_group(
+        &self,
+        database: &mut Handle<'_>,
+        run: &Run<'_, '_>,
+        force: bool,
+        members: &[EnsureSchema],
+        independent: bool,
+    ) -> Result<SchemaState, SchemaStateError> {
+        debug!("[?] Ensuring schema state for group: {}", self.name);
+        let started = Instant::now();
+        let first = run.report.borrow().objects.len();
+
+        run.depth.set(run.depth.get() + 1);
+        let mut result = self
+            .meet_require
+            .iter()
+            .try_for_each(|required| required.ensure_tree(database, run, false).map(drop))
+            .map_err(|err| SchemaStateError::RequirementError(self.name.clone(), Box::new(err)))
+            .map(|()| SchemaState::Ok);
+        if result.is_ok() {
+            let mut members = members.iter().collect::<Vec<_>>();
+            if independent {
+                // exclusive meets first so that they are done with before traffic resumes
+                members.sort_by_key(|member| member.concurrency != Concurrency::Exclusive);
+            }
+            for member in members {
+                match member.ensure_tree(database, run, force) {
+                    Ok(SchemaState::Changed) => {
+                        if let Ok(state) = &mut result {
+                            *state = SchemaState::Changed;
+                        }
+                    }
+                    Ok(SchemaState::Ok) => (),
+                    Err(err) => {
+                        if result.is_ok() {
+                            result = Err(err);
+                        }
+                        if !independent {
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        run.depth.set(run.depth.get() - 1);
+
+        let state = match &result {
+            Err(err) => ObjectState::Failed(err.to_string()),
+            Ok(_) => {
+                let report = run.report.borrow();
+                let members = &report.objects[first..];
+                if members
+                    .iter()
+                    .any(|object| object.state == ObjectState::WouldChange)
+                {
+                    ObjectState::WouldChange
+                } else if members
+                    .iter()
+                    .any(|object| object.state == ObjectState::Changed)
+                {
+                    ObjectState::Changed
+                } else {
+                    ObjectState::Met
+                }
+            }
+        };
+        run.report_group(&self.name, state, started.elapsed());
+        result
+    }
+
+    /// Runs verification retrying it if configured for this object or the run.
+    fn verify(
+        &self,
+        run: &Run<'_, '_>,
+        mut verify: impl FnMut() -> Result<(), Problem>,
+    ) -> Result<(), Problem> {
+        match (&self.verification, &run.options.verify_retry) {
+            (Verification::Once, None) => verify(),
+            (Verification::Once, Some(retry)) | (Verification::Retry(retry), _) => {
+                retry.retry(&format!("Verification of {}", self.name), verify)
+            }
+            (Verification::Skip, _) => Ok(()),
+        }
+    }
+
+    /// Returns `true` if requirements are ensured regardless of the check.
+    fn is_eager(&self, run: &Run<'_, '_>) -> bool {
+        self.eager_requirements || run.options.eager_requirements
+    }
+
+    /// Ensures requirements of this object one level deeper in the tree.
+    fn ensure_requirements(
+        &self,
+        database: &mut Handle<'_>,
+        run: &Run<'_, '_>,
+    ) -> Result<(), SchemaStateError> {
+        run.depth.set(run.depth.get() + 1);
+        let required = self
+            .meet_require
+            .iter()
+            .try_for_each(|required| required.ensure_tree(database, run, false).map(drop));
+        run.depth.set(run.depth.get() - 1);
+        required.map_err(|err| SchemaStateError::RequirementError(self.name.clone(), Box::new(err)))
+    }
+
+    fn ensure_step(
+        &self,
+        database: &mut Handle<'_>,
+        run: &Run<'_, '_>,
+        step: &dyn Ensure,
+    ) -> Result<SchemaState, SchemaStateError> {
+        let name = &self.name;
+        debug!("[?] Ensuring step: {}", name);
+        let eager = self.is_eager(run);
+        if eager {
+            self.ensure_requirements(database, run)?;
+        }
+        let started = Instant::now();
+
+        let met = run
+            .heartbeat(database)
+            .and_then(|()| step.check(database))
+            .map_err(|err| {
+                let state = ObjectState::Failed(err.to_string());
+                run.report(name, state, Some(started.elapsed()), started.elapsed());
+                SchemaStateError::CheckError(name.clone(), err)
+            })?;
+        let check_duration = started.elapsed();
+        if met {
+            debug!("[+] Step is met: {}", name);
+            run.report(name, ObjectState::Met, Some(check_duration), check_duration);
+            return Ok(SchemaState::Ok);
+        }
+        if let Some(message) = step.assertion() {
+            error!("[#] Assertion failed for {}: {}", name, message);
+            let state = ObjectState::AssertionFailed(message.clone());
+            run.report(name, state, Some(check_duration), check_duration);
+            return Err(SchemaStateError::AssertionError(
+                name.clone(),
+                Problem::from_error(message),
+            ));
+        }
+
+        if !eager {
+            self.ensure_requirements(database, run)?;
+        }
+
+        info!("[!] Meeting step: {}", name);
+        let statements = step.statements();
+        if run.dry_run {
+            info!("[would meet step]: {}", name);
+            for statement in &statements {
+                info!("[would meet]: {}", statement);
+            }
+            let applied = Applied {
+                statements,
+                check_duration: Some(check_duration),
+                ..Applied::default()
+            };
+            run.report_applied(name, ObjectState::WouldChange, applied, started.elapsed());
+            return Ok(SchemaState::Ok);
+        }
+        let decision = run
+            .with_meet_database(database, |database| run.verify_privileges(database))
+            .and_then(|()| {
+                if statements.is_empty() && !run.options.allow_destructive {
+                    return problem!(
+                        "Step '{}' does not declare its statements and requires allow_destructive option",
+                        name
+                    );
+                }
+                Ok(())
+            })
+            .and_then(|()| run.check_concurrency(name, self.concurrency))
+            .and_then(|()| run.approve(name, &statements))
+            .and_then(|decision| {
+                if decision == Decision::Apply {
+                    run.with_meet_database(database, |database| {
+                        run.backup(database, name, &statements)
+                    })?;
+                }
+                Ok(decision)
+            });
+        match decision {
+            Ok(Decision::Apply) => (),
+            Ok(_) => {
+                info!("[-] Skipped meeting step: {}", name);
+                run.report(
+                    name,
+                    ObjectState::Skipped,
+                    Some(check_duration),
+                    check_duration,
+                );
+                return Ok(SchemaState::Ok);
+            }
+            Err(err) => {
+                let state = ObjectState::Failed(err.to_string());
+                run.report(name, state, Some(check_duration), check_duration);
+                return Err(SchemaStateError::MeetError(name.clone(), err));
+            }
+        }
+
+        let result = (|| -> Result<_, Problem> {
+            run.heartbeat(database)?;
+            run.with_table_locks(database, name, &self.table_locks, |database| {
+                run.keep_alive(name, || {
+                    run.with_meet_database(database, |database| step.meet(database))
                 })
+            })?;
+            run.executed.set(run.executed.get() + statements.len());
+            debug!("[~] Verifying step is met: {}", name);
+            self.verify(run, || {
+                if !step.check(database)? {
+                    return problem!("Verification failed for step: {}", name);
+                }
+                Ok(())
+            })?;
+            Ok(SchemaState::Changed)
+        })();
+        let duration = started.elapsed();
+        match result {
+            Ok(state) => {
+                run.report(name, ObjectState::Changed, Some(check_duration), duration);
+                Ok(state)
+            }
+            Err(err) => {
+                let state = ObjectState::Failed(err.to_string());
+                run.report(name, state, Some(check_duration), duration);
+                Err(SchemaStateError::MeetError(name.clone(), err))
+            }
+        }
+    }
+}
+
+/// State of single `ensure_with_report` call shared by all objects in the tree.
+struct Run<'o, 'm> {
+    options: &'o EnsureOptions,
+    /// Tree being ensured; references are resolved against it.
+    root: &'o EnsureSchema,
+    /// Objects (but not groups) ensured so far with their state; objects appearing in the tree
+    /// more than once (e.g. after `merge`) are ensured only the first time.
+    ensured: RefCell<Vec<(String, SchemaState)>>,
+    /// Handle used for meet statements instead of the check one.
+    meet_database: Option<RefCell<&'o mut Handle<'m>>>,
+    dry_run: bool,
+    annotation: Option<String>,
+    state_table: Option<&'o StateTable>,
+    lock: Option<HeldLock<'o>>,
+    /// Number of meet statements executed so far.
+    executed: Cell<usize>,
+    privileges_verified: Cell<bool>,
+    /// When time budget of the run is exceeded.
+    deadline: Option<Instant>,
+    /// Depth of currently ensured object in the requirement tree.
+    depth: Cell<usize>,
+    report: RefCell<EnsureReport>,
+}
+
+/// Meet statements executed for an object with their outcome and timing.
+#[derive(Default)]
+struct Applied {
+    statements: Vec<Sql>,
+    affected_rows: Vec<Option<i64>>,
+    check_duration: Option<Duration>,
+    durations: Vec<Duration>,
+    verify_duration: Option<Duration>,
+}
+
+/// Lock held for the duration of a run.
+struct HeldLock<'o> {
+    table: &'o LockTable,
+    name: String,
+    owner: String,
+    held: Cell<bool>,
+    renewed: Cell<Instant>,
+}
+
+impl Run<'_, '_> {
+    /// Consults approval policy and confirmation callback on meet queries of an object;
+    /// aborting is reported as error.
+    fn approve(&self, name: &str, meet_queries: &[Sql]) -> Result<Decision, Problem> {
+        if !self.options.allow_destructive {
+            if let Some(meet_query) = meet_queries.iter().find(|query| is_destructive(query)) {
+                return problem!(
+                    "Destructive statement for '{}' requires allow_destructive option: {}",
+                    name,
+                    meet_query
+                );
+            }
+        }
+        if let Some(guard) = &self.options.production_guard {
+            guard.check(
+                self.options.connection_target.as_deref(),
+                self.options.confirmation_token.as_deref(),
+                name,
+                meet_queries,
+            )?;
+        }
+        if let Some(meet_query) = meet_queries.iter().find(|query| self.is_forbidden(query)) {
+            return problem!(
+                "{} statement for '{}' is forbidden: {}",
+                statement_kind(meet_query),
+                name,
+                meet_query
+            );
+        }
+        if let Some(max_statements) = self.options.max_statements {
+            if self.executed.get() + meet_queries.len() > max_statements {
+                return problem!(
+                    "Meeting '{}' would exceed limit of {} meet statements per run ({} already executed, {} more needed)",
+                    name,
+                    max_statements,
+                    self.executed.get(),
+                    meet_queries.len()
+                );
+            }
+        }
+        if let Some(policy) = &self.options.approval_policy {
+            for meet_query in meet_queries {
+                match policy.0.decide(name, meet_query) {
+                    Decision::Apply => (),
+                    Decision::Skip => return Ok(Decision::Skip),
+                    Decision::Abort => {
+                        return problem!("Statement denied by approval policy: {}", meet_query)
+                    }
+                }
+            }
+        }
+        if let Some(confirm) = &self.options.confirm {
+            let decision = (confirm.0)(name, meet_queries);
+            if decision == Decision::Abort {
+                return problem!("Aborted by confirmation");
+            }
+            return Ok(decision);
+        }
+        Ok(Decision::Apply)
+    }
+
+    /// Calls `f` with the meet handle if set or given check handle otherwise.
+    fn with_meet_database<T>(
+        &self,
+        database: &mut Handle<'_>,
+        f: impl FnOnce(&mut Handle<'_>) -> T,
+    ) -> T {
+        match &self.meet_database {
+            Some(meet_database) => f(&mut meet_database.borrow_mut()),
+            None => f(database),
+        }
+    }
+
+    /// Executes meet statement returning number of affected rows if reported.
+    fn execute(
+        &self,
+        database: &mut Handle<'_>,
+        name: &str,
+        query: &str,
+    ) -> Result<Option<i64>, Problem> {
+        self.keep_alive(name, || {
+            self.with_meet_database(database, |database| {
+                let affected_rows = database.query::<()>(query)?.affected_rows()?;
+                Ok(affected_rows)
             })
         })
     }
+
+    /// Runs `f` while keep-alive connection is pinged and observer notified every interval if
+    /// configured.
+    fn keep_alive<T>(&self, name: &str, f: impl FnOnce() -> T) -> T {
+        let keep_alive = match &self.options.keep_alive {
+            Some(keep_alive) => keep_alive,
+            None => return f(),
+        };
+        let started = Instant::now();
+        let run_id = self.report.borrow().run_id.clone();
+        let observer = self.observer();
+        let lock = self
+            .lock
+            .as_ref()
+            .filter(|lock| lock.held.get())
+            .map(|lock| {
+                (
+                    lock.table,
+                    lock.name.as_str(),
+                    lock.owner.as_str(),
+                    lock.renewed.get(),
+                )
+            });
+        let tick = match lock {
+            Some((table, ..)) => keep_alive.interval().min(table.renew_interval()),
+            None => keep_alive.interval(),
+        };
+        let (done, wait) = mpsc::channel::<()>();
+        thread::scope(|scope| {
+            let pinger = scope.spawn(move || {
+                let mut pinged = started;
+                let mut renewed = lock.map(|(.., renewed)| renewed);
+                while let Err(RecvTimeoutError::Timeout) = wait.recv_timeout(tick) {
+                    if pinged.elapsed() >= keep_alive.interval() {
+                        pinged = Instant::now();
+                        let elapsed = started.elapsed();
+                        info!("[~] Still running after {}s: {}", elapsed.as_secs(), name);
+                        keep_alive.ping();
+                        if let Some(observer) = observer {
+                            observer.statement_running(&run_id, name, elapsed);
+                        }
+                    }
+                    if let (Some((table, lock_name, owner, _)), Some(renewed)) =
+                        (lock, renewed.as_mut())
+                    {
+                        if renewed.elapsed() >= table.renew_interval() {
+                            match keep_alive.renew_lock(table, lock_name, owner) {
+                                Ok(true) => *renewed = Instant::now(),
+                                Ok(false) => {
+                                    warn!("Lost lock {} while running: {}", lock_name, name)
+                                }
+                                Err(err) => warn!("Failed to renew lock {}: {}", lock_name, err),
+                            }
+                        }
+                    }
+                }
+                renewed
+            });
+            let result = f();
+            drop(done);
+            if let (Some(lock), Ok(Some(renewed))) = (&self.lock, pinger.join()) {
+                lock.renewed.set(renewed);
+            }
+            result
+        })
+    }
+
+    /// Executes meet queries as a single batch if enabled and supported by the dialect returning
+    /// `false` if they need to be executed one by one (e.g. batch has failed).
+    fn execute_batch(
+        &self,
+        database: &mut Handle<'_>,
+        name: &str,
+        meet_queries: &[Sql],
+        applied: &mut Applied,
+    ) -> Result<bool, Problem> {
+        if !self.options.batch_meet_queries || meet_queries.len() < 2 {
+            return Ok(false);
+        }
+        let batch = match self.options.dialect.batch(meet_queries) {
+            Some(batch) => batch,
+            None => return Ok(false),
+        };
+        self.heartbeat(database)?;
+        let started = Instant::now();
+        let result = match &self.annotation {
+            Some(annotation) => self.execute(
+                database,
+                name,
+                &format!("{}\n{}", annotation, batch.as_str()),
+            ),
+            None => self.execute(database, name, &batch),
+        };
+        if let Err(err) = result {
+            warn!(
+                "Batch of {} meet queries failed for {}, executing one by one: {}",
+                meet_queries.len(),
+                name,
+                err
+            );
+            return Ok(false);
+        }
+        info!("[=] Executed batch of {} meet queries", meet_queries.len());
+        let duration = started.elapsed() / meet_queries.len() as u32;
+        for meet_query in meet_queries {
+            self.applied(name, applied, meet_query, None, duration);
+        }
+        Ok(true)
+    }
+
+    /// Records executed meet query and notifies the observer.
+    fn applied(
+        &self,
+        name: &str,
+        applied: &mut Applied,
+        meet_query: &Sql,
+        affected_rows: Option<i64>,
+        duration: Duration,
+    ) {
+        self.executed.set(self.executed.get() + 1);
+        applied.statements.push(meet_query.clone());
+        applied.affected_rows.push(affected_rows);
+        applied.durations.push(duration);
+        if let Some(observer) = self.observer() {
+            let redacted = self
+                .options
+                .statement_redaction
+                .redact(meet_query.redacted());
+            observer.statement_executed(
+                &self.report.borrow().run_id,
+                name,
+                redacted.as_deref(),
+                affected_rows,
+                duration,
+            );
+        }
+    }
+
+    /// Calls backup function if any of the meet queries is destructive.
+    fn backup(
+        &self,
+        database: &mut Handle<'_>,
+        name: &str,
+        meet_queries: &[Sql],
+    ) -> Result<(), Problem> {
+        if let Some(backup) = &self.options.backup {
+            if meet_queries.iter().any(|query| is_destructive(query)) {
+                info!("[!] Backing up before destructive changes of: {}", name);
+                (backup.0)(database, name, meet_queries)
+                    .problem_while_with(|| format!("backing up before meeting '{}'", name))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Verifies required privileges once per run.
+    fn verify_privileges(&self, database: &mut Handle<'_>) -> Result<(), Problem> {
+        if self.privileges_verified.replace(true) {
+            return Ok(());
+        }
+        let dialect = self.options.dialect;
+        let mut missing = Vec::new();
+        for privilege in &self.options.required_privileges {
+            match privilege.is_held(database, dialect)? {
+                Some(true) => debug!("Privilege {} is held", privilege),
+                Some(false) => missing.push(privilege.to_string()),
+                None => warn!(
+                    "Cannot verify privilege {} in {:?} dialect",
+                    privilege, dialect
+                ),
+            }
+        }
+        if !missing.is_empty() {
+            return problem!(
+                "Connected principal is missing privileges: {}",
+                missing.join(", ")
+            );
+        }
+        Ok(())
+    }
+
+    /// Fails if strict checks are enabled and check query is not read only or allowed.
+    fn verify_read_only(&self, check_query: &str) -> Result<(), Problem> {
+        if self.options.strict_checks
+            && !is_read_only(check_query)
+            && !self
+                .options
+                .allowed_checks
+                .iter()
+                .any(|pattern| pattern.is_match(check_query))
+        {
+            return problem!(
+                "Refusing to run check query that is not read only: {}",
+                check_query
+            );
+        }
+        Ok(())
+    }
+
+    fn is_forbidden(&self, meet_query: &str) -> bool {
+        self.options
+            .forbidden_kinds
+            .contains(&statement_kind(meet_query))
+    }
+
+    fn observer(&self) -> Option<&'_ dyn Observer> {
+        self.options.observer.as_ref().map(|observer| &*observer.0)
+    }
+
+    /// Renews held lock if it is due; fails if the lock was lost or the run was cancelled.
+    fn heartbeat(&self, database: &mut Handle<'_>) -> Result<(), Problem> {
+        if self.options.is_cancelled() {
+            return problem!("Run was cancelled");
+        }
+        if let Some(lock) = &self.lock {
+            let renewed = lock.renewed.get().elapsed();
+            if renewed >= lock.table.ttl() {
+                return problem!(
+                    "Lock {} may have expired as it was not renewed for {}s; its TTL must exceed the longest statement unless keep_alive is used",
+                    lock.name,
+                    renewed.as_secs()
+                );
+            }
+            if renewed >= lock.table.renew_interval() {
+                if !lock.table.renew(database, &lock.name, &lock.owner)? {
+                    return problem!("Lost lock {}", lock.name);
+                }
+                lock.renewed.set(Instant::now());
+            }
+        }
+        Ok(())
+    }
+
+    fn report(
+        &self,
+        name: &str,
+        state: ObjectState,
+        check_duration: Option<Duration>,
+        duration: Duration,
+    ) {
+        let applied = Applied {
+            check_duration,
+            ..Applied::default()
+        };
+        self.report_applied(name, state, applied, duration)
+    }
+
+    /// State of object already ensured in this run.
+    fn ensured(&self, name: &str) -> Option<SchemaState> {
+        self.ensured
+            .borrow()
+            .iter()
+            .find(|(ensured, _)| ensured == name)
+            .map(|(_, state)| *state)
+    }
+
+    /// Report of object (but not group) visited before reconnecting.
+    fn resumed(&self, schema: &EnsureSchema) -> Option<&ObjectReport> {
+        if let Node::Group { .. } = schema.node {
+            return None;
+        }
+        self.options.resume.as_ref()?.get(&schema.name)
+    }
+
+    fn report_resumed(&self, object: &ObjectReport) {
+        self.push_report(ObjectReport {
+            depth: self.depth.get(),
+            ..object.clone()
+        })
+    }
+
+    fn report_group(&self, name: &str, state: ObjectState, duration: Duration) {
+        self.push_report(ObjectReport {
+            name: name.to_string(),
+            depth: self.depth.get(),
+            state,
+            group: true,
+            statements: Vec::new(),
+            affected_rows: Vec::new(),
+            check_duration: None,
+            statement_durations: Vec::new(),
+            verify_duration: None,
+            duration,
+        })
+    }
+
+    fn report_applied(&self, name: &str, state: ObjectState, applied: Applied, duration: Duration) {
+        self.push_report(ObjectReport {
+            name: name.to_string(),
+            depth: self.depth.get(),
+            state,
+            group: false,
+            statements: applied.statements,
+            affected_rows: applied.affected_rows,
+            check_duration: applied.check_duration,
+            statement_durations: applied.durations,
+            verify_duration: applied.verify_duration,
+            duration,
+        })
+    }
+
+    fn push_report(&self, object: ObjectReport) {
+        if let Some(observer) = self.observer() {
+            observer.object_finished(&self.report.borrow().run_id, &object);
+        }
+        self.report.borrow_mut().objects.push(object);
+    }
+}
+
+/// Generates reasonably unique identifier of a run.
+fn new_run_id() -> String {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    format!(
+        "{:x}{:08x}-{:x}",
+        now.as_secs(),
+        now.subsec_nanos(),
+        std::process::id()
+    )
 }
@@ -0,0 +1,282 @@
+use crate::state::{quote_literal, table_schema, value_to_i64};
+use crate::{split_script, EnsureSchema, Sql};
+use lazy_static::lazy_static;
+use odbc_iter::{Handle, ValueRow};
+use problem::prelude::*;
+use regex::Regex;
+use std::fs;
+use std::path::Path;
+
+lazy_static! {
+    static ref CHANGE_SET: Regex =
+        Regex::new(r"(?s)<changeSet\b([^>]*)>(.*?)</changeSet>").unwrap();
+    static ref ATTRIBUTE: Regex = Regex::new(r#"(\w+)\s*=\s*"([^"]*)""#).unwrap();
+    static ref SQL: Regex = Regex::new(r"(?s)<sql\b([^>]*)>(.*?)</sql>").unwrap();
+    static ref SQL_FILE: Regex = Regex::new(r"<sqlFile\b([^>]*?)/?>").unwrap();
+    static ref PRECONDITIONS: Regex =
+        Regex::new(r"(?s)<preConditions\b([^>]*)>(.*?)</preConditions>").unwrap();
+    static ref SQL_CHECK: Regex = Regex::new(r"(?s)<sqlCheck\b([^>]*)>(.*?)</sqlCheck>").unwrap();
+    static ref CDATA: Regex = Regex::new(r"(?s)<!\[CDATA\[(.*?)\]\]>").unwrap();
+    static ref COMMENT: Regex = Regex::new(r"(?s)<!--.*?-->").unwrap();
+}
+
+/// Default name of Liquibase change log table.
+pub const LIQUIBASE_CHANGELOG_TABLE: &str = "DATABASECHANGELOG";
+
+/// What to do when preconditions of a change set fail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PreconditionFailure {
+    /// Fail the run.
+    Halt,
+    /// Do not apply the change set but try again on next run.
+    Continue,
+    /// Record the change set as ran without applying it.
+    MarkRan,
+}
+
+/// Change set of Liquibase change log.
+#[derive(Debug, Clone)]
+pub struct LiquibaseChangeSet {
+    pub id: String,
+    pub author: String,
+    pub statements: Vec<Sql>,
+    /// `sqlCheck` preconditions as pairs of query and expected result.
+    pub preconditions: Vec<(String, String)>,
+    pub on_fail: PreconditionFailure,
+}
+
+/// Practical subset of Liquibase XML change log: change sets with `sql` and `sqlFile` changes and
+/// `sqlCheck` preconditions.
+///
+/// Change sets are recorded in `DATABASECHANGELOG` compatible table so that databases updated by
+/// Liquibase are not updated again; `MD5SUM` is left empty for Liquibase to compute.
+#[derive(Debug, Clone)]
+pub struct LiquibaseChangelog {
+    /// Change log file name as recorded in `FILENAME` column.
+    pub file_name: String,
+    pub change_sets: Vec<LiquibaseChangeSet>,
+    table: String,
+}
+
+fn attributes(text: &str) -> Vec<(String, String)> {
+    ATTRIBUTE
+        .captures_iter(text)
+        .map(|captures| (captures[1].to_string(), unescape(&captures[2])))
+        .collect()
+}
+
+fn attribute<'a>(attributes: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    attributes
+        .iter()
+        .find(|(attribute, _)| attribute == name)
+        .map(|(_, value)| value.as_str())
+}
+
+fn text(content: &str) -> String {
+    match CDATA.captures(content) {
+        Some(captures) => captures[1].to_string(),
+        None => unescape(content),
+    }
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+fn statements(sql: &str, attributes: &[(String, String)]) -> Result<Vec<Sql>, Problem> {
+    if attribute(attributes, "splitStatements") == Some("false") {
+        Ok(vec![Sql::new(sql.trim())?])
+    } else {
+        split_script(sql)
+    }
+}
+
+impl LiquibaseChangelog {
+    /// Parses XML change log; `sqlFile` paths are resolved relative to `base_dir`.
+    pub fn parse_xml(
+        file_name: impl Into<String>,
+        xml: &str,
+        base_dir: &Path,
+    ) -> Result<LiquibaseChangelog, Problem> {
+        let xml = COMMENT.replace_all(xml, "");
+        let mut change_sets = Vec::new();
+        for change_set in CHANGE_SET.captures_iter(&xml) {
+            let change_set_attributes = attributes(&change_set[1]);
+            let id = attribute(&change_set_attributes, "id")
+                .ok_or_problem("Change set without id")?
+                .to_string();
+            let author = attribute(&change_set_attributes, "author")
+                .unwrap_or_default()
+                .to_string();
+            let body = &change_set[2];
+
+            let mut preconditions = Vec::new();
+            let mut on_fail = PreconditionFailure::Halt;
+            if let Some(captures) = PRECONDITIONS.captures(body) {
+                on_fail = match attribute(&attributes(&captures[1]), "onFail") {
+                    None | Some("HALT") => PreconditionFailure::Halt,
+                    Some("CONTINUE") => PreconditionFailure::Continue,
+                    Some("MARK_RAN") => PreconditionFailure::MarkRan,
+                    Some(other) => {
+                        return problem!("Unsupported onFail {} of change set {}", other, id)
+                    }
+                };
+                for check in SQL_CHECK.captures_iter(&captures[2]) {
+                    let expected = attribute(&attributes(&check[1]), "expectedResult")
+                        .ok_or_problem("sqlCheck without expectedResult")?
+                        .to_string();
+                    preconditions.push((text(&check[2]).trim().to_string(), expected));
+                }
+            }
+            let body = PRECONDITIONS.replace_all(body, "");
+
+            let mut change_set_statements = Vec::new();
+            for sql in SQL.captures_iter(&body) {
+                change_set_statements.extend(statements(&text(&sql[2]), &attributes(&sql[1]))?);
+            }
+            for sql_file in SQL_FILE.captures_iter(&body) {
+                let sql_file_attributes = attributes(&sql_file[1]);
+                let path = base_dir.join(
+                    attribute(&sql_file_attributes, "path")
+                        .ok_or_problem("sqlFile without path")?,
+                );
+                let sql = fs::read_to_string(&path)
+                    .problem_while_with(|| format!("reading SQL file {:?}", path))?;
+                change_set_statements.extend(statements(&sql, &sql_file_attributes)?);
+            }
+            if change_set_statements.is_empty() {
+                return problem!(
+                    "Change set {} has no supported changes (only sql and sqlFile are supported)",
+                    id
+                );
+            }
+
+            change_sets.push(LiquibaseChangeSet {
+                id,
+                author,
+                statements: change_set_statements,
+                preconditions,
+                on_fail,
+            });
+        }
+        Ok(LiquibaseChangelog {
+            file_name: file_name.into(),
+            change_sets,
+            table: LIQUIBASE_CHANGELOG_TABLE.to_string(),
+        })
+    }
+
+    /// Loads XML change log from file; its path is recorded as file name.
+    pub fn load_xml(path: &Path) -> Result<LiquibaseChangelog, Problem> {
+        let xml = fs::read_to_string(path)
+            .problem_while_with(|| format!("reading change log {:?}", path))?;
+        LiquibaseChangelog::parse_xml(
+            path.to_string_lossy(),
+            &xml,
+            path.parent().unwrap_or_else(|| Path::new(".")),
+        )
+    }
+
+    /// Sets name of the change log table.
+    pub fn with_table(mut self, table: impl Into<String>) -> LiquibaseChangelog {
+        self.table = table.into();
+        self
+    }
+
+    /// `EnsureSchema` that creates the change log table if it does not exist.
+    pub fn table_schema(&self) -> EnsureSchema {
+        table_schema(
+            format!("Liquibase change log table {}", self.table),
+            &self.table,
+            format!(
+                "CREATE TABLE {} (ID VARCHAR(255) NOT NULL, AUTHOR VARCHAR(255) NOT NULL, FILENAME VARCHAR(255) NOT NULL, \
+                DATEEXECUTED TIMESTAMP NOT NULL, ORDEREXECUTED INT NOT NULL, EXECTYPE VARCHAR(10) NOT NULL, MD5SUM VARCHAR(35), \
+                DESCRIPTION VARCHAR(255), COMMENTS VARCHAR(255), TAG VARCHAR(255), LIQUIBASE VARCHAR(20), CONTEXTS VARCHAR(255), \
+                LABELS VARCHAR(255), DEPLOYMENT_ID VARCHAR(10))",
+                self.table
+            ),
+        )
+    }
+
+    /// `EnsureSchema` applying change sets not yet recorded in the change log table in order.
+    pub fn ensure_schema(&self) -> EnsureSchema {
+        let mut members = vec![self.table_schema()];
+        for change_set in &self.change_sets {
+            members.push(self.change_set_schema(change_set));
+        }
+        EnsureSchema::sequence(format!("Liquibase change log {}", self.file_name), members)
+    }
+
+    fn record_query(&self, change_set: &LiquibaseChangeSet, exec_type: &str) -> Sql {
+        format!(
+            "INSERT INTO {table} (ID, AUTHOR, FILENAME, DATEEXECUTED, ORDEREXECUTED, EXECTYPE, DESCRIPTION) \
+            SELECT {}, {}, {}, CURRENT_TIMESTAMP, COALESCE(MAX(ORDEREXECUTED), 0) + 1, {}, 'sql' FROM {table}",
+            quote_literal(&change_set.id),
+            quote_literal(&change_set.author),
+            quote_literal(&self.file_name),
+            quote_literal(exec_type),
+            table = self.table
+        )
+        .into()
+    }
+
+    fn change_set_schema(&self, change_set: &LiquibaseChangeSet) -> EnsureSchema {
+        let mut meet_queries = change_set.statements.clone();
+        meet_queries.push(self.record_query(change_set, "EXECUTED"));
+        let mark_ran = vec![self.record_query(change_set, "MARK_RAN")];
+        let declared = meet_queries.clone();
+        let preconditions = change_set.preconditions.clone();
+        let on_fail = change_set.on_fail;
+        let id = change_set.id.clone();
+        EnsureSchema::with_inspection(
+            format!("change set {}::{}", change_set.id, change_set.author),
+            format!(
+                "SELECT COUNT(*) FROM {} WHERE ID = {} AND AUTHOR = {} AND FILENAME = {}",
+                self.table,
+                quote_literal(&change_set.id),
+                quote_literal(&change_set.author),
+                quote_literal(&self.file_name)
+            ),
+            move |rows, database| {
+                let count = rows
+                    .into_iter()
+                    .next()
+                    .and_then(|row| value_to_i64(row.into_iter().next().flatten()));
+                if count.unwrap_or(0) > 0 {
+                    return Ok(vec![]);
+                }
+                for (query, expected) in &preconditions {
+                    if !precondition_holds(database, query, expected)? {
+                        return match on_fail {
+                            PreconditionFailure::Halt => {
+                                problem!("Precondition of change set {} failed: {}", id, query)
+                            }
+                            PreconditionFailure::Continue => Ok(vec![]),
+                            PreconditionFailure::MarkRan => Ok(mark_ran.clone()),
+                        };
+                    }
+                }
+                Ok(meet_queries.clone())
+            },
+        )
+        .with_declared_meet_queries(declared)
+    }
+}
+
+fn precondition_holds(
+    database: &mut Handle<'_>,
+    query: &str,
+    expected: &str,
+) -> Result<bool, Problem> {
+    let row = database.query::<ValueRow>(query)?.single()?;
+    let value = row
+        .into_iter()
+        .next()
+        .flatten()
+        .map(|value| value.to_string());
+    Ok(value.as_deref().map(str::trim) == Some(expected.trim()))
+}
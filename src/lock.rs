@@ -0,0 +1,198 @@
+use crate::state::{now_epoch, quote_literal, table_schema};
+use crate::EnsureSchema;
+use log::*;
+use odbc_iter::{Handle, ValueRow};
+use problem::prelude::*;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+/// Portable lock implemented as a row in a table maintained by this crate.
+///
+/// Lock expires if not renewed within its TTL so that a crashed owner can be taken over.
+#[derive(Debug, Clone)]
+pub struct LockTable {
+    table: String,
+    ttl: Duration,
+    acquire_timeout: Duration,
+    poll_interval: Duration,
+}
+
+impl LockTable {
+    /// Creates `LockTable` given table name; locks expire after 5 minutes if not renewed and
+    /// acquisition fails immediately if the lock is held.
+    pub fn new(table: impl Into<String>) -> LockTable {
+        LockTable {
+            table: table.into(),
+            ttl: Duration::from_secs(5 * 60),
+            acquire_timeout: Duration::from_secs(0),
+            poll_interval: Duration::from_secs(1),
+        }
+    }
+
+    /// Sets for how long lock is valid after it was acquired or last renewed.
+    pub fn with_ttl(mut self, ttl: Duration) -> LockTable {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Sets for how long to keep trying to acquire held lock and how often.
+    pub fn with_acquire_timeout(mut self, timeout: Duration, poll_interval: Duration) -> LockTable {
+        self.acquire_timeout = timeout;
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Name of the table.
+    pub fn table(&self) -> &str {
+        &self.table
+    }
+
+    /// For how long lock is valid after it was acquired or last renewed.
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Time after which the lock should be renewed to not expire.
+    pub fn renew_interval(&self) -> Duration {
+        self.ttl / 3
+    }
+
+    /// `EnsureSchema` that creates the table if it does not exist.
+    pub fn ensure_schema(&self) -> EnsureSchema {
+        table_schema(
+            format!("lock table {}", self.table),
+            &self.table,
+            format!(
+                "CREATE TABLE {} (lock_name VARCHAR(255) NOT NULL PRIMARY KEY, owner VARCHAR(255) NOT NULL, expires_at BIGINT NOT NULL)",
+                self.table
+            ),
+        )
+    }
+
+    fn expires_at(&self) -> i64 {
+        now_epoch() + self.ttl.as_secs() as i64
+    }
+
+    /// Tries to acquire the lock once; expired lock is taken over.
+    pub fn try_acquire(
+        &self,
+        database: &mut Handle<'_>,
+        lock_name: &str,
+        owner: &str,
+    ) -> Result<bool, Problem> {
+        let updated = database
+            .query::<()>(&format!(
+                "UPDATE {} SET owner = {}, expires_at = {} WHERE lock_name = {} AND (expires_at < {} OR owner = {})",
+                self.table,
+                quote_literal(owner),
+                self.expires_at(),
+                quote_literal(lock_name),
+                now_epoch(),
+                quote_literal(owner)
+            ))?
+            .affected_rows()?;
+
+        if updated != Some(1) {
+            // Fails on primary key violation if the lock is held by someone else
+            let inserted = database
+                .query::<()>(&format!(
+                    "INSERT INTO {} (lock_name, owner, expires_at) VALUES ({}, {}, {})",
+                    self.table,
+                    quote_literal(lock_name),
+                    quote_literal(owner),
+                    self.expires_at()
+                ))
+                .map_err(Problem::from_error)
+                .and_then(|rows| rows.no_result().map_err(Problem::from_error));
+            if let Err(err) = inserted {
+                debug!("Lock {} insert failed: {}", lock_name, err);
+            }
+        }
+
+        Ok(self.owner(database, lock_name)?.as_deref() == Some(owner))
+    }
+
+    /// Acquires the lock waiting up to configured acquire timeout.
+    pub fn acquire(
+        &self,
+        database: &mut Handle<'_>,
+        lock_name: &str,
+        owner: &str,
+    ) -> Result<(), Problem> {
+        let started = Instant::now();
+        loop {
+            if self.try_acquire(database, lock_name, owner)? {
+                debug!("Acquired lock {} as {}", lock_name, owner);
+                return Ok(());
+            }
+            if started.elapsed() >= self.acquire_timeout {
+                return problem!(
+                    "Lock {} is held by {}",
+                    lock_name,
+                    self.owner(database, lock_name)?
+                        .unwrap_or_else(|| "unknown".to_string())
+                );
+            }
+            sleep(self.poll_interval);
+        }
+    }
+
+    /// Extends validity of held lock; returns `false` if the lock is no longer held.
+    pub fn renew(
+        &self,
+        database: &mut Handle<'_>,
+        lock_name: &str,
+        owner: &str,
+    ) -> Result<bool, Problem> {
+        let updated = database
+            .query::<()>(&format!(
+                "UPDATE {} SET expires_at = {} WHERE lock_name = {} AND owner = {}",
+                self.table,
+                self.expires_at(),
+                quote_literal(lock_name),
+                quote_literal(owner)
+            ))?
+            .affected_rows()?;
+        Ok(updated == Some(1))
+    }
+
+    /// Releases held lock.
+    pub fn release(
+        &self,
+        database: &mut Handle<'_>,
+        lock_name: &str,
+        owner: &str,
+    ) -> Result<(), Problem> {
+        database
+            .query::<()>(&format!(
+                "DELETE FROM {} WHERE lock_name = {} AND owner = {}",
+                self.table,
+                quote_literal(lock_name),
+                quote_literal(owner)
+            ))?
+            .no_result()?;
+        Ok(())
+    }
+
+    /// Current owner of the lock if it is held and not expired.
+    pub fn owner(
+        &self,
+        database: &mut Handle<'_>,
+        lock_name: &str,
+    ) -> Result<Option<String>, Problem> {
+        let mut rows = database.query::<ValueRow>(&format!(
+            "SELECT owner FROM {} WHERE lock_name = {} AND expires_at >= {}",
+            self.table,
+            quote_literal(lock_name),
+            now_epoch()
+        ))?;
+        Ok(match rows.next() {
+            Some(row) => row?
+                .into_iter()
+                .next()
+                .flatten()
+                .map(|owner| owner.to_string()),
+            None => None,
+        })
+    }
+}
@@ -0,0 +1,70 @@
+use crate::{Applied, Dialect, EnsureSchema, ObjectState, Run, SchemaStateError, Sql};
+use log::*;
+use odbc_iter::Handle;
+use std::time::Instant;
+
+impl EnsureSchema {
+    /// Executes given maintenance statement after this object (including its members and
+    /// requirements) was changed; it is not executed in dry run or when the object was already
+    /// met.
+    ///
+    /// Maintenance statements are reported as separate object and are not subject to approval;
+    /// they are part of the fingerprint.
+    pub fn with_maintenance(mut self, statement: impl Into<Sql>) -> EnsureSchema {
+        self.maintenance.push(statement.into());
+        self
+    }
+
+    /// Refreshes optimizer statistics of given table (e.g. `UPDATE STATISTICS` or `ANALYZE`)
+    /// after this object was changed so that freshly created or backfilled table performs well
+    /// immediately; ignored with warning if not supported in the dialect.
+    pub fn with_analyze(self, table: &str, dialect: Dialect) -> EnsureSchema {
+        match dialect.analyze_query(table) {
+            Some(query) => self.with_maintenance(query),
+            None => {
+                warn!(
+                    "Statistics of {} cannot be refreshed in {:?} dialect",
+                    table, dialect
+                );
+                self
+            }
+        }
+    }
+
+    /// Executes maintenance statements of this object reported as single object.
+    pub(crate) fn run_maintenance(
+        &self,
+        database: &mut Handle<'_>,
+        run: &Run<'_, '_>,
+    ) -> Result<(), SchemaStateError> {
+        if self.maintenance.is_empty() {
+            return Ok(());
+        }
+        let name = format!("maintenance of {}", self.name);
+        let started = Instant::now();
+        let mut applied = Applied::default();
+        for statement in &self.maintenance {
+            info!("[=] {}: {}", name, statement);
+            let statement_started = Instant::now();
+            match statement
+                .validate()
+                .and_then(|()| run.execute(database, &name, statement))
+            {
+                Ok(affected_rows) => run.applied(
+                    &name,
+                    &mut applied,
+                    statement,
+                    affected_rows,
+                    statement_started.elapsed(),
+                ),
+                Err(err) => {
+                    let state = ObjectState::Failed(err.to_string());
+                    run.report_applied(&name, state, applied, started.elapsed());
+                    return Err(SchemaStateError::MeetError(name, err));
+                }
+            }
+        }
+        run.report_applied(&name, ObjectState::Changed, applied, started.elapsed());
+        Ok(())
+    }
+}
@@ -0,0 +1,99 @@
+use std::fs;
+
+/// Identity of the process performing a run so that concurrent deployers can be distinguished.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RunMetadata {
+    pub host: Option<String>,
+    pub pid: u32,
+    pub os_user: Option<String>,
+    /// Operator supplied description of who or what has initiated the run.
+    pub initiator: Option<String>,
+}
+
+impl RunMetadata {
+    /// Collects metadata of current process.
+    pub fn collect(initiator: Option<String>) -> RunMetadata {
+        RunMetadata {
+            host: host_name(),
+            pid: std::process::id(),
+            os_user: os_user(),
+            initiator,
+        }
+    }
+
+    /// SQL comment identifying the run that can be prepended to queries.
+    pub fn annotation(&self, run_id: &str) -> String {
+        let mut annotation = format!(
+            "/* odbc-iter-schema run={} pid={}",
+            run_id.replace("*/", "* /"),
+            self.pid
+        );
+        for (key, value) in &[
+            ("host", &self.host),
+            ("user", &self.os_user),
+            ("initiator", &self.initiator),
+        ] {
+            if let Some(value) = value {
+                annotation.push_str(&format!(" {}={}", key, value.replace("*/", "* /")));
+            }
+        }
+        annotation.push_str(" */");
+        annotation
+    }
+}
+
+fn host_name() -> Option<String> {
+    std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| std::env::var("COMPUTERNAME").ok())
+        .or_else(|| {
+            fs::read_to_string("/etc/hostname")
+                .ok()
+                .map(|name| name.trim().to_string())
+        })
+        .filter(|name| !name.is_empty())
+}
+
+fn os_user() -> Option<String> {
+    std::env::var("USER")
+        .ok()
+        .or_else(|| std::env::var("USERNAME").ok())
+        .or_else(|| std::env::var("LOGNAME").ok())
+        .filter(|name| !name.is_empty())
+}
+
+/// Returns `true` if run identifier consists only of ASCII letters, digits, `.`, `_` and `-` so
+/// that it can be safely embedded in SQL comments and messages.
+pub(crate) fn is_valid_run_id(run_id: &str) -> bool {
+    !run_id.is_empty()
+        && run_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '_' || c == '-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn run_id_validation() {
+        assert!(is_valid_run_id("16f3a2b1c0-1a2b"));
+        assert!(is_valid_run_id("deploy_2020.01.01"));
+        assert!(!is_valid_run_id(""));
+        assert!(!is_valid_run_id("x */ DROP TABLE users; /*"));
+    }
+
+    #[test]
+    fn annotation_cannot_close_comment() {
+        let metadata = RunMetadata {
+            host: Some("host*/".to_string()),
+            pid: 1,
+            os_user: None,
+            initiator: None,
+        };
+        let annotation = metadata.annotation("run*/id");
+        assert!(annotation.starts_with("/*"));
+        assert_eq!(annotation.matches("*/").count(), 1);
+        assert!(annotation.ends_with("*/"));
+    }
+}
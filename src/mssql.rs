@@ -0,0 +1,68 @@
+use crate::state::quote_literal;
+use crate::{create_procedure_query, create_view_query, Dialect, Sql};
+
+/// Wraps statement so it is only executed if `condition` query (e.g. `SELECT 1 FROM sys.indexes
+/// WHERE ...`) produces no rows.
+///
+/// Statement is run with `EXEC` so statements that need to be first in a batch (e.g. `CREATE
+/// VIEW`) can be wrapped.
+pub fn mssql_if_not_exists(condition: &str, statement: &str) -> Sql {
+    format!(
+        "IF NOT EXISTS ({}) BEGIN EXEC({}) END",
+        condition,
+        mssql_literal(statement)
+    )
+    .into()
+}
+
+/// Statement creating table with given column definitions if it does not exist.
+pub fn mssql_create_table(table: &str, definition: &str) -> Sql {
+    format!(
+        "IF OBJECT_ID({}, 'U') IS NULL BEGIN EXEC({}) END",
+        mssql_literal(table),
+        mssql_literal(&format!("CREATE TABLE {} ({})", table, definition))
+    )
+    .into()
+}
+
+/// Statement creating index with given definition (e.g. `ON dbo.t (a, b)`) if the table does not
+/// have index of that name.
+pub fn mssql_create_index(table: &str, index: &str, definition: &str) -> Sql {
+    mssql_if_not_exists(
+        &format!(
+            "SELECT 1 FROM sys.indexes WHERE name = {} AND object_id = OBJECT_ID({})",
+            mssql_literal(index),
+            mssql_literal(table)
+        ),
+        &format!("CREATE INDEX {} {}", index, definition),
+    )
+}
+
+/// Statement adding column if the table does not have it.
+pub fn mssql_add_column(table: &str, column: &str, definition: &str) -> Sql {
+    format!(
+        "IF COL_LENGTH({}, {}) IS NULL BEGIN EXEC({}) END",
+        mssql_literal(table),
+        mssql_literal(column),
+        mssql_literal(&format!(
+            "ALTER TABLE {} ADD {} {}",
+            table, column, definition
+        ))
+    )
+    .into()
+}
+
+/// `CREATE OR ALTER VIEW` statement.
+pub fn mssql_create_or_alter_view(view: &str, select: &str) -> Sql {
+    create_view_query(Dialect::SqlServer, view, select)
+}
+
+/// `CREATE OR ALTER PROCEDURE` statement given everything following the procedure name.
+pub fn mssql_create_or_alter_procedure(procedure: &str, definition: &str) -> Sql {
+    create_procedure_query(Dialect::SqlServer, procedure, definition)
+}
+
+/// Unicode string literal.
+fn mssql_literal(value: &str) -> String {
+    format!("N{}", quote_literal(value))
+}
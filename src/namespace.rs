@@ -0,0 +1,25 @@
+use crate::state::quote_literal;
+use crate::EnsureSchema;
+
+impl EnsureSchema {
+    /// Creates `EnsureSchema` creating schema (namespace) of given name if
+    /// `information_schema.schemata` does not list it.
+    pub fn schema_namespace(name: impl Into<String>) -> EnsureSchema {
+        let name = name.into();
+        EnsureSchema::with_count_check(
+            format!("schema {}", name),
+            format!(
+                "SELECT COUNT(*) FROM information_schema.schemata WHERE UPPER(schema_name) = UPPER({})",
+                quote_literal(&name)
+            ),
+            vec![format!("CREATE SCHEMA {}", name)],
+        )
+    }
+
+    /// Requires schema (namespace) of given name to exist before this object is met (see
+    /// `schema_namespace`); objects sharing the namespace can each require it as it is only
+    /// created once.
+    pub fn with_schema_namespace(self, name: impl Into<String>) -> EnsureSchema {
+        self.with_meet_require(EnsureSchema::schema_namespace(name))
+    }
+}
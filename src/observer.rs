@@ -0,0 +1,75 @@
+use crate::hash::Fnv64;
+use crate::{EnsureReport, ObjectReport};
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::fmt;
+use std::time::Duration;
+
+lazy_static! {
+    static ref LITERALS: Regex = Regex::new(r"'(?:[^']|'')*'|\b\d+(?:\.\d+)?\b").unwrap();
+}
+
+/// Receives events of ensure runs so they can be exported as telemetry (e.g. OpenTelemetry spans
+/// for runs, objects and statements).
+///
+/// Statement text is passed after applying configured `StatementRedaction`.
+///
+/// Observer is notified from a separate thread while statements are running with keep-alive.
+pub trait Observer: fmt::Debug + Send + Sync {
+    /// Run of given root object has started.
+    fn run_started(&self, _run_id: &str, _root: &str) {}
+
+    /// Ensuring of given object (including its check) has started.
+    fn object_started(&self, _run_id: &str, _object: &str) {}
+
+    /// Meet statement of an object was executed.
+    fn statement_executed(
+        &self,
+        _run_id: &str,
+        _object: &str,
+        _statement: Option<&str>,
+        _affected_rows: Option<i64>,
+        _duration: Duration,
+    ) {
+    }
+
+    /// Meet statement or step of an object is still running after given time; notified every
+    /// keep-alive interval (see `EnsureOptions::keep_alive`).
+    fn statement_running(&self, _run_id: &str, _object: &str, _elapsed: Duration) {}
+
+    /// Ensuring of an object has finished with given outcome.
+    fn object_finished(&self, _run_id: &str, _object: &ObjectReport) {}
+
+    /// Run has finished; `error` is set if it has failed.
+    fn run_finished(&self, _report: &EnsureReport, _error: Option<&str>) {}
+}
+
+/// How statement text is exposed to `Observer`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum StatementRedaction {
+    /// Statement text as executed.
+    Full,
+    /// String and numeric literals are replaced with `?`.
+    #[default]
+    Literals,
+    /// Only hash of the statement text is exposed (as recorded in `AuditTable`).
+    Hash,
+    /// Statement text is not exposed.
+    Omit,
+}
+
+impl StatementRedaction {
+    /// Applies redaction to given statement.
+    pub fn redact(&self, statement: &str) -> Option<String> {
+        match self {
+            StatementRedaction::Full => Some(statement.to_string()),
+            StatementRedaction::Literals => Some(LITERALS.replace_all(statement, "?").into_owned()),
+            StatementRedaction::Hash => {
+                let mut hasher = Fnv64::new();
+                hasher.write(statement.as_bytes());
+                Some(hasher.finish_hex())
+            }
+            StatementRedaction::Omit => None,
+        }
+    }
+}
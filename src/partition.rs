@@ -0,0 +1,347 @@
+use crate::hive::add_partition_query;
+use crate::state::{collect_rows, quote_literal};
+use crate::{Dialect, EnsureSchema, Sql};
+use chrono::{Datelike, Duration, NaiveDate, Utc};
+use odbc_iter::ValueRow;
+
+/// Time period covered by single partition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartitionScheme {
+    Daily,
+    Monthly,
+}
+
+impl PartitionScheme {
+    /// Start of the period given date belongs to.
+    pub fn period_start(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            PartitionScheme::Daily => date,
+            PartitionScheme::Monthly => NaiveDate::from_ymd(date.year(), date.month(), 1),
+        }
+    }
+
+    /// Start of the period following the one given date belongs to.
+    pub fn next_period(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            PartitionScheme::Daily => date.succ(),
+            PartitionScheme::Monthly => {
+                if date.month() == 12 {
+                    NaiveDate::from_ymd(date.year() + 1, 1, 1)
+                } else {
+                    NaiveDate::from_ymd(date.year(), date.month() + 1, 1)
+                }
+            }
+        }
+    }
+
+    fn default_format(&self) -> &'static str {
+        match self {
+            PartitionScheme::Daily => "%Y-%m-%d",
+            PartitionScheme::Monthly => "%Y-%m",
+        }
+    }
+
+    fn default_suffix(&self) -> &'static str {
+        match self {
+            PartitionScheme::Daily => "%Y%m%d",
+            PartitionScheme::Monthly => "%Y%m",
+        }
+    }
+}
+
+/// Keeps time-partitioned table ahead of the calendar by creating partitions for the current and
+/// following periods up to the horizon.
+///
+/// With `Dialect::Postgres` partitions are tables named `<table>_<suffix>` attached with
+/// `PARTITION OF`; otherwise Hive style `ADD PARTITION` on a partition column is used.
+#[derive(Debug, Clone)]
+pub struct PartitionPolicy {
+    table: String,
+    column: String,
+    scheme: PartitionScheme,
+    horizon: u32,
+    format: Option<String>,
+    dialect: Dialect,
+}
+
+impl PartitionPolicy {
+    /// Creates `PartitionPolicy` given table, partition column and scheme; by default partitions
+    /// for current and next 7 periods are maintained.
+    pub fn new(
+        table: impl Into<String>,
+        column: impl Into<String>,
+        scheme: PartitionScheme,
+    ) -> PartitionPolicy {
+        PartitionPolicy {
+            table: table.into(),
+            column: column.into(),
+            scheme,
+            horizon: 7,
+            format: None,
+            dialect: Dialect::Generic,
+        }
+    }
+
+    /// Sets number of future periods to maintain partitions for (in addition to current one).
+    pub fn with_horizon(mut self, horizon: u32) -> PartitionPolicy {
+        self.horizon = horizon;
+        self
+    }
+
+    /// Sets `chrono` format of partition value (Hive style) or partition table suffix
+    /// (Postgres).
+    pub fn with_format(mut self, format: impl Into<String>) -> PartitionPolicy {
+        self.format = Some(format.into());
+        self
+    }
+
+    /// Sets SQL dialect used to list and create partitions.
+    pub fn with_dialect(mut self, dialect: Dialect) -> PartitionPolicy {
+        self.dialect = dialect;
+        self
+    }
+
+    /// Start dates of periods that should have partitions given current date.
+    pub fn periods(&self, today: NaiveDate) -> Vec<NaiveDate> {
+        let mut period = self.scheme.period_start(today);
+        let mut periods = Vec::with_capacity(self.horizon as usize + 1);
+        for _ in 0..=self.horizon {
+            periods.push(period);
+            period = self.scheme.next_period(period);
+        }
+        periods
+    }
+
+    /// Name of the partition (value or table) for period starting at given date.
+    pub fn partition_name(&self, period: NaiveDate) -> String {
+        match self.dialect {
+            Dialect::Postgres => format!(
+                "{}_{}",
+                self.table,
+                period.format(
+                    self.format
+                        .as_deref()
+                        .unwrap_or_else(|| self.scheme.default_suffix())
+                )
+            ),
+            _ => period
+                .format(
+                    self.format
+                        .as_deref()
+                        .unwrap_or_else(|| self.scheme.default_format()),
+                )
+                .to_string(),
+        }
+    }
+
+    /// Start date of the period given partition name (as listed) is for.
+    pub fn partition_period(&self, name: &str) -> Option<NaiveDate> {
+        let (value, format) = match self.dialect {
+            Dialect::Postgres => (
+                name.strip_prefix(&format!("{}_", self.table))?,
+                self.format
+                    .as_deref()
+                    .unwrap_or_else(|| self.scheme.default_suffix()),
+            ),
+            _ => (
+                name,
+                self.format
+                    .as_deref()
+                    .unwrap_or_else(|| self.scheme.default_format()),
+            ),
+        };
+        match self.scheme {
+            PartitionScheme::Daily => NaiveDate::parse_from_str(value, format).ok(),
+            // Monthly formats may not have day of month
+            PartitionScheme::Monthly => {
+                NaiveDate::parse_from_str(&format!("{}|01", value), &format!("{}|%d", format))
+                    .ok()
+                    .map(|date| self.scheme.period_start(date))
+            }
+        }
+    }
+
+    /// Query listing existing partitions.
+    pub fn list_query(&self) -> Sql {
+        match self.dialect {
+            Dialect::Postgres => format!(
+                "SELECT child.relname FROM pg_inherits JOIN pg_class parent ON parent.oid = pg_inherits.inhparent \
+                JOIN pg_class child ON child.oid = pg_inherits.inhrelid WHERE parent.relname = {}",
+                quote_literal(&self.table)
+            ),
+            _ => format!("SHOW PARTITIONS {}", self.table),
+        }
+        .into()
+    }
+
+    /// Statement creating partition for period starting at given date.
+    pub fn create_query(&self, period: NaiveDate) -> Sql {
+        let name = self.partition_name(period);
+        match self.dialect {
+            Dialect::Postgres => format!(
+                "CREATE TABLE IF NOT EXISTS {} PARTITION OF {} FOR VALUES FROM ({}) TO ({})",
+                name,
+                self.table,
+                quote_literal(&period.to_string()),
+                quote_literal(&self.scheme.next_period(period).to_string())
+            )
+            .into(),
+            _ => add_partition_query(&self.table, &[(&self.column, &name)]),
+        }
+    }
+
+    /// Statement dropping partition of given name.
+    pub fn drop_query(&self, name: &str) -> Sql {
+        match self.dialect {
+            Dialect::Postgres => format!("DROP TABLE {}", name),
+            _ => format!(
+                "ALTER TABLE {} DROP IF EXISTS PARTITION ({}={})",
+                self.table,
+                self.column,
+                quote_literal(name)
+            ),
+        }
+        .into()
+    }
+
+    /// Extracts partition name from a row listed by `list_query`.
+    fn listed_name(&self, listed: &str) -> Option<String> {
+        match self.dialect {
+            Dialect::Postgres => Some(listed.to_string()),
+            // Hive lists partitions as `col1=value1/col2=value2`
+            _ => listed.split('/').find_map(|part| {
+                let mut pair = part.splitn(2, '=');
+                match (pair.next(), pair.next()) {
+                    (Some(column), Some(value)) if column.eq_ignore_ascii_case(&self.column) => {
+                        Some(value.to_string())
+                    }
+                    _ => None,
+                }
+            }),
+        }
+    }
+
+    fn listed_names(&self, rows: Vec<ValueRow>) -> Vec<String> {
+        rows.into_iter()
+            .filter_map(|row| row.into_iter().next().flatten())
+            .filter_map(|value| self.listed_name(&value.to_string()))
+            .collect()
+    }
+
+    /// `EnsureSchema` dropping partitions of periods that have ended more than given number of
+    /// days ago (as of current UTC date when the check is run).
+    ///
+    /// Drop statements are destructive so the run needs `EnsureOptions::allow_destructive`.
+    pub fn retention_schema(&self, older_than_days: i64) -> EnsureSchema {
+        let policy = self.clone();
+        EnsureSchema::new(
+            format!("retention of {} partitions", self.table),
+            self.list_query(),
+            move |rows| {
+                let cutoff = Utc::today().naive_utc() - Duration::days(older_than_days);
+                Ok(policy
+                    .listed_names(collect_rows(rows)?)
+                    .into_iter()
+                    .filter(|name| {
+                        policy
+                            .partition_period(name)
+                            .is_some_and(|period| policy.scheme.next_period(period) <= cutoff)
+                    })
+                    .map(|name| policy.drop_query(&name))
+                    .collect())
+            },
+        )
+    }
+
+    /// `EnsureSchema` creating missing partitions for current and future periods; periods are
+    /// computed from current UTC date when the check is run.
+    pub fn ensure_schema(&self) -> EnsureSchema {
+        let policy = self.clone();
+        EnsureSchema::new(
+            format!("partitions of {}", self.table),
+            self.list_query(),
+            move |rows| {
+                let existing = policy.listed_names(collect_rows(rows)?);
+                Ok(policy
+                    .periods(Utc::today().naive_utc())
+                    .into_iter()
+                    .filter(|period| !existing.contains(&policy.partition_name(*period)))
+                    .map(|period| policy.create_query(period))
+                    .collect())
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd(year, month, day)
+    }
+
+    #[test]
+    fn period_boundaries() {
+        let daily = PartitionScheme::Daily;
+        assert_eq!(daily.period_start(date(2020, 3, 15)), date(2020, 3, 15));
+        assert_eq!(daily.next_period(date(2020, 2, 28)), date(2020, 2, 29));
+        assert_eq!(daily.next_period(date(2020, 2, 29)), date(2020, 3, 1));
+
+        let monthly = PartitionScheme::Monthly;
+        assert_eq!(monthly.period_start(date(2020, 3, 31)), date(2020, 3, 1));
+        assert_eq!(monthly.period_start(date(2020, 3, 1)), date(2020, 3, 1));
+        assert_eq!(monthly.next_period(date(2020, 1, 31)), date(2020, 2, 1));
+    }
+
+    #[test]
+    fn year_rollover() {
+        assert_eq!(
+            PartitionScheme::Daily.next_period(date(2020, 12, 31)),
+            date(2021, 1, 1)
+        );
+        assert_eq!(
+            PartitionScheme::Monthly.next_period(date(2020, 12, 15)),
+            date(2021, 1, 1)
+        );
+    }
+
+    #[test]
+    fn periods_roll_over_months_and_years() {
+        let policy = PartitionPolicy::new("events", "day", PartitionScheme::Daily).with_horizon(2);
+        assert_eq!(
+            policy.periods(date(2020, 12, 30)),
+            vec![date(2020, 12, 30), date(2020, 12, 31), date(2021, 1, 1)]
+        );
+
+        let policy =
+            PartitionPolicy::new("events", "month", PartitionScheme::Monthly).with_horizon(2);
+        assert_eq!(
+            policy.periods(date(2020, 11, 20)),
+            vec![date(2020, 11, 1), date(2020, 12, 1), date(2021, 1, 1)]
+        );
+    }
+
+    #[test]
+    fn partition_names() {
+        let policy = PartitionPolicy::new("events", "month", PartitionScheme::Monthly);
+        assert_eq!(policy.partition_name(date(2021, 1, 1)), "2021-01");
+
+        let policy = policy.with_dialect(Dialect::Postgres);
+        assert_eq!(policy.partition_name(date(2021, 1, 1)), "events_202101");
+        assert_eq!(
+            policy.create_query(date(2020, 12, 1)).as_str(),
+            "CREATE TABLE IF NOT EXISTS events_202012 PARTITION OF events FOR VALUES FROM ('2020-12-01') TO ('2021-01-01')"
+        );
+    }
+
+    #[test]
+    fn listed_partition_names() {
+        let policy = PartitionPolicy::new("events", "day", PartitionScheme::Daily);
+        assert_eq!(
+            policy.listed_name("region=eu/DAY=2020-12-31"),
+            Some("2020-12-31".to_string())
+        );
+        assert_eq!(policy.listed_name("region=eu"), None);
+    }
+}
@@ -0,0 +1,103 @@
+use crate::{EnsureOptions, EnsureReport, EnsureSchema, Renderer};
+use log::*;
+use odbc_iter::Handle;
+use std::io;
+use std::process::ExitCode;
+
+/// Outcome of `EnsureSchema::plan` mapped to process exit code so that pipelines can gate on
+/// pending schema changes.
+///
+/// Exit codes follow `terraform plan -detailed-exitcode`: 0 if nothing would change, 1 on error
+/// and 2 if changes are pending.
+///
+/// ```no_run
+/// use odbc_iter::Odbc;
+/// use odbc_iter_schema::{EnsureOptions, EnsureSchema, TextRenderer};
+/// use std::process::ExitCode;
+///
+/// fn main() -> ExitCode {
+///     let mut connection = match Odbc::connect("DSN=warehouse") {
+///         Ok(connection) => connection,
+///         Err(err) => {
+///             eprintln!("Failed to connect: {}", err);
+///             return ExitCode::from(1);
+///         }
+///     };
+///     let schema = EnsureSchema::with_count_check(
+///         "table foo",
+///         "SELECT COUNT(*) FROM information_schema.tables WHERE table_name = 'foo'",
+///         vec!["CREATE TABLE foo (id INTEGER)"],
+///     );
+///     schema
+///         .plan(
+///             &mut connection.handle(),
+///             &EnsureOptions::default(),
+///             &TextRenderer::default(),
+///             &mut std::io::stdout(),
+///         )
+///         .into()
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlanStatus {
+    /// Nothing would change; exit code 0.
+    NoChanges,
+    /// Plan could not be made (check failed or plan could not be written); exit code 1.
+    Error,
+    /// Some objects would change; exit code 2.
+    Pending,
+}
+
+impl PlanStatus {
+    /// Process exit code: 0 for `NoChanges`, 1 for `Error` and 2 for `Pending`.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            PlanStatus::NoChanges => 0,
+            PlanStatus::Error => 1,
+            PlanStatus::Pending => 2,
+        }
+    }
+}
+
+impl From<PlanStatus> for ExitCode {
+    fn from(status: PlanStatus) -> ExitCode {
+        ExitCode::from(status.exit_code())
+    }
+}
+
+/// For use with `std::process::exit`.
+impl From<PlanStatus> for i32 {
+    fn from(status: PlanStatus) -> i32 {
+        i32::from(status.exit_code())
+    }
+}
+
+impl EnsureSchema {
+    /// Performs dry run with given `EnsureOptions` and writes the report with given `Renderer`
+    /// (e.g. `TextRenderer` or `JsonRenderer`) to `out`; intended for `plan` command of command
+    /// line tools returning the status as exit code (see `PlanStatus`).
+    ///
+    /// Plan is written also if the run fails before the error is logged.
+    pub fn plan(
+        &self,
+        database: &mut Handle<'_>,
+        options: &EnsureOptions,
+        renderer: &dyn Renderer,
+        out: &mut dyn io::Write,
+    ) -> PlanStatus {
+        let mut report = EnsureReport::default();
+        let result = self.ensure_with_report(database, &options.clone().dry_run(true), &mut report);
+        if let Err(err) = renderer.render(&report, out) {
+            error!("Failed to write plan of {}: {}", self.name, err);
+            return PlanStatus::Error;
+        }
+        match result {
+            Err(err) => {
+                error!("Failed to plan {}: {}", self.name, err);
+                PlanStatus::Error
+            }
+            Ok(_) if report.has_changes() => PlanStatus::Pending,
+            Ok(_) => PlanStatus::NoChanges,
+        }
+    }
+}
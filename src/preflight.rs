@@ -0,0 +1,182 @@
+use crate::{sqlstate, LockTable};
+use log::*;
+use odbc_iter::{Connection, Handle, Odbc, ValueRow};
+use problem::prelude::*;
+use std::sync::{Arc, Mutex};
+use std::thread::sleep;
+use std::time::Duration;
+
+/// Query used to check that the database is responding.
+pub const PING_QUERY: &str = "SELECT 1";
+
+/// SQLSTATEs (or two character classes) of errors commonly safe to retry: connection errors,
+/// timeouts, serialization failures, deadlocks and database starting up.
+pub const TRANSIENT_SQLSTATES: &[&str] = &["08", "HYT00", "HYT01", "40001", "40P01", "57P03"];
+
+/// How many times and how often to retry an operation; delay between attempts is doubled after
+/// each failure up to maximum delay.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    attempts: u32,
+    initial_delay: Duration,
+    max_delay: Duration,
+    retryable_sqlstates: Option<Vec<String>>,
+}
+
+impl RetryPolicy {
+    /// Creates `RetryPolicy` making up to given number of attempts with delay starting at 1 second
+    /// and not exceeding 30 seconds.
+    pub fn new(attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            attempts,
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+            retryable_sqlstates: None,
+        }
+    }
+
+    /// Maximum number of attempts.
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    /// Sets delay before second attempt and maximum delay between attempts.
+    pub fn with_backoff(mut self, initial_delay: Duration, max_delay: Duration) -> RetryPolicy {
+        self.initial_delay = initial_delay;
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Uses the same delay between all attempts.
+    pub fn with_fixed_delay(self, delay: Duration) -> RetryPolicy {
+        self.with_backoff(delay, delay)
+    }
+
+    /// Retries only errors with SQLSTATE matching any of given SQLSTATEs or two character classes
+    /// (e.g. `TRANSIENT_SQLSTATES`); by default all errors are retried.
+    ///
+    /// Which errors are safe to retry differs between drivers; errors without SQLSTATE are not
+    /// retried.
+    pub fn with_retryable_sqlstates(mut self, sqlstates: &[&str]) -> RetryPolicy {
+        self.retryable_sqlstates = Some(sqlstates.iter().map(|state| state.to_string()).collect());
+        self
+    }
+
+    /// Returns `true` if error with given message should be retried.
+    pub fn is_retryable(&self, message: &str) -> bool {
+        match &self.retryable_sqlstates {
+            None => true,
+            Some(retryable) => sqlstate(message).is_some_and(|state| {
+                retryable.iter().any(|retryable| {
+                    if retryable.len() == 2 {
+                        state.starts_with(retryable.as_str())
+                    } else {
+                        state == retryable
+                    }
+                })
+            }),
+        }
+    }
+
+    /// Calls `f` until it succeeds, fails with error that is not retryable or attempts are
+    /// exhausted in which case last error is returned.
+    pub fn retry<T>(
+        &self,
+        what: &str,
+        mut f: impl FnMut() -> Result<T, Problem>,
+    ) -> Result<T, Problem> {
+        let mut delay = self.initial_delay;
+        let mut attempt = 1;
+        loop {
+            match f() {
+                Ok(value) => return Ok(value),
+                Err(err) if attempt < self.attempts && self.is_retryable(&err.to_string()) => {
+                    warn!(
+                        "{} failed (attempt {} of {}), retrying in {:?}: {}",
+                        what, attempt, self.attempts, delay, err
+                    );
+                    sleep(delay);
+                    delay = (delay * 2).min(self.max_delay);
+                    attempt += 1;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
+
+/// Connects to the database retrying accordingly to given `RetryPolicy` so that a process started
+/// before its database does not fail immediately.
+pub fn connect_with_retry(
+    connection_string: &str,
+    retry: &RetryPolicy,
+) -> Result<Connection, Problem> {
+    retry.retry("Connecting to database", || {
+        Odbc::connect(connection_string).map_err(Problem::from_error)
+    })
+}
+
+/// Pings the database with `PING_QUERY`.
+pub fn ping(database: &mut Handle<'_>) -> Result<(), Problem> {
+    database.query::<ValueRow>(PING_QUERY)?.single()?;
+    Ok(())
+}
+
+/// Pings the database with `PING_QUERY` retrying accordingly to given `RetryPolicy`.
+pub fn wait_for_database(database: &mut Handle<'_>, retry: &RetryPolicy) -> Result<(), Problem> {
+    retry.retry("Pinging database", || ping(database))
+}
+
+/// Secondary connection pinged periodically while long running meet statements or steps execute
+/// so that idle timeouts of firewalls or proxies do not kill the session and progress can be
+/// reported (see `EnsureOptions::keep_alive`).
+#[derive(Debug, Clone)]
+pub struct KeepAlive {
+    connection: Arc<Mutex<Connection>>,
+    interval: Duration,
+}
+
+impl KeepAlive {
+    /// Creates `KeepAlive` given secondary connection pinged every minute.
+    pub fn new(connection: Connection) -> KeepAlive {
+        KeepAlive {
+            connection: Arc::new(Mutex::new(connection)),
+            interval: Duration::from_secs(60),
+        }
+    }
+
+    /// Sets how often the connection is pinged and progress reported.
+    pub fn with_interval(mut self, interval: Duration) -> KeepAlive {
+        self.interval = interval;
+        self
+    }
+
+    /// How often the connection is pinged.
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Pings the connection; failures are only logged.
+    pub(crate) fn ping(&self) {
+        if let Ok(mut connection) = self.connection.lock() {
+            if let Err(err) = ping(&mut connection.handle()) {
+                warn!("Keep-alive ping has failed: {}", err);
+            }
+        }
+    }
+
+    /// Renews given lock over the connection so that it does not expire while a long statement
+    /// is running.
+    pub(crate) fn renew_lock(
+        &self,
+        lock_table: &LockTable,
+        lock_name: &str,
+        owner: &str,
+    ) -> Result<bool, Problem> {
+        let mut connection = self
+            .connection
+            .lock()
+            .map_err(|_| Problem::from_error("Keep-alive connection is poisoned"))?;
+        lock_table.renew(&mut connection.handle(), lock_name, owner)
+    }
+}
@@ -0,0 +1,303 @@
+use crate::state::{collect_rows, quote_literal, value_to_i64};
+use crate::{Dialect, EnsureSchema, Sql};
+use problem::prelude::*;
+use std::env;
+use std::sync::Arc;
+
+/// Source of secrets (e.g. passwords) looked up by name only when they are needed.
+pub trait SecretProvider {
+    fn secret(&self, name: &str) -> Result<String, Problem>;
+}
+
+impl<F> SecretProvider for F
+where
+    F: Fn(&str) -> Result<String, Problem>,
+{
+    fn secret(&self, name: &str) -> Result<String, Problem> {
+        self(name)
+    }
+}
+
+/// `SecretProvider` reading secrets from environment variables named after the secret.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EnvSecrets;
+
+impl SecretProvider for EnvSecrets {
+    fn secret(&self, name: &str) -> Result<String, Problem> {
+        env::var(name).map_err(|err| {
+            Problem::from_error(format!("Secret {} is not available: {}", name, err))
+        })
+    }
+}
+
+/// Database role created if it does not exist.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoleDef {
+    pub name: String,
+}
+
+impl RoleDef {
+    pub fn new(name: impl Into<String>) -> RoleDef {
+        RoleDef { name: name.into() }
+    }
+
+    /// Query producing single row with count of roles of this name; `None` if not supported in
+    /// the dialect.
+    pub fn check_query(&self, dialect: Dialect) -> Option<Sql> {
+        let name = quote_literal(&self.name);
+        let query = match dialect {
+            Dialect::SqlServer => format!(
+                "SELECT COUNT(*) FROM sys.database_principals WHERE name = {} AND type = 'R'",
+                name
+            ),
+            Dialect::Postgres => format!("SELECT COUNT(*) FROM pg_roles WHERE rolname = {}", name),
+            Dialect::MySql => format!("SELECT COUNT(*) FROM mysql.user WHERE user = {}", name),
+            _ => return None,
+        };
+        Some(query.into())
+    }
+
+    /// `EnsureSchema` creating the role if it does not exist.
+    pub fn ensure_schema(&self, dialect: Dialect) -> Result<EnsureSchema, Problem> {
+        Ok(EnsureSchema::with_count_check(
+            format!("role {}", self.name),
+            checked(self.check_query(dialect), "Role", &self.name, dialect)?,
+            vec![format!("CREATE ROLE {}", self.name)],
+        ))
+    }
+}
+
+/// Database user (login) created if it does not exist.
+///
+/// Password is looked up by secret name from `SecretProvider` only when the user needs to be
+/// created and statements containing it are flagged sensitive (see `Sql::sensitive`) so it is
+/// never logged or reported.
+///
+/// In SQL Server server login is created first (if missing) and the database user is mapped to
+/// it; in MySQL the user is created for given host (`%` by default).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserDef {
+    pub name: String,
+    /// Name of the password secret; users without password are created without login in SQL
+    /// Server.
+    pub password_secret: Option<String>,
+    /// MySQL host part of the account.
+    pub host: String,
+}
+
+impl UserDef {
+    pub fn new(name: impl Into<String>) -> UserDef {
+        UserDef {
+            name: name.into(),
+            password_secret: None,
+            host: "%".to_string(),
+        }
+    }
+
+    /// Sets name of the secret holding password of the user.
+    pub fn with_password_secret(mut self, secret: impl Into<String>) -> UserDef {
+        self.password_secret = Some(secret.into());
+        self
+    }
+
+    /// Sets MySQL host part of the account.
+    pub fn with_host(mut self, host: impl Into<String>) -> UserDef {
+        self.host = host.into();
+        self
+    }
+
+    /// Query producing single row with count of users of this name; `None` if not supported in
+    /// the dialect.
+    pub fn check_query(&self, dialect: Dialect) -> Option<Sql> {
+        let name = quote_literal(&self.name);
+        let query = match dialect {
+            Dialect::SqlServer => format!(
+                "SELECT COUNT(*) FROM sys.database_principals WHERE name = {} AND type IN ('S', 'U')",
+                name
+            ),
+            Dialect::Postgres => format!(
+                "SELECT COUNT(*) FROM pg_roles WHERE rolname = {} AND rolcanlogin",
+                name
+            ),
+            Dialect::MySql => format!(
+                "SELECT COUNT(*) FROM mysql.user WHERE user = {} AND host = {}",
+                name,
+                quote_literal(&self.host)
+            ),
+            _ => return None,
+        };
+        Some(query.into())
+    }
+
+    /// Statement creating the user given its password.
+    pub fn create_query(&self, dialect: Dialect, password: Option<&str>) -> Sql {
+        let query = match (dialect, password) {
+            (Dialect::SqlServer, Some(_)) => {
+                format!("CREATE USER {} FOR LOGIN {}", self.name, self.name)
+            }
+            (Dialect::SqlServer, None) => format!("CREATE USER {} WITHOUT LOGIN", self.name),
+            (Dialect::MySql, password) => format!(
+                "CREATE USER {}@{}{}",
+                quote_literal(&self.name),
+                quote_literal(&self.host),
+                password
+                    .map(|password| format!(" IDENTIFIED BY {}", quote_literal(password)))
+                    .unwrap_or_default()
+            ),
+            (_, password) => format!(
+                "CREATE ROLE {} LOGIN{}",
+                self.name,
+                password
+                    .map(|password| format!(" PASSWORD {}", quote_literal(password)))
+                    .unwrap_or_default()
+            ),
+        };
+        let query = Sql::from(query);
+        if password.is_some() && dialect != Dialect::SqlServer {
+            query.sensitive()
+        } else {
+            query
+        }
+    }
+
+    /// SQL Server statement creating server login given its password.
+    pub fn login_query(&self, password: &str) -> Sql {
+        Sql::from(format!(
+            "CREATE LOGIN {} WITH PASSWORD = N{}",
+            self.name,
+            quote_literal(password)
+        ))
+        .sensitive()
+    }
+
+    /// `EnsureSchema` creating the user (and SQL Server login) if it does not exist.
+    pub fn ensure_schema(
+        &self,
+        dialect: Dialect,
+        secrets: Arc<dyn SecretProvider>,
+    ) -> Result<EnsureSchema, Problem> {
+        let check_query = checked(self.check_query(dialect), "User", &self.name, dialect)?;
+        // declared statements carry secret name in place of the password
+        let declared = vec![self.create_query(dialect, self.password_secret.as_deref())];
+        let schema = self.password_schema(
+            format!("user {}", self.name),
+            check_query,
+            secrets.clone(),
+            move |user, password| user.create_query(dialect, password),
+        );
+        let schema = schema.with_declared_meet_queries(declared);
+
+        match (&self.password_secret, dialect) {
+            (Some(secret), Dialect::SqlServer) => {
+                let login = self.password_schema(
+                    format!("login {}", self.name),
+                    format!(
+                        "SELECT COUNT(*) FROM sys.server_principals WHERE name = {}",
+                        quote_literal(&self.name)
+                    )
+                    .into(),
+                    secrets,
+                    |user, password| user.login_query(password.unwrap_or_default()),
+                );
+                let declared = self.login_query(secret);
+                Ok(schema.with_meet_require(login.with_declared_meet_queries(vec![declared])))
+            }
+            _ => Ok(schema),
+        }
+    }
+
+    /// `EnsureSchema` running statement built with the password (looked up only if the count
+    /// check is zero).
+    fn password_schema(
+        &self,
+        name: String,
+        check_query: Sql,
+        secrets: Arc<dyn SecretProvider>,
+        create: impl Fn(&UserDef, Option<&str>) -> Sql + 'static,
+    ) -> EnsureSchema {
+        let user = self.clone();
+        EnsureSchema::new(name, check_query, move |rows| {
+            let count = collect_rows(rows)?
+                .into_iter()
+                .next()
+                .and_then(|row| value_to_i64(row.into_iter().next().flatten()))
+                .ok_or_problem("User check query did not return count")?;
+            if count != 0 {
+                return Ok(vec![]);
+            }
+            let password = user
+                .password_secret
+                .as_deref()
+                .map(|secret| secrets.secret(secret))
+                .transpose()?;
+            Ok(vec![create(&user, password.as_deref())])
+        })
+    }
+}
+
+/// Membership of a user or role in a role added if missing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoleMembership {
+    pub role: String,
+    pub member: String,
+}
+
+impl RoleMembership {
+    pub fn new(role: impl Into<String>, member: impl Into<String>) -> RoleMembership {
+        RoleMembership {
+            role: role.into(),
+            member: member.into(),
+        }
+    }
+
+    /// Query producing single row with count of matching memberships; `None` if not supported in
+    /// the dialect.
+    pub fn check_query(&self, dialect: Dialect) -> Option<Sql> {
+        let role = quote_literal(&self.role);
+        let member = quote_literal(&self.member);
+        let query = match dialect {
+            Dialect::SqlServer => format!(
+                "SELECT COUNT(*) FROM sys.database_role_members rm JOIN sys.database_principals r ON r.principal_id = rm.role_principal_id JOIN sys.database_principals m ON m.principal_id = rm.member_principal_id WHERE r.name = {} AND m.name = {}",
+                role, member
+            ),
+            Dialect::Postgres => format!(
+                "SELECT COUNT(*) FROM pg_auth_members am JOIN pg_roles r ON r.oid = am.roleid JOIN pg_roles m ON m.oid = am.member WHERE r.rolname = {} AND m.rolname = {}",
+                role, member
+            ),
+            Dialect::MySql => format!(
+                "SELECT COUNT(*) FROM mysql.role_edges WHERE from_user = {} AND to_user = {}",
+                role, member
+            ),
+            _ => return None,
+        };
+        Some(query.into())
+    }
+
+    /// Statement adding the member to the role.
+    pub fn grant_query(&self, dialect: Dialect) -> Sql {
+        match dialect {
+            Dialect::SqlServer => format!("ALTER ROLE {} ADD MEMBER {}", self.role, self.member),
+            _ => format!("GRANT {} TO {}", self.role, self.member),
+        }
+        .into()
+    }
+
+    /// `EnsureSchema` adding the member to the role if it is not a member.
+    pub fn ensure_schema(&self, dialect: Dialect) -> Result<EnsureSchema, Problem> {
+        let name = format!("{} in {}", self.member, self.role);
+        Ok(EnsureSchema::with_count_check(
+            format!("membership {}", name),
+            checked(self.check_query(dialect), "Membership", &name, dialect)?,
+            vec![self.grant_query(dialect)],
+        ))
+    }
+}
+
+fn checked(query: Option<Sql>, kind: &str, name: &str, dialect: Dialect) -> Result<Sql, Problem> {
+    query.ok_or_else(|| {
+        Problem::from_error(format!(
+            "{} {} cannot be checked in {:?} dialect",
+            kind, name, dialect
+        ))
+    })
+}
@@ -0,0 +1,120 @@
+use crate::state::{quote_literal, value_to_i64};
+use crate::{Dialect, Sql};
+use odbc_iter::{Handle, ValueRow};
+use problem::prelude::*;
+use std::fmt;
+
+/// Right the connected principal needs to have to meet the schema.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Privilege {
+    /// Create tables in given schema (or the default one).
+    CreateTable(Option<String>),
+    /// Create views in given schema (or the default one).
+    CreateView(Option<String>),
+    /// Read given table.
+    Select(String),
+    /// Insert rows into given table.
+    Insert(String),
+    /// Update rows of given table.
+    Update(String),
+    /// Delete rows from given table.
+    Delete(String),
+}
+
+impl fmt::Display for Privilege {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Privilege::CreateTable(Some(schema)) => write!(f, "CREATE TABLE in schema {}", schema),
+            Privilege::CreateTable(None) => write!(f, "CREATE TABLE"),
+            Privilege::CreateView(Some(schema)) => write!(f, "CREATE VIEW in schema {}", schema),
+            Privilege::CreateView(None) => write!(f, "CREATE VIEW"),
+            Privilege::Select(table) => write!(f, "SELECT on {}", table),
+            Privilege::Insert(table) => write!(f, "INSERT on {}", table),
+            Privilege::Update(table) => write!(f, "UPDATE on {}", table),
+            Privilege::Delete(table) => write!(f, "DELETE on {}", table),
+        }
+    }
+}
+
+impl Privilege {
+    /// Query producing single row with non-zero (true) value if the privilege is held; `None` if
+    /// it cannot be verified in given dialect.
+    pub fn check_query(&self, dialect: Dialect) -> Option<Sql> {
+        let query = match dialect {
+            Dialect::SqlServer => match self {
+                Privilege::CreateTable(schema) | Privilege::CreateView(schema) => {
+                    let permission = if let Privilege::CreateTable(_) = self {
+                        "CREATE TABLE"
+                    } else {
+                        "CREATE VIEW"
+                    };
+                    let database = format!(
+                        "HAS_PERMS_BY_NAME(NULL, 'DATABASE', {})",
+                        quote_literal(permission)
+                    );
+                    match schema {
+                        Some(schema) => format!(
+                            "SELECT CASE WHEN {} = 1 AND HAS_PERMS_BY_NAME({}, 'SCHEMA', 'ALTER') = 1 THEN 1 ELSE 0 END",
+                            database,
+                            quote_literal(schema)
+                        ),
+                        None => format!("SELECT {}", database),
+                    }
+                }
+                Privilege::Select(table)
+                | Privilege::Insert(table)
+                | Privilege::Update(table)
+                | Privilege::Delete(table) => format!(
+                    "SELECT HAS_PERMS_BY_NAME({}, 'OBJECT', {})",
+                    quote_literal(table),
+                    quote_literal(self.table_permission())
+                ),
+            },
+            Dialect::Postgres => match self {
+                Privilege::CreateTable(schema) | Privilege::CreateView(schema) => format!(
+                    "SELECT CASE WHEN has_schema_privilege({}, 'CREATE') THEN 1 ELSE 0 END",
+                    schema
+                        .as_deref()
+                        .map(quote_literal)
+                        .unwrap_or_else(|| "current_schema()".to_string())
+                ),
+                Privilege::Select(table)
+                | Privilege::Insert(table)
+                | Privilege::Update(table)
+                | Privilege::Delete(table) => format!(
+                    "SELECT CASE WHEN has_table_privilege({}, {}) THEN 1 ELSE 0 END",
+                    quote_literal(table),
+                    quote_literal(self.table_permission())
+                ),
+            },
+            _ => return None,
+        };
+        Some(query.into())
+    }
+
+    fn table_permission(&self) -> &'static str {
+        match self {
+            Privilege::Select(_) => "SELECT",
+            Privilege::Insert(_) => "INSERT",
+            Privilege::Update(_) => "UPDATE",
+            Privilege::Delete(_) => "DELETE",
+            Privilege::CreateTable(_) | Privilege::CreateView(_) => "CREATE",
+        }
+    }
+
+    /// Checks if the privilege is held; `None` if it cannot be verified in given dialect.
+    pub fn is_held(
+        &self,
+        database: &mut Handle<'_>,
+        dialect: Dialect,
+    ) -> Result<Option<bool>, Problem> {
+        let query = match self.check_query(dialect) {
+            Some(query) => query,
+            None => return Ok(None),
+        };
+        let row = database.query::<ValueRow>(&query)?.single()?;
+        Ok(Some(
+            value_to_i64(row.into_iter().next().flatten()).unwrap_or(0) != 0,
+        ))
+    }
+}
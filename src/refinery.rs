@@ -0,0 +1,97 @@
+use crate::{EnsureSchema, FlywayHistory};
+use lazy_static::lazy_static;
+use problem::prelude::*;
+use regex::Regex;
+use std::fs;
+use std::path::Path;
+
+lazy_static! {
+    static ref MIGRATION_NAME: Regex = Regex::new(r"^([UV])(\d+(?:\.\d+)?)__(\w+)").unwrap();
+}
+
+/// Migration following refinery naming convention: `V<version>__<name>` for versioned and
+/// `U<version>__<name>` for unversioned migrations (`.sql` files or `.rs` modules).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RefineryMigration {
+    pub version: String,
+    pub name: String,
+    /// `false` for unversioned (`U`) migrations that refinery applies out of order.
+    pub versioned: bool,
+    pub sql: String,
+}
+
+impl RefineryMigration {
+    /// Creates `RefineryMigration` given file or module name and its SQL (e.g. as returned by
+    /// `migration()` function of embedded `.rs` migration module); `None` if the name does not
+    /// follow the convention.
+    pub fn from_name(file_name: &str, sql: impl Into<String>) -> Option<RefineryMigration> {
+        let captures = MIGRATION_NAME.captures(file_name)?;
+        Some(RefineryMigration {
+            versioned: &captures[1] == "V",
+            version: captures[2].to_string(),
+            name: captures[3].to_string(),
+            sql: sql.into(),
+        })
+    }
+
+    fn sort_key(&self) -> Vec<u64> {
+        self.version
+            .split('.')
+            .map(|part| part.parse().unwrap_or(0))
+            .collect()
+    }
+}
+
+/// Loads `.sql` migrations following refinery naming convention from given directory and its
+/// subdirectories ordered by version; other files are ignored.
+pub fn load_refinery_migrations(dir: &Path) -> Result<Vec<RefineryMigration>, Problem> {
+    fn visit(dir: &Path, migrations: &mut Vec<RefineryMigration>) -> Result<(), Problem> {
+        for entry in fs::read_dir(dir).problem_while_with(|| format!("reading {:?}", dir))? {
+            let path = entry?.path();
+            if path.is_dir() {
+                visit(&path, migrations)?;
+                continue;
+            }
+            if path.extension().and_then(|extension| extension.to_str()) != Some("sql") {
+                continue;
+            }
+            let file_name = match path.file_name().and_then(|name| name.to_str()) {
+                Some(file_name) => file_name,
+                None => continue,
+            };
+            if MIGRATION_NAME.is_match(file_name) {
+                let sql = fs::read_to_string(&path)
+                    .problem_while_with(|| format!("reading migration {:?}", path))?;
+                migrations.extend(RefineryMigration::from_name(file_name, sql));
+            }
+        }
+        Ok(())
+    }
+
+    let mut migrations = Vec::new();
+    visit(dir, &mut migrations)?;
+    migrations.sort_by_key(RefineryMigration::sort_key);
+    if let Some(duplicate) = migrations
+        .windows(2)
+        .find(|pair| pair[0].sort_key() == pair[1].sort_key())
+    {
+        return problem!("Duplicate migration version: {}", duplicate[1].version);
+    }
+    Ok(migrations)
+}
+
+/// `EnsureSchema` applying given refinery migrations in order through `FlywayHistory` (see
+/// `FlywayHistory::migration`).
+pub fn refinery_migrations_schema(
+    name: impl Into<String>,
+    history: &FlywayHistory,
+    migrations: &[RefineryMigration],
+) -> Result<EnsureSchema, Problem> {
+    Ok(EnsureSchema::sequence(
+        name,
+        migrations
+            .iter()
+            .map(|migration| history.migration(&migration.version, &migration.name, &migration.sql))
+            .collect::<Result<_, _>>()?,
+    ))
+}
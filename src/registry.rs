@@ -0,0 +1,121 @@
+use crate::{EnsureOptions, EnsureReport, EnsureSchema, SchemaState, SchemaStateError};
+use odbc_iter::Handle;
+use problem::prelude::*;
+use std::fmt;
+
+/// Function building registered object.
+type FactoryFn = dyn Fn() -> EnsureSchema;
+
+struct Registered {
+    name: String,
+    requires: Vec<String>,
+    factory: Box<FactoryFn>,
+}
+
+/// Registry of objects referenced by name so that definitions can be split across modules (or
+/// crates) each registering its objects and naming objects it requires.
+///
+/// References are resolved into `EnsureSchema` tree when an object is ensured; objects required
+/// by multiple others are built for each of them and checked again after they were met.
+#[derive(Default)]
+pub struct SchemaRegistry {
+    objects: Vec<Registered>,
+}
+
+impl fmt::Debug for SchemaRegistry {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_map()
+            .entries(
+                self.objects
+                    .iter()
+                    .map(|object| (&object.name, &object.requires)),
+            )
+            .finish()
+    }
+}
+
+impl SchemaRegistry {
+    pub fn new() -> SchemaRegistry {
+        SchemaRegistry::default()
+    }
+
+    /// Registers object under given name with names of objects it requires (see
+    /// `EnsureSchema::with_meet_require`) and function building it; fails if the name is already
+    /// registered.
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        requires: &[&str],
+        factory: impl Fn() -> EnsureSchema + 'static,
+    ) -> Result<(), Problem> {
+        let name = name.into();
+        if self.objects.iter().any(|object| object.name == name) {
+            return problem!("Schema object '{}' is already registered", name);
+        }
+        self.objects.push(Registered {
+            name,
+            requires: requires.iter().map(|name| name.to_string()).collect(),
+            factory: Box::new(factory),
+        });
+        Ok(())
+    }
+
+    /// Names of registered objects in order of registration.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.objects.iter().map(|object| object.name.as_str())
+    }
+
+    /// Builds object of given name with its requirements; fails if any reference is not
+    /// registered or requirements form a cycle.
+    pub fn resolve(&self, name: &str) -> Result<EnsureSchema, Problem> {
+        self.resolve_path(name, &mut Vec::new())
+    }
+
+    fn resolve_path<'r>(
+        &'r self,
+        name: &str,
+        path: &mut Vec<&'r str>,
+    ) -> Result<EnsureSchema, Problem> {
+        let object = match self.objects.iter().find(|object| object.name == name) {
+            Some(object) => object,
+            None if path.is_empty() => {
+                return problem!("Schema object '{}' is not registered", name)
+            }
+            None => {
+                return problem!(
+                    "Schema object '{}' required by '{}' is not registered",
+                    name,
+                    path.last().unwrap()
+                )
+            }
+        };
+        if path.contains(&object.name.as_str()) {
+            return problem!(
+                "Requirement cycle: {} -> {}",
+                path.join(" -> "),
+                object.name
+            );
+        }
+        path.push(&object.name);
+        let mut schema = (object.factory)();
+        for required in &object.requires {
+            schema = schema.with_meet_require(self.resolve_path(required, path)?);
+        }
+        path.pop();
+        Ok(schema)
+    }
+
+    /// Resolves and ensures object of given name (see `EnsureSchema::ensure_with_report`);
+    /// resolution failure is reported as `CheckError`.
+    pub fn ensure_with_report(
+        &self,
+        database: &mut Handle<'_>,
+        name: &str,
+        options: &EnsureOptions,
+        report: &mut EnsureReport,
+    ) -> Result<SchemaState, SchemaStateError> {
+        self.resolve(name)
+            .map_err(|err| SchemaStateError::CheckError(name.to_string(), err))?
+            .ensure_with_report(database, options, report)
+    }
+}
@@ -0,0 +1,215 @@
+use crate::changelog::format_duration;
+use crate::{EnsureReport, ObjectReport, ObjectState};
+use std::io::{self, Write};
+use std::time::Duration;
+
+/// Formats `EnsureReport` of a run; report of a dry run renders as a plan.
+pub trait Renderer {
+    fn render(&self, report: &EnsureReport, out: &mut dyn Write) -> io::Result<()>;
+
+    /// Renders the report into a `String`.
+    fn render_to_string(&self, report: &EnsureReport) -> String {
+        let mut out = Vec::new();
+        self.render(report, &mut out)
+            .expect("writing to Vec failed");
+        String::from_utf8_lossy(&out).into_owned()
+    }
+}
+
+/// Human-readable tree of objects with their requirements indented below them.
+#[derive(Debug, Default, Clone)]
+pub struct TextRenderer {
+    statements: bool,
+}
+
+impl TextRenderer {
+    /// If set to `true` statements are listed below each object.
+    pub fn with_statements(mut self, statements: bool) -> TextRenderer {
+        self.statements = statements;
+        self
+    }
+}
+
+impl Renderer for TextRenderer {
+    fn render(&self, report: &EnsureReport, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(
+            out,
+            "{} {} ({})",
+            if report.dry_run { "Plan" } else { "Run" },
+            report.run_id,
+            format_duration(report.duration)
+        )?;
+        for object in tree_order(&report.objects) {
+            let indent = "  ".repeat(object.depth + 1);
+            let mark = match object.state {
+                ObjectState::Met => "[+]",
+                ObjectState::Changed => "[!]",
+                ObjectState::WouldChange => "[~]",
+                ObjectState::Skipped => "[-]",
+                ObjectState::Failed(_) => "[x]",
+                ObjectState::AssertionFailed(_) => "[#]",
+                ObjectState::VerificationFailed(_) => "[x]",
+                ObjectState::NotAttempted => "[ ]",
+            };
+            writeln!(
+                out,
+                "{}{} {}: {} ({})",
+                indent,
+                mark,
+                object.name,
+                object.state,
+                format_duration(object.duration)
+            )?;
+            if self.statements {
+                for statement in &object.statements {
+                    writeln!(
+                        out,
+                        "{}    {}",
+                        indent,
+                        statement
+                            .redacted()
+                            .replace('\n', &format!("\n{}    ", indent))
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Objects in order of the requirement tree (object followed by its requirements) given objects
+/// as reported (requirements before the object that required them).
+fn tree_order(objects: &[ObjectReport]) -> Vec<&ObjectReport> {
+    fn visit<'r>(objects: &'r [ObjectReport], depth: usize, ordered: &mut Vec<&'r ObjectReport>) {
+        let mut start = 0;
+        for (index, object) in objects.iter().enumerate() {
+            if object.depth <= depth {
+                ordered.push(object);
+                visit(&objects[start..index], object.depth + 1, ordered);
+                start = index + 1;
+            }
+        }
+        // Requirements of an object that did not get reported (run has failed)
+        ordered.extend(&objects[start..]);
+    }
+
+    let mut ordered = Vec::with_capacity(objects.len());
+    let depth = objects.iter().map(|object| object.depth).min().unwrap_or(0);
+    visit(objects, depth, &mut ordered);
+    ordered
+}
+
+/// JSON document with all report fields; durations are in milliseconds.
+#[derive(Debug, Default, Clone)]
+pub struct JsonRenderer;
+
+impl Renderer for JsonRenderer {
+    fn render(&self, report: &EnsureReport, out: &mut dyn Write) -> io::Result<()> {
+        write!(
+            out,
+            "{{\"run_id\":{},\"dry_run\":{},\"started_at\":{},\"duration_ms\":{},",
+            json_string(&report.run_id),
+            report.dry_run,
+            report.started_at,
+            millis(report.duration)
+        )?;
+        let metadata = &report.metadata;
+        write!(
+            out,
+            "\"metadata\":{{\"host\":{},\"pid\":{},\"os_user\":{},\"initiator\":{}}},\"objects\":[",
+            json_optional(metadata.host.as_deref()),
+            metadata.pid,
+            json_optional(metadata.os_user.as_deref()),
+            json_optional(metadata.initiator.as_deref())
+        )?;
+        for (index, object) in report.objects.iter().enumerate() {
+            if index > 0 {
+                write!(out, ",")?;
+            }
+            let (state, error) = match &object.state {
+                ObjectState::Met => ("met", None),
+                ObjectState::Changed => ("changed", None),
+                ObjectState::WouldChange => ("would_change", None),
+                ObjectState::Skipped => ("skipped", None),
+                ObjectState::Failed(message) => ("failed", Some(message.as_str())),
+                ObjectState::AssertionFailed(message) => {
+                    ("assertion_failed", Some(message.as_str()))
+                }
+                ObjectState::VerificationFailed(message) => {
+                    ("verification_failed", Some(message.as_str()))
+                }
+                ObjectState::NotAttempted => ("not_attempted", None),
+            };
+            write!(
+                out,
+                "{{\"name\":{},\"depth\":{},\"state\":\"{}\",\"error\":{},\"duration_ms\":{},\"check_duration_ms\":{},\"verify_duration_ms\":{},\"statements\":[",
+                json_string(&object.name),
+                object.depth,
+                state,
+                json_optional(error),
+                millis(object.duration),
+                json_optional_millis(object.check_duration),
+                json_optional_millis(object.verify_duration)
+            )?;
+            for (index, statement) in object.statements.iter().enumerate() {
+                if index > 0 {
+                    write!(out, ",")?;
+                }
+                write!(
+                    out,
+                    "{{\"sql\":{},\"affected_rows\":{},\"duration_ms\":{}}}",
+                    json_string(statement.redacted()),
+                    object
+                        .affected_rows
+                        .get(index)
+                        .copied()
+                        .flatten()
+                        .map_or_else(|| "null".to_string(), |rows| rows.to_string()),
+                    json_optional_millis(object.statement_durations.get(index).copied())
+                )?;
+            }
+            write!(out, "]}}")?;
+        }
+        writeln!(out, "]}}")
+    }
+}
+
+fn millis(duration: Duration) -> String {
+    format!("{:.3}", duration.as_secs_f64() * 1000.0)
+}
+
+fn json_optional_millis(duration: Option<Duration>) -> String {
+    duration.map_or_else(|| "null".to_string(), millis)
+}
+
+fn json_optional(value: Option<&str>) -> String {
+    value.map_or_else(|| "null".to_string(), json_string)
+}
+
+fn json_string(value: &str) -> String {
+    let mut json = String::with_capacity(value.len() + 2);
+    json.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+/// Flat SQL script of executed (or planned) statements (see `EnsureReport::write_script`).
+#[derive(Debug, Default, Clone)]
+pub struct SqlRenderer;
+
+impl Renderer for SqlRenderer {
+    fn render(&self, report: &EnsureReport, out: &mut dyn Write) -> io::Result<()> {
+        report.write_script(out)
+    }
+}
@@ -0,0 +1,378 @@
+use crate::hash::Fnv64;
+use crate::{rollback_statement, Dialect, EnsureSchema, RunMetadata, Sql};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io::{self, Write};
+use std::time::Duration;
+
+/// Outcome of ensuring single schema object.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ObjectState {
+    /// Check has found the object to be in target state.
+    Met,
+    /// Meet queries were executed and the object was verified to be in target state.
+    Changed,
+    /// Meet queries would be executed but this is dry run.
+    WouldChange,
+    /// Meeting the object was skipped on confirmation.
+    Skipped,
+    /// Check or meet has failed with given message.
+    Failed(String),
+    /// Assertion step found its invariant violated; nothing was met.
+    AssertionFailed(String),
+    /// Smoke test failed after the object was changed.
+    VerificationFailed(String),
+    /// Object was not reached before time budget of the run was exceeded.
+    NotAttempted,
+}
+
+impl fmt::Display for ObjectState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ObjectState::Met => write!(f, "met"),
+            ObjectState::Changed => write!(f, "changed"),
+            ObjectState::WouldChange => write!(f, "would change"),
+            ObjectState::Skipped => write!(f, "skipped"),
+            ObjectState::Failed(message) => write!(f, "failed: {}", message),
+            ObjectState::AssertionFailed(message) => write!(f, "assertion failed: {}", message),
+            ObjectState::VerificationFailed(message) => {
+                write!(f, "verification failed: {}", message)
+            }
+            ObjectState::NotAttempted => write!(f, "not attempted"),
+        }
+    }
+}
+
+impl ObjectState {
+    /// Returns `true` if the object was changed or would be changed in dry run.
+    pub fn is_change(&self) -> bool {
+        *self == ObjectState::Changed || *self == ObjectState::WouldChange
+    }
+
+    /// Returns `true` if check, meet, assertion or smoke test of the object has failed.
+    pub fn is_failure(&self) -> bool {
+        matches!(
+            self,
+            ObjectState::Failed(_)
+                | ObjectState::AssertionFailed(_)
+                | ObjectState::VerificationFailed(_)
+        )
+    }
+}
+
+/// Report on single schema object ensured during a run.
+#[derive(Debug, Clone)]
+pub struct ObjectReport {
+    pub name: String,
+    /// Depth of the object in the requirement tree; the ensured object has depth 0.
+    ///
+    /// Objects are reported after their requirements.
+    pub depth: usize,
+    pub state: ObjectState,
+    /// `true` for group whose state is derived from states of its members.
+    pub group: bool,
+    /// Meet statements executed in order (or these that would be executed in dry run).
+    pub statements: Vec<Sql>,
+    /// Number of rows affected by each executed statement if reported by the driver; empty in
+    /// dry run.
+    pub affected_rows: Vec<Option<i64>>,
+    /// Time spent running the check query and ensure function; `None` if the check was bypassed.
+    pub check_duration: Option<Duration>,
+    /// Wall-clock time of each executed statement; empty in dry run.
+    pub statement_durations: Vec<Duration>,
+    /// Time spent verifying the object after meet statements were executed.
+    pub verify_duration: Option<Duration>,
+    /// Time spent checking, meeting and verifying the object excluding its requirements.
+    pub duration: Duration,
+}
+
+/// Report of all schema objects visited during single run in order they were ensured.
+#[derive(Debug, Default, Clone)]
+pub struct EnsureReport {
+    pub run_id: String,
+    pub dry_run: bool,
+    /// Seconds since UNIX epoch when the run has started.
+    pub started_at: i64,
+    pub metadata: RunMetadata,
+    pub objects: Vec<ObjectReport>,
+    /// Total duration of the run.
+    pub duration: Duration,
+}
+
+/// Objects whose outcome differs between two runs (see `EnsureReport::diff`); names are listed
+/// in order of the current report.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ReportDiff {
+    /// Objects changed (or that would be changed) that were met in the previous report (e.g.
+    /// object met on last deploy was dropped since).
+    pub newly_changed: Vec<String>,
+    /// Objects failed that have not failed in the previous report (or were not in it).
+    pub newly_failed: Vec<String>,
+    /// Objects met or changed that have failed in the previous report.
+    pub recovered: Vec<String>,
+}
+
+impl ReportDiff {
+    /// Returns `true` if there are neither newly changed nor newly failed objects.
+    pub fn is_clean(&self) -> bool {
+        self.newly_changed.is_empty() && self.newly_failed.is_empty()
+    }
+}
+
+/// Statements that would undo changes applied during a run.
+#[derive(Debug, Default, Clone)]
+pub struct Rollback {
+    /// Inverse statements in order they should be executed.
+    pub statements: Vec<Sql>,
+    /// Applied statements for which inverse could not be derived.
+    pub irreversible: Vec<Sql>,
+}
+
+impl EnsureReport {
+    /// Reports objects of the tree that were not reported during the run as not attempted.
+    pub(crate) fn not_attempted(&mut self, schema: &EnsureSchema) {
+        let reported = self
+            .objects
+            .iter()
+            .map(|object| object.name.clone())
+            .collect::<HashSet<_>>();
+        let mut added = HashSet::new();
+        for node in schema.iter() {
+            let name = &node.schema.name;
+            if reported.contains(name) || !added.insert(name.clone()) {
+                continue;
+            }
+            self.objects.push(ObjectReport {
+                name: name.clone(),
+                depth: node.depth(),
+                state: ObjectState::NotAttempted,
+                group: node.schema.is_group(),
+                statements: Vec::new(),
+                affected_rows: Vec::new(),
+                check_duration: None,
+                statement_durations: Vec::new(),
+                verify_duration: None,
+                duration: Duration::default(),
+            });
+        }
+    }
+
+    /// Returns `true` if any object was changed or would be changed in dry run.
+    pub fn has_changes(&self) -> bool {
+        self.objects.iter().any(|object| object.state.is_change())
+    }
+
+    /// Compares outcome of each object with the previous run report matching objects by name.
+    pub fn diff(&self, previous: &EnsureReport) -> ReportDiff {
+        let mut previous_states = HashMap::new();
+        for object in &previous.objects {
+            previous_states
+                .entry(object.name.as_str())
+                .or_insert(&object.state);
+        }
+        let mut diff = ReportDiff::default();
+        let mut seen = HashSet::new();
+        for object in &self.objects {
+            if !seen.insert(object.name.as_str()) {
+                continue;
+            }
+            let previous = previous_states.get(object.name.as_str());
+            let previously_failed = previous.is_some_and(|state| state.is_failure());
+            if object.state.is_failure() {
+                if !previously_failed {
+                    diff.newly_failed.push(object.name.clone());
+                }
+            } else if previously_failed {
+                if object.state == ObjectState::Met || object.state == ObjectState::Changed {
+                    diff.recovered.push(object.name.clone());
+                }
+            } else if object.state.is_change() && previous == Some(&&ObjectState::Met) {
+                diff.newly_changed.push(object.name.clone());
+            }
+        }
+        diff
+    }
+
+    /// Hash of statements of each object that would be (or was) changed in order; dry run report
+    /// hash can be passed to `EnsureOptions::approved_plan` so that only reviewed plan is applied.
+    ///
+    /// Statements are normalized (see `normalize_sql`) so cosmetic differences do not matter.
+    /// Objects that would change without known statements (e.g. opaque steps) are hashed by
+    /// name.
+    pub fn plan_hash(&self) -> String {
+        let mut hasher = Fnv64::new();
+        for object in self
+            .objects
+            .iter()
+            .filter(|object| !object.statements.is_empty() || object.state.is_change())
+        {
+            hasher.write_str(&object.name);
+            if object.statements.is_empty() {
+                // Changed and would change are the same so applied run hashes as its plan
+                hasher.write_str("change");
+            }
+            for statement in &object.statements {
+                hasher.write_str(&statement.normalized());
+            }
+        }
+        hasher.finish_hex()
+    }
+
+    /// Lists up to `limit` executed statements that took longest as tuples of object name,
+    /// statement and its duration.
+    pub fn slowest_statements(&self, limit: usize) -> Vec<(&str, &Sql, Duration)> {
+        let mut statements = self
+            .objects
+            .iter()
+            .flat_map(|object| {
+                object
+                    .statements
+                    .iter()
+                    .zip(object.statement_durations.iter())
+                    .map(move |(statement, duration)| (object.name.as_str(), statement, *duration))
+            })
+            .collect::<Vec<_>>();
+        statements.sort_by_key(|statement| std::cmp::Reverse(statement.2));
+        statements.truncate(limit);
+        statements
+    }
+
+    /// Derives rollback of everything applied in the run, newest statement first.
+    ///
+    /// Only `CREATE TABLE`, `CREATE INDEX`, `CREATE VIEW` and `ALTER TABLE ... ADD COLUMN` can be
+    /// reversed (indexes depending on dialect, see `rollback_statement`); other applied statements
+    /// are listed as irreversible.
+    pub fn rollback(&self, dialect: Dialect) -> Rollback {
+        let mut rollback = Rollback::default();
+        if self.dry_run {
+            return rollback;
+        }
+        for statement in self
+            .objects
+            .iter()
+            .rev()
+            .flat_map(|object| object.statements.iter().rev())
+        {
+            match rollback_statement(statement, dialect) {
+                Some(inverse) => rollback.statements.push(inverse),
+                None => rollback.irreversible.push(statement.clone()),
+            }
+        }
+        rollback
+    }
+
+    /// Writes statements executed in the run (or that would be executed in dry run) as SQL script
+    /// with a comment header for each object.
+    pub fn write_script(&self, out: &mut dyn Write) -> io::Result<()> {
+        self.write_dialect_script(Dialect::Generic, out)
+    }
+
+    /// Same as `write_script` but statements are terminated as expected by the dialect client
+    /// (see `Dialect::script_statement`).
+    pub fn write_dialect_script(&self, dialect: Dialect, out: &mut dyn Write) -> io::Result<()> {
+        if self.dry_run {
+            writeln!(out, "-- Dry run {}", self.run_id)?;
+        } else {
+            writeln!(out, "-- Run {}", self.run_id)?;
+        }
+        for object in self
+            .objects
+            .iter()
+            .filter(|object| !object.statements.is_empty())
+        {
+            writeln!(out, "\n-- {}", object.name.replace('\n', " "))?;
+            for statement in &object.statements {
+                writeln!(out, "{}", dialect.script_statement(statement))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes rollback of the run as SQL script; irreversible statements are listed as comments.
+    pub fn write_rollback_script(&self, dialect: Dialect, out: &mut dyn Write) -> io::Result<()> {
+        let rollback = self.rollback(dialect);
+        writeln!(out, "-- Rollback of run {}", self.run_id)?;
+        for statement in &rollback.irreversible {
+            writeln!(
+                out,
+                "-- irreversible: {}",
+                statement.redacted().replace('\n', " ")
+            )?;
+        }
+        for statement in &rollback.statements {
+            writeln!(out, "{};", statement)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn object(name: &str, state: ObjectState, statements: &[&str]) -> ObjectReport {
+        ObjectReport {
+            name: name.to_string(),
+            depth: 0,
+            state,
+            group: false,
+            statements: statements
+                .iter()
+                .map(|statement| Sql::from(*statement))
+                .collect(),
+            affected_rows: Vec::new(),
+            check_duration: None,
+            statement_durations: Vec::new(),
+            verify_duration: None,
+            duration: Duration::default(),
+        }
+    }
+
+    fn plan_hash(objects: Vec<ObjectReport>) -> String {
+        EnsureReport {
+            objects,
+            ..EnsureReport::default()
+        }
+        .plan_hash()
+    }
+
+    #[test]
+    fn plan_hash_covers_statements() {
+        let table = object(
+            "table",
+            ObjectState::WouldChange,
+            &["CREATE TABLE foo (id INT)"],
+        );
+        assert_eq!(
+            plan_hash(vec![table.clone()]),
+            plan_hash(vec![
+                object("other", ObjectState::Met, &[]),
+                object(
+                    "table",
+                    ObjectState::Changed,
+                    &["CREATE  TABLE foo (id INT);"]
+                ),
+            ])
+        );
+        assert_ne!(
+            plan_hash(vec![table]),
+            plan_hash(vec![object(
+                "table",
+                ObjectState::WouldChange,
+                &["CREATE TABLE foo (id BIGINT)"]
+            )])
+        );
+    }
+
+    #[test]
+    fn plan_hash_covers_changes_without_statements() {
+        assert_ne!(
+            plan_hash(Vec::new()),
+            plan_hash(vec![object("step", ObjectState::WouldChange, &[])])
+        );
+        assert_eq!(
+            plan_hash(vec![object("step", ObjectState::WouldChange, &[])]),
+            plan_hash(vec![object("step", ObjectState::Changed, &[])])
+        );
+    }
+}
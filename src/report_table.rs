@@ -0,0 +1,105 @@
+use crate::state::{quote_literal, table_schema, value_to_i64};
+use crate::{Dialect, EnsureReport, EnsureSchema, JsonRenderer, Renderer, SchemaStateError};
+use odbc_iter::{Handle, Value, ValueRow};
+use problem::prelude::*;
+
+/// Table maintained by this crate storing JSON report (see `JsonRenderer`) of every ensure run
+/// that was not a dry run, one row per run, so that recent runs can be inspected from any SQL
+/// client.
+#[derive(Debug, Clone)]
+pub struct ReportTable {
+    table: String,
+}
+
+/// Run read back from `ReportTable`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredRun {
+    pub run_id: String,
+    /// Seconds since UNIX epoch when the run has started.
+    pub started_at: i64,
+    /// Seconds since UNIX epoch when the run has finished.
+    pub finished_at: i64,
+    /// `ok` or error the run has failed with.
+    pub outcome: String,
+    /// Report rendered with `JsonRenderer`.
+    pub report: String,
+}
+
+impl ReportTable {
+    /// Creates `ReportTable` given table name.
+    pub fn new(table: impl Into<String>) -> ReportTable {
+        ReportTable {
+            table: table.into(),
+        }
+    }
+
+    /// Name of the table.
+    pub fn table(&self) -> &str {
+        &self.table
+    }
+
+    /// `EnsureSchema` that creates the table if it does not exist.
+    pub fn ensure_schema(&self, dialect: Dialect) -> EnsureSchema {
+        let create = format!(
+            "CREATE TABLE {} (run_id VARCHAR(64) NOT NULL, started_at BIGINT NOT NULL, finished_at BIGINT NOT NULL, \
+            outcome VARCHAR(4000) NOT NULL, report {})",
+            self.table,
+            dialect.text_type()
+        );
+        table_schema(format!("report table {}", self.table), &self.table, create)
+    }
+
+    /// Writes row with given run report and error the run has failed with, if any.
+    pub fn record(
+        &self,
+        database: &mut Handle<'_>,
+        report: &EnsureReport,
+        error: Option<&SchemaStateError>,
+    ) -> Result<(), Problem> {
+        let outcome = match error {
+            Some(err) => format!("failed: {}", err),
+            None => "ok".to_string(),
+        };
+        let query = format!(
+            "INSERT INTO {} (run_id, started_at, finished_at, outcome, report) VALUES ({}, {}, {}, {}, {})",
+            self.table,
+            quote_literal(&report.run_id),
+            report.started_at,
+            report.started_at + report.duration.as_secs() as i64,
+            quote_literal(&outcome.chars().take(4000).collect::<String>()),
+            quote_literal(&JsonRenderer.render_to_string(report))
+        );
+        database.query::<()>(&query)?.no_result()?;
+        Ok(())
+    }
+
+    /// Reads up to `limit` most recent runs, newest first.
+    pub fn recent_runs(
+        &self,
+        database: &mut Handle<'_>,
+        limit: usize,
+    ) -> Result<Vec<StoredRun>, Problem> {
+        let query = format!(
+            "SELECT run_id, started_at, finished_at, outcome, report FROM {} ORDER BY started_at DESC",
+            self.table
+        );
+        let mut runs = Vec::new();
+        for row in database.query::<ValueRow>(&query)?.take(limit) {
+            let mut row = row?.into_iter();
+            let mut next = || row.next().flatten();
+            let text =
+                |value: Option<Value>| value.map(|value| value.to_string()).unwrap_or_default();
+            let run_id = text(next());
+            let started_at = value_to_i64(next()).ok_or_problem("Run has no start time")?;
+            let finished_at = value_to_i64(next()).ok_or_problem("Run has no finish time")?;
+            runs.push(StoredRun {
+                run_id,
+                started_at,
+                finished_at,
+                outcome: text(next()),
+                report: text(next()),
+            });
+        }
+        Ok(runs)
+    }
+}
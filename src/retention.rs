@@ -0,0 +1,163 @@
+use crate::state::{collect_rows, quote_literal};
+use crate::{Dialect, EnsureSchema, Sql};
+use chrono::{Duration, NaiveDate, Utc};
+use problem::prelude::*;
+
+/// Drops dated tables (e.g. staging tables named `staging_20200131`) older than a threshold.
+///
+/// Only tables of one schema are considered: the one set with `with_schema` or the current
+/// schema of the connection (which is not known in `Dialect::Generic` and `Dialect::Hive`, in
+/// which case the schema has to be set).
+///
+/// Drop statements are destructive so the run needs `EnsureOptions::allow_destructive`; dry run
+/// lists tables that would be dropped.
+#[derive(Debug, Clone)]
+pub struct TableRetention {
+    prefix: String,
+    date_format: String,
+    older_than_days: i64,
+    schema: Option<String>,
+    dialect: Dialect,
+}
+
+impl TableRetention {
+    /// Creates `TableRetention` for tables named with given prefix followed by date in given
+    /// `chrono` format (e.g. `%Y%m%d`) dropping these dated more than given number of days ago.
+    pub fn new(
+        prefix: impl Into<String>,
+        date_format: impl Into<String>,
+        older_than_days: i64,
+    ) -> TableRetention {
+        TableRetention {
+            prefix: prefix.into(),
+            date_format: date_format.into(),
+            older_than_days,
+            schema: None,
+            dialect: Dialect::Generic,
+        }
+    }
+
+    /// Sets schema (database in MySQL) dated tables are dropped from.
+    pub fn with_schema(mut self, schema: impl Into<String>) -> TableRetention {
+        self.schema = Some(schema.into());
+        self
+    }
+
+    /// Sets SQL dialect used to find the current schema and quote table names.
+    pub fn with_dialect(mut self, dialect: Dialect) -> TableRetention {
+        self.dialect = dialect;
+        self
+    }
+
+    /// Query listing schema and name of candidate tables.
+    pub fn list_query(&self) -> Sql {
+        let prefix = self
+            .prefix
+            .replace('!', "!!")
+            .replace('%', "!%")
+            .replace('_', "!_");
+        let mut query = format!(
+            "SELECT table_schema, table_name FROM information_schema.tables WHERE UPPER(table_name) LIKE UPPER({}) ESCAPE '!'",
+            quote_literal(&format!("{}%", prefix))
+        );
+        let schema = match &self.schema {
+            Some(schema) => Some(quote_literal(schema)),
+            None => self.dialect.current_schema().map(str::to_string),
+        };
+        if let Some(schema) = schema {
+            query.push_str(&format!(" AND table_schema = {}", schema));
+        }
+        query.into()
+    }
+
+    /// Date of table with given name if it is one of the dated tables.
+    pub fn table_date(&self, table: &str) -> Option<NaiveDate> {
+        let suffix = table.get(self.prefix.len()..)?;
+        if !table
+            .get(..self.prefix.len())?
+            .eq_ignore_ascii_case(&self.prefix)
+        {
+            return None;
+        }
+        NaiveDate::parse_from_str(suffix, &self.date_format).ok()
+    }
+
+    /// `EnsureSchema` dropping dated tables older than the threshold as of current UTC date when
+    /// the check is run.
+    ///
+    /// Check fails if schema was not set and current schema is not known in the dialect.
+    pub fn ensure_schema(&self) -> EnsureSchema {
+        let retention = self.clone();
+        EnsureSchema::new(
+            format!("retention of {}* tables", self.prefix),
+            self.list_query(),
+            move |rows| {
+                if retention.schema.is_none() && retention.dialect.current_schema().is_none() {
+                    return problem!(
+                        "Schema of {}* tables has to be set in {:?} dialect",
+                        retention.prefix,
+                        retention.dialect
+                    );
+                }
+                let cutoff = Utc::today().naive_utc() - Duration::days(retention.older_than_days);
+                let mut drops = Vec::new();
+                for row in collect_rows(rows)? {
+                    let mut row = row.into_iter();
+                    let (schema, table) = match (row.next().flatten(), row.next().flatten()) {
+                        (Some(schema), Some(table)) => (schema.to_string(), table.to_string()),
+                        _ => continue,
+                    };
+                    if retention
+                        .table_date(&table)
+                        .is_some_and(|date| date < cutoff)
+                    {
+                        drops.push(
+                            format!(
+                                "DROP TABLE {}.{}",
+                                retention.dialect.quote_identifier(&schema),
+                                retention.dialect.quote_identifier(&table)
+                            )
+                            .into(),
+                        );
+                    }
+                }
+                Ok(drops)
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn list_query_escapes_prefix_and_filters_schema() {
+        let retention = TableRetention::new("stg_100%", "%Y%m%d", 7);
+        assert_eq!(
+            retention.list_query().as_str(),
+            "SELECT table_schema, table_name FROM information_schema.tables WHERE UPPER(table_name) LIKE UPPER('stg!_100!%%') ESCAPE '!'"
+        );
+        assert!(retention
+            .clone()
+            .with_dialect(Dialect::Postgres)
+            .list_query()
+            .ends_with(" AND table_schema = current_schema()"));
+        assert!(retention
+            .with_dialect(Dialect::Postgres)
+            .with_schema("staging")
+            .list_query()
+            .ends_with(" AND table_schema = 'staging'"));
+    }
+
+    #[test]
+    fn table_date() {
+        let retention = TableRetention::new("staging_", "%Y%m%d", 7);
+        assert_eq!(
+            retention.table_date("STAGING_20200131"),
+            Some(NaiveDate::from_ymd(2020, 1, 31))
+        );
+        assert_eq!(retention.table_date("staging_latest"), None);
+        assert_eq!(retention.table_date("other_20200131"), None);
+    }
+}
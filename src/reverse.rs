@@ -0,0 +1,177 @@
+use crate::state::quote_literal;
+use crate::view::exists_schema;
+use crate::{create_view_query, table_columns, Dialect, EnsureSchema, Sql};
+use odbc_iter::{Handle, ValueRow};
+use problem::prelude::*;
+use std::io::{self, Write};
+
+const SYSTEM_SCHEMAS: &str =
+    "'information_schema', 'pg_catalog', 'sys', 'mysql', 'performance_schema', 'INFORMATION_SCHEMA'";
+
+/// Kind of reverse engineered object.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectKind {
+    Table,
+    View,
+    Index,
+}
+
+/// Object read from a live database with statement creating it.
+#[derive(Debug, Clone)]
+pub struct ReverseObject {
+    pub kind: ObjectKind,
+    pub name: String,
+    pub create: Sql,
+}
+
+impl ReverseObject {
+    /// `EnsureSchema` that creates the object if it does not exist.
+    pub fn ensure_schema(&self, dialect: Dialect) -> EnsureSchema {
+        let (name, exists_query) = match self.kind {
+            ObjectKind::Table => (
+                format!("table {}", self.name),
+                dialect.table_exists_query(&self.name),
+            ),
+            ObjectKind::View => (
+                format!("view {}", self.name),
+                dialect.view_exists_query(&self.name),
+            ),
+            ObjectKind::Index => (
+                format!("index {}", self.name),
+                format!(
+                    "SELECT indexname FROM pg_indexes WHERE indexname = {}",
+                    quote_literal(&self.name)
+                )
+                .into(),
+            ),
+        };
+        exists_schema(name, exists_query, self.create.clone())
+    }
+}
+
+/// Definitions of objects of a live database to be used as a starting point when adopting this
+/// crate on an existing system.
+#[derive(Debug, Clone)]
+pub struct ReverseEngineered {
+    pub dialect: Dialect,
+    /// Tables followed by views and indexes.
+    pub objects: Vec<ReverseObject>,
+}
+
+impl ReverseEngineered {
+    /// `EnsureSchema` for each object; views and indexes should be made to require tables they
+    /// depend on.
+    pub fn ensure_schemas(&self) -> Vec<EnsureSchema> {
+        self.objects
+            .iter()
+            .map(|object| object.ensure_schema(self.dialect))
+            .collect()
+    }
+
+    /// Writes create statements as SQL script.
+    pub fn write_script(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "-- Reverse engineered {:?} schema", self.dialect)?;
+        for object in &self.objects {
+            writeln!(out, "\n-- {:?} {}", object.kind, object.name)?;
+            writeln!(out, "{};", object.create)?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads tables, views and (for Postgres) indexes of a live database.
+///
+/// Tables are recreated from `information_schema.columns` so only column names, types and
+/// nullability are captured; constraints, defaults and indexes of other dialects need to be added
+/// by hand.
+pub fn reverse_engineer(
+    database: &mut Handle<'_>,
+    dialect: Dialect,
+) -> Result<ReverseEngineered, Problem> {
+    let mut objects = Vec::new();
+
+    let tables = query_pairs(
+        database,
+        &format!(
+            "SELECT table_schema, table_name FROM information_schema.tables WHERE table_type = 'BASE TABLE' AND table_schema NOT IN ({}) ORDER BY table_name",
+            SYSTEM_SCHEMAS
+        ),
+    )?;
+    for (schema, table) in tables {
+        let columns = match table_columns(database, dialect, &format!("{}.{}", schema, table))? {
+            Some(columns) => columns,
+            None => continue,
+        };
+        let columns = columns
+            .iter()
+            .map(|column| {
+                let mut column = column.clone();
+                if column.max_length.as_deref() == Some("-1") {
+                    column.max_length = Some("MAX".to_string());
+                }
+                column.to_string()
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        objects.push(ReverseObject {
+            kind: ObjectKind::Table,
+            create: format!("CREATE TABLE {} ({})", table, columns).into(),
+            name: table,
+        });
+    }
+
+    let views = query_pairs(
+        database,
+        &format!(
+            "SELECT table_name, view_definition FROM information_schema.views WHERE table_schema NOT IN ({}) ORDER BY table_name",
+            SYSTEM_SCHEMAS
+        ),
+    )?;
+    for (view, definition) in views {
+        let definition = definition.trim();
+        // SQL Server lists complete CREATE VIEW statement
+        let create = if definition
+            .get(..6)
+            .is_some_and(|start| start.eq_ignore_ascii_case("CREATE"))
+        {
+            definition.into()
+        } else {
+            create_view_query(dialect, &view, definition)
+        };
+        objects.push(ReverseObject {
+            kind: ObjectKind::View,
+            name: view,
+            create,
+        });
+    }
+
+    if dialect == Dialect::Postgres {
+        let indexes = query_pairs(
+            database,
+            "SELECT indexname, indexdef FROM pg_indexes WHERE schemaname = current_schema() AND indexname NOT IN (SELECT conname FROM pg_constraint) ORDER BY indexname",
+        )?;
+        for (index, definition) in indexes {
+            objects.push(ReverseObject {
+                kind: ObjectKind::Index,
+                name: index,
+                create: definition.into(),
+            });
+        }
+    }
+
+    Ok(ReverseEngineered { dialect, objects })
+}
+
+fn query_pairs(database: &mut Handle<'_>, query: &str) -> Result<Vec<(String, String)>, Problem> {
+    database
+        .query::<ValueRow>(query)?
+        .map(|row| {
+            let mut row = row?
+                .into_iter()
+                .map(|value| value.map(|value| value.to_string()));
+            let name = row.next().flatten().ok_or_problem("Object name is NULL")?;
+            let definition = row.next().flatten().unwrap_or_default();
+            Ok((name, definition))
+        })
+        .collect()
+}
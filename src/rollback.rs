@@ -0,0 +1,139 @@
+use crate::{Dialect, Sql};
+use lazy_static::lazy_static;
+use regex::Regex;
+
+lazy_static! {
+    static ref CREATE_TABLE: Regex =
+        Regex::new(r"(?is)^\s*CREATE\s+TABLE\s+(?:IF\s+NOT\s+EXISTS\s+)?([\w\.\[\]`\x22]+)")
+            .unwrap();
+    static ref CREATE_VIEW: Regex =
+        Regex::new(r"(?is)^\s*CREATE\s+VIEW\s+([\w\.\[\]`\x22]+)").unwrap();
+    static ref CREATE_INDEX: Regex = Regex::new(
+        r"(?is)^\s*CREATE\s+(?:UNIQUE\s+)?(?:(?:NON)?CLUSTERED\s+)?INDEX\s+(?:IF\s+NOT\s+EXISTS\s+)?([\w\.\[\]`\x22]+)\s+ON\s+([\w\.\[\]`\x22]+)"
+    )
+    .unwrap();
+    static ref ADD_COLUMN: Regex = Regex::new(
+        r"(?is)^\s*ALTER\s+TABLE\s+([\w\.\[\]`\x22]+)\s+ADD\s+(?:COLUMN\s+)?([\w\[\]`\x22]+)\s"
+    )
+    .unwrap();
+}
+
+/// Words following `ADD` that do not start a column definition.
+const NOT_COLUMN: &[&str] = &[
+    "CONSTRAINT",
+    "PRIMARY",
+    "UNIQUE",
+    "INDEX",
+    "KEY",
+    "FOREIGN",
+    "CHECK",
+    "PARTITION",
+    "IF",
+];
+
+/// Derives statement reversing given meet statement if possible.
+///
+/// Indexes are dropped with `DROP INDEX name ON table` form in SQL Server and MySQL and with
+/// `DROP INDEX name` in Postgres and MonetDB; in other dialects they are irreversible.
+pub fn rollback_statement(statement: &str, dialect: Dialect) -> Option<Sql> {
+    if let Some(captures) = CREATE_TABLE.captures(statement) {
+        return Some(format!("DROP TABLE {}", &captures[1]).into());
+    }
+    if let Some(captures) = CREATE_VIEW.captures(statement) {
+        return Some(format!("DROP VIEW {}", &captures[1]).into());
+    }
+    if let Some(captures) = CREATE_INDEX.captures(statement) {
+        return match dialect {
+            Dialect::SqlServer | Dialect::MySql => {
+                Some(format!("DROP INDEX {} ON {}", &captures[1], &captures[2]).into())
+            }
+            Dialect::Postgres | Dialect::MonetDb => {
+                Some(format!("DROP INDEX {}", &captures[1]).into())
+            }
+            _ => None,
+        };
+    }
+    if let Some(captures) = ADD_COLUMN.captures(statement) {
+        let column = &captures[2];
+        if NOT_COLUMN
+            .iter()
+            .any(|keyword| column.eq_ignore_ascii_case(keyword))
+        {
+            return None;
+        }
+        return Some(format!("ALTER TABLE {} DROP COLUMN {}", &captures[1], column).into());
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rollback(statement: &str) -> Option<String> {
+        rollback_statement(statement, Dialect::SqlServer).map(|sql| sql.as_str().to_string())
+    }
+
+    #[test]
+    fn create_statements() {
+        assert_eq!(
+            rollback("create table if not exists foo (id INT)").as_deref(),
+            Some("DROP TABLE foo")
+        );
+        assert_eq!(
+            rollback("CREATE VIEW v AS SELECT 1").as_deref(),
+            Some("DROP VIEW v")
+        );
+        assert_eq!(
+            rollback("CREATE UNIQUE INDEX ix ON foo (id)").as_deref(),
+            Some("DROP INDEX ix ON foo")
+        );
+    }
+
+    #[test]
+    fn drop_index_follows_dialect() {
+        let statement = "CREATE INDEX ix ON foo (id)";
+        let rollback =
+            |dialect| rollback_statement(statement, dialect).map(|sql| sql.as_str().to_string());
+        assert_eq!(
+            rollback(Dialect::MySql).as_deref(),
+            Some("DROP INDEX ix ON foo")
+        );
+        assert_eq!(
+            rollback(Dialect::Postgres).as_deref(),
+            Some("DROP INDEX ix")
+        );
+        assert_eq!(rollback(Dialect::Snowflake), None);
+        assert_eq!(rollback(Dialect::Hive), None);
+        assert_eq!(rollback(Dialect::Generic), None);
+    }
+
+    #[test]
+    fn add_column() {
+        assert_eq!(
+            rollback("ALTER TABLE foo ADD bar INT").as_deref(),
+            Some("ALTER TABLE foo DROP COLUMN bar")
+        );
+        assert_eq!(
+            rollback("alter table foo add column bar INT").as_deref(),
+            Some("ALTER TABLE foo DROP COLUMN bar")
+        );
+    }
+
+    #[test]
+    fn add_other_than_column_is_irreversible() {
+        for statement in &[
+            "ALTER TABLE foo ADD CONSTRAINT pk PRIMARY KEY (id)",
+            "ALTER TABLE foo ADD PRIMARY KEY (id)",
+            "ALTER TABLE foo ADD UNIQUE (id)",
+            "ALTER TABLE foo ADD INDEX ix (id)",
+            "ALTER TABLE foo ADD KEY ix (id)",
+            "ALTER TABLE foo ADD FOREIGN KEY (bar_id) REFERENCES bar (id)",
+            "ALTER TABLE foo ADD CHECK (id > 0)",
+            "ALTER TABLE foo ADD PARTITION (day='2020-01-01')",
+            "ALTER TABLE foo ADD IF NOT EXISTS PARTITION (day='2020-01-01')",
+        ] {
+            assert_eq!(rollback(statement), None, "{}", statement);
+        }
+    }
+}
@@ -0,0 +1,181 @@
+use crate::{
+    EnsureOptions, EnsureReport, EnsureSchema, RetryPolicy, SchemaState, SchemaStateError,
+};
+use chrono::{NaiveDateTime, Timelike, Utc};
+use log::*;
+use problem::prelude::*;
+use std::sync::mpsc::{channel, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+type SuccessFn = dyn FnMut(SchemaState, &EnsureReport) + Send;
+type FailureFn = dyn FnMut(&SchemaStateError, &EnsureReport) + Send;
+
+/// When scheduled runs happen.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Schedule {
+    /// Every given interval counting from the end of the previous run; first run happens
+    /// immediately.
+    Every(Duration),
+    /// Every hour at given minute (UTC).
+    Hourly { minute: u32 },
+    /// Every day at given hour and minute (UTC).
+    Daily { hour: u32, minute: u32 },
+}
+
+impl Schedule {
+    /// Time to wait from `now` until the next run.
+    fn delay(&self, now: NaiveDateTime, first: bool) -> Duration {
+        let next = match *self {
+            Schedule::Every(interval) => {
+                return if first {
+                    Duration::from_secs(0)
+                } else {
+                    interval
+                };
+            }
+            Schedule::Hourly { minute } => {
+                let next = now.date().and_hms(now.time().hour(), minute % 60, 0);
+                if next > now {
+                    next
+                } else {
+                    next + chrono::Duration::hours(1)
+                }
+            }
+            Schedule::Daily { hour, minute } => {
+                let next = now.date().and_hms(hour % 24, minute % 60, 0);
+                if next > now {
+                    next
+                } else {
+                    next + chrono::Duration::days(1)
+                }
+            }
+        };
+        (next - now).to_std().unwrap_or_default()
+    }
+}
+
+/// Runs ensure (or verify with `EnsureOptions::dry_run`) of a schema on a `Schedule` in a
+/// background thread connecting to the database for each run.
+///
+/// Unlike `EnsureSchema::watch` the schema and options are built in the scheduler thread as they
+/// hold callbacks that cannot be sent between threads.
+pub struct Scheduler {
+    schedule: Schedule,
+    retry: RetryPolicy,
+    on_success: Option<Box<SuccessFn>>,
+    on_failure: Option<Box<FailureFn>>,
+}
+
+impl Scheduler {
+    /// Creates `Scheduler` running on given schedule; connection attempts are not retried.
+    pub fn new(schedule: Schedule) -> Scheduler {
+        Scheduler {
+            schedule,
+            retry: RetryPolicy::new(1),
+            on_success: None,
+            on_failure: None,
+        }
+    }
+
+    /// Sets retry policy used when connecting and reconnecting (see
+    /// `EnsureSchema::ensure_with_connector`).
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Scheduler {
+        self.retry = retry;
+        self
+    }
+
+    /// Called with the state and report of each successful run.
+    pub fn on_success(
+        mut self,
+        on_success: impl FnMut(SchemaState, &EnsureReport) + Send + 'static,
+    ) -> Scheduler {
+        self.on_success = Some(Box::new(on_success));
+        self
+    }
+
+    /// Called with the error and (partial) report of each failed run; failed runs do not stop
+    /// the scheduler.
+    pub fn on_failure(
+        mut self,
+        on_failure: impl FnMut(&SchemaStateError, &EnsureReport) + Send + 'static,
+    ) -> Scheduler {
+        self.on_failure = Some(Box::new(on_failure));
+        self
+    }
+
+    /// Starts the scheduler thread; `build` is called once in the thread to build the schema and
+    /// options of the runs.
+    pub fn start(
+        self,
+        connection_string: impl Into<String>,
+        build: impl FnOnce() -> (EnsureSchema, EnsureOptions) + Send + 'static,
+    ) -> Result<SchedulerHandle, Problem> {
+        let connection_string = connection_string.into();
+        let (stop, stopped) = channel();
+        let mut scheduler = self;
+        let thread = thread::Builder::new()
+            .name("schema scheduler".to_string())
+            .spawn(move || {
+                let (schema, options) = build();
+                let mut first = true;
+                loop {
+                    let delay = scheduler.schedule.delay(Utc::now().naive_utc(), first);
+                    first = false;
+                    debug!(
+                        "Next scheduled run of schema {} in {:?}",
+                        schema.name, delay
+                    );
+                    match stopped.recv_timeout(delay) {
+                        Err(RecvTimeoutError::Timeout) => (),
+                        Ok(()) | Err(RecvTimeoutError::Disconnected) => break,
+                    }
+
+                    let mut report = EnsureReport::default();
+                    match schema.ensure_with_connection_string(
+                        &connection_string,
+                        &options,
+                        &scheduler.retry,
+                        &mut report,
+                    ) {
+                        Ok(state) => {
+                            info!("Scheduled run of schema {}: {:?}", schema.name, state);
+                            if let Some(on_success) = scheduler.on_success.as_mut() {
+                                on_success(state, &report);
+                            }
+                        }
+                        Err(err) => {
+                            error!("Scheduled run of schema {} failed: {}", schema.name, err);
+                            if let Some(on_failure) = scheduler.on_failure.as_mut() {
+                                on_failure(&err, &report);
+                            }
+                        }
+                    }
+                    if options.is_cancelled() {
+                        break;
+                    }
+                }
+                info!("Stopped scheduler of schema: {}", schema.name);
+            })
+            .problem_while("spawning scheduler thread")?;
+        Ok(SchedulerHandle { stop, thread })
+    }
+}
+
+/// Handle of running `Scheduler`; dropping it stops the scheduler before its next run without
+/// waiting for the thread.
+pub struct SchedulerHandle {
+    stop: Sender<()>,
+    thread: JoinHandle<()>,
+}
+
+impl SchedulerHandle {
+    /// Stops the scheduler waiting for the run in progress (if any) to finish.
+    pub fn stop(self) -> Result<(), Problem> {
+        // thread may have already stopped due to cancellation
+        self.stop.send(()).ok();
+        self.thread
+            .join()
+            .map_err(|_| Problem::from_error("Scheduler thread panicked"))
+    }
+}
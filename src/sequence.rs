@@ -0,0 +1,184 @@
+use crate::state::{quote_literal, value_to_i64};
+use crate::{Dialect, EnsureSchema, Sql};
+use log::*;
+use problem::prelude::*;
+
+/// Sequence created when missing or altered when its increment (or start) differs from expected.
+///
+/// Start of an existing sequence is only changed where it does not restart the sequence
+/// (Postgres); elsewhere difference is logged as restarting could reissue values.
+///
+/// With `SequenceDef::identity` the `IDENTITY` column of a SQL Server table is checked instead:
+/// the table is reseeded when its current identity value is below the start and differing
+/// increment fails the check as it cannot be altered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SequenceDef {
+    /// Name of the sequence or of the table with `IDENTITY` column.
+    pub name: String,
+    pub start: i64,
+    pub increment: i64,
+    pub identity: bool,
+}
+
+impl SequenceDef {
+    /// Creates `SequenceDef` starting with 1 and incremented by 1.
+    pub fn new(name: impl Into<String>) -> SequenceDef {
+        SequenceDef {
+            name: name.into(),
+            start: 1,
+            increment: 1,
+            identity: false,
+        }
+    }
+
+    /// Creates `SequenceDef` checking seed of SQL Server `IDENTITY` column of given table.
+    pub fn identity(table: impl Into<String>) -> SequenceDef {
+        SequenceDef {
+            identity: true,
+            ..SequenceDef::new(table)
+        }
+    }
+
+    pub fn with_start(mut self, start: i64) -> SequenceDef {
+        self.start = start;
+        self
+    }
+
+    pub fn with_increment(mut self, increment: i64) -> SequenceDef {
+        self.increment = increment;
+        self
+    }
+
+    /// Query producing single row with start (or current identity value) and increment of the
+    /// sequence or no rows if it does not exist; `None` if not supported in the dialect.
+    pub fn check_query(&self, dialect: Dialect) -> Option<Sql> {
+        let name = quote_literal(&self.name);
+        let query = match (dialect, self.identity) {
+            (Dialect::SqlServer, true) => format!(
+                "SELECT CAST(IDENT_CURRENT({}) AS BIGINT), CAST(increment_value AS BIGINT) FROM sys.identity_columns WHERE object_id = OBJECT_ID({})",
+                name, name
+            ),
+            (_, true) => return None,
+            (Dialect::SqlServer, false) => format!(
+                "SELECT CAST(start_value AS BIGINT), CAST(increment AS BIGINT) FROM sys.sequences WHERE name = {}",
+                name
+            ),
+            (Dialect::Postgres, false) => format!(
+                "SELECT start_value, increment_by FROM pg_sequences WHERE UPPER(sequencename) = UPPER({})",
+                name
+            ),
+            (Dialect::MonetDb, false) => format!(
+                "SELECT start, increment FROM sys.sequences WHERE name = {}",
+                name
+            ),
+            _ => return None,
+        };
+        Some(query.into())
+    }
+
+    /// `CREATE SEQUENCE` statement.
+    pub fn create_query(&self) -> Sql {
+        format!(
+            "CREATE SEQUENCE {} START WITH {} INCREMENT BY {}",
+            self.name, self.start, self.increment
+        )
+        .into()
+    }
+
+    /// Statements bringing sequence with given start (or current identity value) and increment to
+    /// the definition.
+    pub fn alter_queries(
+        &self,
+        dialect: Dialect,
+        start: i64,
+        increment: i64,
+    ) -> Result<Vec<Sql>, Problem> {
+        let mut queries = Vec::new();
+        if self.identity {
+            if increment != self.increment {
+                return problem!(
+                    "Identity of {} is incremented by {} but {} was expected; it cannot be altered",
+                    self.name,
+                    increment,
+                    self.increment
+                );
+            }
+            if start < self.start {
+                queries.push(
+                    format!(
+                        "DBCC CHECKIDENT ({}, RESEED, {})",
+                        quote_literal(&self.name),
+                        self.start
+                    )
+                    .into(),
+                );
+            }
+            return Ok(queries);
+        }
+
+        if increment != self.increment {
+            queries.push(
+                format!(
+                    "ALTER SEQUENCE {} INCREMENT BY {}",
+                    self.name, self.increment
+                )
+                .into(),
+            );
+        }
+        if start != self.start {
+            if dialect == Dialect::Postgres {
+                queries
+                    .push(format!("ALTER SEQUENCE {} START WITH {}", self.name, self.start).into());
+            } else {
+                warn!(
+                    "Sequence {} starts with {} but {} was expected; not restarting it",
+                    self.name, start, self.start
+                );
+            }
+        }
+        Ok(queries)
+    }
+
+    /// `EnsureSchema` creating or altering the sequence (or reseeding the identity) to match the
+    /// definition.
+    pub fn ensure_schema(&self, dialect: Dialect) -> Result<EnsureSchema, Problem> {
+        let check_query = self.check_query(dialect).ok_or_else(|| {
+            Problem::from_error(format!(
+                "Sequence {} cannot be checked in {:?} dialect",
+                self.name, dialect
+            ))
+        })?;
+        let sequence = self.clone();
+        let (kind, declared) = if self.identity {
+            ("identity", Vec::new())
+        } else {
+            ("sequence", vec![self.create_query()])
+        };
+        Ok(EnsureSchema::with_inspection(
+            format!("{} {}", kind, self.name),
+            check_query,
+            move |rows, _database| {
+                let row = match rows.into_iter().next() {
+                    Some(row) => row,
+                    None if sequence.identity => {
+                        return problem!("Table {} has no identity column", sequence.name)
+                    }
+                    None => return Ok(vec![sequence.create_query()]),
+                };
+                let mut values = row.into_iter().map(value_to_i64);
+                let start = values.next().flatten();
+                let increment = values.next().flatten();
+                match (start, increment) {
+                    (Some(start), Some(increment)) => {
+                        sequence.alter_queries(dialect, start, increment)
+                    }
+                    _ => problem!(
+                        "Sequence {} check returned unexpected values",
+                        sequence.name
+                    ),
+                }
+            },
+        )
+        .with_declared_meet_queries(declared))
+    }
+}
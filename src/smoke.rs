@@ -0,0 +1,109 @@
+use crate::{EnsureSchema, ObjectState, Run, SchemaStateError, Sql};
+use log::*;
+use odbc_iter::{Handle, ValueRow};
+use problem::prelude::*;
+use std::time::Instant;
+
+/// What smoke test query has to produce to pass.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SmokeExpectation {
+    /// Query executes without error (e.g. procedure called with sample arguments).
+    Executes,
+    /// Query returns at least one row (e.g. view compiles and returns rows).
+    ReturnsRows,
+    /// First column of the first row equals given value (compared as text).
+    Value(String),
+}
+
+/// Query run after the object it is attached to was changed (see
+/// `EnsureSchema::with_smoke_test`); failure is reported as `ObjectState::VerificationFailed` and
+/// fails the run with `SchemaStateError::VerificationError`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SmokeTest {
+    pub name: String,
+    pub query: Sql,
+    pub expectation: SmokeExpectation,
+}
+
+impl SmokeTest {
+    pub fn new(
+        name: impl Into<String>,
+        query: impl Into<Sql>,
+        expectation: SmokeExpectation,
+    ) -> SmokeTest {
+        SmokeTest {
+            name: name.into(),
+            query: query.into(),
+            expectation,
+        }
+    }
+
+    /// Runs the query failing if it does not produce expected result.
+    pub fn run(&self, database: &mut Handle<'_>) -> Result<(), Problem> {
+        let mut rows = database.query::<ValueRow>(&self.query)?;
+        match &self.expectation {
+            SmokeExpectation::Executes => {
+                for row in rows {
+                    row?;
+                }
+                Ok(())
+            }
+            SmokeExpectation::ReturnsRows => match rows.next() {
+                Some(row) => row.map(drop).map_err(Problem::from_error),
+                None => problem!("Query returned no rows"),
+            },
+            SmokeExpectation::Value(expected) => {
+                let value = rows
+                    .next()
+                    .ok_or_problem("Query returned no rows")??
+                    .into_iter()
+                    .next()
+                    .flatten()
+                    .map(|value| value.to_string());
+                match value {
+                    Some(value) if value.trim() == expected => Ok(()),
+                    value => problem!(
+                        "Expected {} but got {}",
+                        expected,
+                        value.as_deref().unwrap_or("NULL")
+                    ),
+                }
+            }
+        }
+    }
+}
+
+impl EnsureSchema {
+    /// Runs given smoke test after this object (including its members and requirements) was
+    /// changed; smoke tests are not run in dry run or when the object was already met.
+    ///
+    /// Smoke tests are part of the fingerprint so that changing them verifies the object again.
+    pub fn with_smoke_test(mut self, test: SmokeTest) -> EnsureSchema {
+        self.smoke_tests.push(test);
+        self
+    }
+
+    /// Runs smoke tests of this object reporting each as separate object.
+    pub(crate) fn run_smoke_tests(
+        &self,
+        database: &mut Handle<'_>,
+        run: &Run<'_, '_>,
+    ) -> Result<(), SchemaStateError> {
+        for test in &self.smoke_tests {
+            let name = format!("smoke test {} of {}", test.name, self.name);
+            debug!("[~] Running {}", name);
+            let started = Instant::now();
+            let result = run.with_meet_database(database, |database| test.run(database));
+            match result {
+                Ok(()) => run.report(&name, ObjectState::Met, None, started.elapsed()),
+                Err(err) => {
+                    error!("[x] Failed {}: {}", name, err);
+                    let state = ObjectState::VerificationFailed(err.to_string());
+                    run.report(&name, state, None, started.elapsed());
+                    return Err(SchemaStateError::VerificationError(name, err));
+                }
+            }
+        }
+        Ok(())
+    }
+}
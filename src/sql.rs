@@ -0,0 +1,278 @@
+use odbc_iter::split_queries;
+use problem::prelude::*;
+use std::fmt;
+use std::ops::Deref;
+use std::sync::Arc;
+
+/// SQL statement text.
+///
+/// Trailing statement separators (`;`) and whitespace are removed on construction. Statement can
+/// be flagged as sensitive (e.g. when it contains a password) in which case its text is replaced
+/// with a placeholder when displayed (e.g. in logs and reports) but not when executed.
+///
+/// Text is shared between clones so large generated statements are not copied.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct Sql {
+    text: Arc<str>,
+    sensitive: bool,
+}
+
+/// Text displayed in place of sensitive statements.
+pub const SENSITIVE_PLACEHOLDER: &str = "/* sensitive statement */";
+
+impl Sql {
+    /// Creates `Sql` failing if the statement is empty after trimming separators.
+    ///
+    /// Conversions with `From` do not fail; statement created that way is validated before it is
+    /// executed instead.
+    pub fn new(text: impl Into<String>) -> Result<Sql, Problem> {
+        let sql = Sql::from(text.into());
+        sql.validate()?;
+        Ok(sql)
+    }
+
+    /// Fails if the statement is empty after trimming separators.
+    pub fn validate(&self) -> Result<(), Problem> {
+        if self.text.trim().is_empty() {
+            return problem!("Empty SQL statement");
+        }
+        Ok(())
+    }
+
+    /// Flags the statement as sensitive.
+    pub fn sensitive(mut self) -> Sql {
+        self.sensitive = true;
+        self
+    }
+
+    /// Returns `true` if the statement was flagged as sensitive.
+    pub fn is_sensitive(&self) -> bool {
+        self.sensitive
+    }
+
+    /// Statement text as executed.
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// Statement text or placeholder if the statement is sensitive.
+    pub fn redacted(&self) -> &str {
+        if self.sensitive {
+            SENSITIVE_PLACEHOLDER
+        } else {
+            &self.text
+        }
+    }
+
+    /// Statement text normalized with `normalize_sql`.
+    pub fn normalized(&self) -> String {
+        normalize_sql(&self.text)
+    }
+
+    /// Consumes the statement returning its text.
+    pub fn into_string(self) -> String {
+        self.text.to_string()
+    }
+}
+
+/// Splits SQL script into statements terminated with `;` (last one may be unterminated);
+/// statements consisting only of comments are skipped.
+pub fn split_script(script: &str) -> Result<Vec<Sql>, Problem> {
+    let mut script = script.trim_end().to_string();
+    if !script.ends_with(';') {
+        script.push(';');
+    }
+    script.push('\n');
+    split_queries(&script)
+        .map(|statement| Ok(Sql::from(statement?)))
+        .filter(|statement| {
+            statement
+                .as_ref()
+                .map_or(true, |statement: &Sql| !normalize_sql(statement).is_empty())
+        })
+        .collect()
+}
+
+/// Normalizes statement text so that cosmetic edits do not change it: comments are removed
+/// (except optimizer hints `/*+ .. */` and MySQL `/*! .. */` comments) and runs of whitespace
+/// are folded into single space; text of quoted literals and identifiers is kept verbatim.
+///
+/// This is used for fingerprints and plan hashes.
+pub fn normalize_sql(statement: &str) -> String {
+    let mut normalized = String::with_capacity(statement.len());
+    let mut space = false;
+    let mut chars = statement.chars().peekable();
+    while let Some(c) = chars.next() {
+        let close = match c {
+            '\'' | '"' | '`' => Some(c),
+            '[' => Some(']'),
+            '-' if chars.peek() == Some(&'-') => {
+                chars.by_ref().find(|c| *c == '\n');
+                space = true;
+                continue;
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let hint = matches!(chars.peek(), Some('+') | Some('!'));
+                let mut comment = String::from("/*");
+                let mut last = None;
+                for c in chars.by_ref() {
+                    comment.push(c);
+                    if last == Some('*') && c == '/' {
+                        break;
+                    }
+                    last = Some(c);
+                }
+                if hint {
+                    push_token(&mut normalized, &mut space, &comment);
+                } else {
+                    space = true;
+                }
+                continue;
+            }
+            c if c.is_whitespace() => {
+                space = true;
+                continue;
+            }
+            _ => None,
+        };
+        let mut token = c.to_string();
+        if let Some(close) = close {
+            token.extend(chars.by_ref().take_while(|c| *c != close));
+            token.push(close);
+        }
+        push_token(&mut normalized, &mut space, &token);
+    }
+    let trimmed = normalized.trim_end_matches(';').trim_end().len();
+    normalized.truncate(trimmed);
+    normalized
+}
+
+fn push_token(normalized: &mut String, space: &mut bool, token: &str) {
+    if *space && !normalized.is_empty() {
+        normalized.push(' ');
+    }
+    *space = false;
+    normalized.push_str(token);
+}
+
+impl From<String> for Sql {
+    fn from(mut text: String) -> Sql {
+        let trimmed = text
+            .trim_end_matches(|c: char| c == ';' || c.is_whitespace())
+            .len();
+        text.truncate(trimmed);
+        Sql {
+            text: text.into(),
+            sensitive: false,
+        }
+    }
+}
+
+impl From<&str> for Sql {
+    fn from(text: &str) -> Sql {
+        Sql::from(text.to_string())
+    }
+}
+
+impl From<&String> for Sql {
+    fn from(text: &String) -> Sql {
+        Sql::from(text.clone())
+    }
+}
+
+impl Deref for Sql {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.text
+    }
+}
+
+impl AsRef<str> for Sql {
+    fn as_ref(&self) -> &str {
+        &self.text
+    }
+}
+
+impl fmt::Display for Sql {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.redacted())
+    }
+}
+
+impl fmt::Debug for Sql {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.redacted(), f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn separators_are_trimmed() {
+        assert_eq!(Sql::from("SELECT 1; \n").as_str(), "SELECT 1");
+        assert!(Sql::new("SELECT 1;").is_ok());
+    }
+
+    #[test]
+    fn empty_statement_is_invalid() {
+        assert!(Sql::new(" ;\n").is_err());
+        let sql = Sql::from(" ;\n");
+        assert!(sql.validate().is_err());
+    }
+
+    fn texts(statements: Vec<Sql>) -> Vec<String> {
+        statements.into_iter().map(Sql::into_string).collect()
+    }
+
+    #[test]
+    fn split_statements() {
+        assert_eq!(
+            texts(split_script("CREATE TABLE foo (id INT);\nINSERT INTO foo VALUES (1)").unwrap()),
+            vec!["CREATE TABLE foo (id INT)", "INSERT INTO foo VALUES (1)"]
+        );
+    }
+
+    #[test]
+    fn split_keeps_quoted_semicolons() {
+        assert_eq!(
+            texts(
+                split_script("INSERT INTO foo VALUES ('a;b');\nSELECT \"x;y\" FROM foo;").unwrap()
+            ),
+            vec!["INSERT INTO foo VALUES ('a;b')", "SELECT \"x;y\" FROM foo"]
+        );
+    }
+
+    #[test]
+    fn split_skips_comments_and_empty_statements() {
+        assert_eq!(
+            texts(split_script("-- setup\nSELECT 1;\n;\n-- done\n").unwrap()),
+            vec!["SELECT 1"]
+        );
+    }
+
+    #[test]
+    fn normalize_whitespace_and_comments() {
+        assert_eq!(
+            normalize_sql("  SELECT\n\t*   FROM foo -- all\n WHERE /* c */ id = 1;\n"),
+            "SELECT * FROM foo WHERE id = 1"
+        );
+        assert_eq!(
+            normalize_sql("SELECT /*+ INDEX(foo) */ * FROM foo"),
+            "SELECT /*+ INDEX(foo) */ * FROM foo"
+        );
+    }
+
+    #[test]
+    fn normalize_keeps_case_and_literals() {
+        assert_eq!(
+            normalize_sql("select 'a  -- b'   from \"Foo  Bar\""),
+            "select 'a  -- b' from \"Foo  Bar\""
+        );
+        assert_ne!(normalize_sql("SELECT 'A'"), normalize_sql("SELECT 'a'"));
+        assert_eq!(normalize_sql("SELECT [a  b]"), "SELECT [a  b]");
+    }
+}
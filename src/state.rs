@@ -0,0 +1,301 @@
+use crate::{EnsureSchema, Sql};
+use log::*;
+use odbc_iter::{
+    ColumnConvertError, DatumType, DefaultConfiguration, Executed, Handle, ResultSet, Row,
+    RowConvertError, TryFromRow, Value, ValueRow,
+};
+use problem::prelude::*;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Table maintained by this crate that records, per object, fingerprint of its definition and
+/// when and by which run it was last verified to be met.
+///
+/// This allows to skip checks when nothing has changed since the last successful run.
+#[derive(Debug, Clone)]
+pub struct StateTable {
+    table: String,
+    freshness: Duration,
+}
+
+/// Entry stored in the `StateTable` for a schema object.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateEntry {
+    pub fingerprint: String,
+    /// Seconds since UNIX epoch when the object was last verified to be met.
+    pub verified_at: i64,
+    /// Identifier of the run that verified the object.
+    pub run_id: Option<String>,
+}
+
+impl StateTable {
+    /// Creates `StateTable` given table name; stored fingerprints are trusted for one hour.
+    pub fn new(table: impl Into<String>) -> StateTable {
+        StateTable {
+            table: table.into(),
+            freshness: Duration::from_secs(60 * 60),
+        }
+    }
+
+    /// Sets for how long after last verification stored fingerprint can be trusted to skip
+    /// the checks.
+    pub fn with_freshness(mut self, freshness: Duration) -> StateTable {
+        self.freshness = freshness;
+        self
+    }
+
+    /// Name of the table.
+    pub fn table(&self) -> &str {
+        &self.table
+    }
+
+    /// `EnsureSchema` that creates the table if it does not exist.
+    pub fn ensure_schema(&self) -> EnsureSchema {
+        table_schema(
+            format!("state table {}", self.table),
+            &self.table,
+            self.create_query(),
+        )
+    }
+
+    fn create_query(&self) -> Sql {
+        format!(
+            "CREATE TABLE {} (object_name VARCHAR(255) NOT NULL, fingerprint VARCHAR(32) NOT NULL, verified_at BIGINT NOT NULL, run_id VARCHAR(64))",
+            self.table
+        )
+        .into()
+    }
+
+    /// Loads stored entry for given object name.
+    pub fn load(
+        &self,
+        database: &mut Handle<'_>,
+        name: &str,
+    ) -> Result<Option<StateEntry>, Problem> {
+        let query = format!(
+            "SELECT object_name, fingerprint, verified_at, run_id FROM {} WHERE object_name = {}",
+            self.table,
+            quote_literal(name)
+        );
+        let mut rows = database.query::<ValueRow>(&query)?;
+        match rows.next() {
+            Some(row) => Ok(Some(entry_from_row(row?)?.1)),
+            None => Ok(None),
+        }
+    }
+
+    /// Loads all stored entries ordered by object name.
+    pub fn entries(&self, database: &mut Handle<'_>) -> Result<Vec<(String, StateEntry)>, Problem> {
+        self.query_entries(
+            database,
+            format!(
+                "SELECT object_name, fingerprint, verified_at, run_id FROM {} ORDER BY object_name",
+                self.table
+            ),
+        )
+    }
+
+    /// Loads entries of objects that were not verified to be met within given duration.
+    pub fn stale_entries(
+        &self,
+        database: &mut Handle<'_>,
+        older_than: Duration,
+    ) -> Result<Vec<(String, StateEntry)>, Problem> {
+        self.query_entries(
+            database,
+            format!(
+                "SELECT object_name, fingerprint, verified_at, run_id FROM {} WHERE verified_at < {} ORDER BY verified_at",
+                self.table,
+                now_epoch() - older_than.as_secs() as i64
+            ),
+        )
+    }
+
+    fn query_entries(
+        &self,
+        database: &mut Handle<'_>,
+        query: String,
+    ) -> Result<Vec<(String, StateEntry)>, Problem> {
+        database
+            .query::<ValueRow>(&query)?
+            .map(|row| entry_from_row(row?))
+            .collect()
+    }
+
+    /// Stores entry for given object name replacing any previously stored one.
+    pub fn store(
+        &self,
+        database: &mut Handle<'_>,
+        name: &str,
+        entry: &StateEntry,
+    ) -> Result<(), Problem> {
+        database
+            .query::<()>(&format!(
+                "DELETE FROM {} WHERE object_name = {}",
+                self.table,
+                quote_literal(name)
+            ))?
+            .no_result()?;
+        database
+            .query::<()>(&format!(
+                "INSERT INTO {} (object_name, fingerprint, verified_at, run_id) VALUES ({}, {}, {}, {})",
+                self.table,
+                quote_literal(name),
+                quote_literal(&entry.fingerprint),
+                entry.verified_at,
+                entry
+                    .run_id
+                    .as_ref()
+                    .map(|run_id| quote_literal(run_id))
+                    .unwrap_or_else(|| "NULL".to_string())
+            ))?
+            .no_result()?;
+        Ok(())
+    }
+
+    /// Returns `true` if stored entry matches the fingerprint and is still fresh.
+    pub(crate) fn is_fresh(&self, entry: &StateEntry, fingerprint: &str) -> bool {
+        entry.fingerprint == fingerprint
+            && now_epoch() - entry.verified_at < self.freshness.as_secs() as i64
+    }
+
+    /// Makes sure the table exists before recording; failures are only logged as the state table
+    /// is an optimisation.
+    pub(crate) fn prepare(&self, database: &mut Handle<'_>) {
+        if let Err(err) = self.ensure_schema().ensure(database) {
+            warn!("Failed to prepare schema state table: {}", err);
+        }
+    }
+
+    /// Records that object was verified to be met by given run.
+    pub(crate) fn record(
+        &self,
+        database: &mut Handle<'_>,
+        name: &str,
+        fingerprint: String,
+        run_id: &str,
+    ) {
+        let entry = StateEntry {
+            fingerprint,
+            verified_at: now_epoch(),
+            run_id: Some(run_id.to_string()),
+        };
+        if let Err(err) = self.store(database, name, &entry) {
+            warn!("Failed to record schema state for {}: {}", name, err);
+        }
+    }
+}
+
+fn entry_from_row(row: ValueRow) -> Result<(String, StateEntry), Problem> {
+    let mut row = row
+        .into_iter()
+        .map(|value| value.map(|value| value.to_string()));
+    let name = row
+        .next()
+        .flatten()
+        .ok_or_problem("State table object_name is NULL")?;
+    let fingerprint = row
+        .next()
+        .flatten()
+        .ok_or_problem("State table fingerprint is NULL")?;
+    let verified_at = row
+        .next()
+        .flatten()
+        .and_then(|verified_at| verified_at.parse().ok())
+        .ok_or_problem("State table verified_at is not an integer")?;
+    let run_id = row.next().flatten();
+    Ok((
+        name,
+        StateEntry {
+            fingerprint,
+            verified_at,
+            run_id,
+        },
+    ))
+}
+
+/// `EnsureSchema` creating table with given query if `information_schema` does not list it.
+pub(crate) fn table_schema(name: String, table: &str, create: impl Into<Sql>) -> EnsureSchema {
+    EnsureSchema::with_count_check(
+        name,
+        format!(
+            "SELECT COUNT(*) FROM information_schema.tables WHERE UPPER(table_name) = UPPER({})",
+            quote_literal(table)
+        ),
+        vec![create.into()],
+    )
+}
+
+pub(crate) fn now_epoch() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// First column of a check row read directly as integer; `BIT` is read as 0 or 1 and strings are
+/// parsed. This avoids allocating `ValueRow` for common boolean and count checks.
+pub(crate) struct Scalar(pub(crate) Option<i64>);
+
+impl TryFromRow<DefaultConfiguration> for Scalar {
+    type Error = RowConvertError;
+
+    fn try_from_row<'r, 's, 'c, S>(
+        mut row: Row<'r, 's, 'c, S, DefaultConfiguration>,
+    ) -> Result<Scalar, RowConvertError> {
+        let column = row
+            .shift_column()
+            .ok_or(RowConvertError::UnexpectedNumberOfColumns {
+                expected: 1,
+                got: 0,
+            })?;
+        let not_integer = || ColumnConvertError::ValueOutOfRange {
+            expected: "BIT or integer",
+        };
+        let value = match column.column_type.datum_type {
+            DatumType::Bit => column.into_bool().map(|value| value.map(i64::from)),
+            DatumType::Tinyint => column.into_i8().map(|value| value.map(i64::from)),
+            DatumType::Smallint => column.into_i16().map(|value| value.map(i64::from)),
+            DatumType::Integer => column.into_i32().map(|value| value.map(i64::from)),
+            DatumType::Bigint => column.into_i64(),
+            DatumType::String => match column.into_string().map_err(ColumnConvertError::from)? {
+                Some(value) => Ok(Some(value.trim().parse().map_err(|_| not_integer())?)),
+                None => Ok(None),
+            },
+            _ => return Err(not_integer().into()),
+        };
+        Ok(Scalar(value.map_err(ColumnConvertError::from)?))
+    }
+}
+
+/// Quotes value as SQL string literal.
+pub(crate) fn quote_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// Converts any integer like value to `i64`; drivers differ in types used for counts.
+pub(crate) fn value_to_i64(value: Option<Value>) -> Option<i64> {
+    match value? {
+        Value::Bit(value) => Some(value as i64),
+        Value::Tinyint(value) => Some(i64::from(value)),
+        Value::Smallint(value) => Some(i64::from(value)),
+        Value::Integer(value) => Some(i64::from(value)),
+        Value::Bigint(value) => Some(value),
+        Value::String(value) => value.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+/// Collects all check rows; this is a function (not closure) so that lifetime bounds of the
+/// result set are implied.
+pub(crate) fn collect_rows(
+    rows: ResultSet<'_, '_, ValueRow, Executed, DefaultConfiguration>,
+) -> Result<Vec<ValueRow>, Problem> {
+    Ok(rows.collect::<Result<Vec<_>, _>>()?)
+}
+
+/// Fetches first check row without fetching the rest; see `collect_rows`.
+pub(crate) fn first_row<'h, 'c: 'h>(
+    mut rows: ResultSet<'h, 'c, ValueRow, Executed, DefaultConfiguration>,
+) -> Result<Option<ValueRow>, Problem> {
+    Ok(rows.next().transpose()?)
+}
@@ -0,0 +1,134 @@
+use crate::{EnsureSchema, SchemaState, Sql};
+use odbc_iter::Handle;
+use problem::prelude::*;
+use std::fmt;
+
+/// Step of the schema tree that can be checked and met.
+///
+/// Custom steps (e.g. registering with an external service) can be added to the tree with
+/// `EnsureSchema::step` so they can require and be required by SQL based objects. Meet is not
+/// called in dry run.
+///
+/// Before meet the statements declared with `statements` are approved as meet queries are
+/// (destructive and forbidden statements, statement limit, approval policy, confirmation and
+/// backup); steps declaring no statements are opaque and are only met with
+/// `EnsureOptions::allow_destructive`.
+pub trait Ensure: fmt::Debug {
+    /// Reference name of the step.
+    fn name(&self) -> &str;
+
+    /// Returns `true` if the step is in target state.
+    fn check(&self, database: &mut Handle<'_>) -> Result<bool, Problem>;
+
+    /// Brings the step into target state; it is verified with `check` afterwards.
+    fn meet(&self, database: &mut Handle<'_>) -> Result<(), Problem>;
+
+    /// Statements meet executes, as far as they are known upfront; empty if the step is opaque.
+    fn statements(&self) -> Vec<Sql> {
+        Vec::new()
+    }
+
+    /// Description of violated invariant if the step only asserts (see `AssertStep`); such step
+    /// is never met and failed check is reported as `ObjectState::AssertionFailed`.
+    fn assertion(&self) -> Option<String> {
+        None
+    }
+}
+
+impl Ensure for EnsureSchema {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn check(&self, database: &mut Handle<'_>) -> Result<bool, Problem> {
+        self.is_met(database).map_err(Problem::from_error)
+    }
+
+    fn meet(&self, database: &mut Handle<'_>) -> Result<(), Problem> {
+        self.ensure(database)
+            .map(|_: SchemaState| ())
+            .map_err(Problem::from_error)
+    }
+
+    fn statements(&self) -> Vec<Sql> {
+        self.iter()
+            .flat_map(|node| node.schema.declared_meet_queries().to_vec())
+            .collect()
+    }
+}
+
+/// Function checking if `CodeStep` is in target state.
+type CodeCheckFn = dyn for<'h> Fn(&mut Handle<'h>) -> Result<bool, Problem>;
+
+/// Function bringing `CodeStep` into target state.
+type CodeMeetFn = dyn for<'h> Fn(&mut Handle<'h>) -> Result<(), Problem>;
+
+/// Step with check and meet implemented as Rust closures given the database handle.
+///
+/// This is useful for data migrations that cannot be expressed as static SQL (e.g. transforming
+/// rows with application logic); as with other steps meet is not called in dry run.
+pub struct CodeStep {
+    name: String,
+    check: Box<CodeCheckFn>,
+    meet: Box<CodeMeetFn>,
+    statements: Vec<Sql>,
+}
+
+impl fmt::Debug for CodeStep {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("CodeStep")
+            .field("name", &self.name)
+            .finish()
+    }
+}
+
+impl CodeStep {
+    /// Creates `CodeStep` given name, check function returning `true` if the step is met and meet
+    /// function.
+    pub fn new(
+        name: impl Into<String>,
+        check: impl for<'h> Fn(&mut Handle<'h>) -> Result<bool, Problem> + 'static,
+        meet: impl for<'h> Fn(&mut Handle<'h>) -> Result<(), Problem> + 'static,
+    ) -> CodeStep {
+        CodeStep {
+            name: name.into(),
+            check: Box::new(check),
+            meet: Box::new(meet),
+            statements: Vec::new(),
+        }
+    }
+
+    /// Declares statements executed by meet function so that the step is approved as others
+    /// (see `Ensure::statements`).
+    pub fn with_statements(
+        mut self,
+        statements: impl IntoIterator<Item = impl Into<Sql>>,
+    ) -> CodeStep {
+        self.statements = statements.into_iter().map(Into::into).collect();
+        self
+    }
+}
+
+impl Ensure for CodeStep {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn check(&self, database: &mut Handle<'_>) -> Result<bool, Problem> {
+        (self.check)(database)
+    }
+
+    fn meet(&self, database: &mut Handle<'_>) -> Result<(), Problem> {
+        (self.meet)(database)
+    }
+
+    fn statements(&self) -> Vec<Sql> {
+        self.statements.clone()
+    }
+}
+
+impl From<CodeStep> for EnsureSchema {
+    fn from(step: CodeStep) -> EnsureSchema {
+        EnsureSchema::step(step)
+    }
+}
@@ -0,0 +1,336 @@
+use crate::compare::{column_from_row, columns_query};
+use crate::{ColumnInfo, Dialect, EnsureSchema, IndexDef, Sql};
+use lazy_static::lazy_static;
+use log::*;
+use odbc_iter::ValueRow;
+use problem::prelude::*;
+use regex::Regex;
+
+lazy_static! {
+    static ref DECLARED_LENGTH: Regex =
+        Regex::new(r"(?i)^\s*\w*(?:CHAR|BINARY)[\w\s]*\(\s*(\d+|MAX)\s*\)\s*$").unwrap();
+}
+
+/// Column of `TableDef`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnDef {
+    pub name: String,
+    /// Type as used in `CREATE TABLE` (e.g. `VARCHAR(100)`).
+    pub data_type: String,
+    pub nullable: bool,
+    /// Default value expression.
+    pub default: Option<String>,
+}
+
+impl ColumnDef {
+    /// Creates nullable `ColumnDef` given name and type.
+    pub fn new(name: impl Into<String>, data_type: impl Into<String>) -> ColumnDef {
+        ColumnDef {
+            name: name.into(),
+            data_type: data_type.into(),
+            nullable: true,
+            default: None,
+        }
+    }
+
+    /// Makes the column `NOT NULL`.
+    pub fn not_null(mut self) -> ColumnDef {
+        self.nullable = false;
+        self
+    }
+
+    /// Sets default value expression (e.g. `0` or `CURRENT_TIMESTAMP`).
+    pub fn with_default(mut self, default: impl Into<String>) -> ColumnDef {
+        self.default = Some(default.into());
+        self
+    }
+
+    /// Column definition as used in `CREATE TABLE` and `ALTER TABLE`.
+    pub fn definition(&self) -> String {
+        let mut definition = format!("{} {}", self.name, self.data_type);
+        if let Some(default) = &self.default {
+            definition.push_str(" DEFAULT ");
+            definition.push_str(default);
+        }
+        if !self.nullable {
+            definition.push_str(" NOT NULL");
+        }
+        definition
+    }
+}
+
+/// Declarative definition of a table from which both the check and meet queries are generated.
+///
+/// If the table does not exist it is created; otherwise it is altered to match with statements
+/// generated by `column_diff`. Indexes are created after the table if they do not exist (see
+/// `IndexDef`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableDef {
+    pub name: String,
+    pub columns: Vec<ColumnDef>,
+    pub primary_key: Vec<String>,
+    pub indexes: Vec<IndexDef>,
+    /// Drop columns of existing table that are not declared.
+    pub drop_columns: bool,
+}
+
+impl TableDef {
+    pub fn new(name: impl Into<String>) -> TableDef {
+        TableDef {
+            name: name.into(),
+            columns: Vec::new(),
+            primary_key: Vec::new(),
+            indexes: Vec::new(),
+            drop_columns: false,
+        }
+    }
+
+    /// Adds column.
+    pub fn with_column(mut self, column: ColumnDef) -> TableDef {
+        self.columns.push(column);
+        self
+    }
+
+    /// Sets primary key columns; columns added so far are made `NOT NULL` accordingly.
+    pub fn with_primary_key(mut self, columns: &[&str]) -> TableDef {
+        self.primary_key = columns.iter().map(|column| column.to_string()).collect();
+        for column in &mut self.columns {
+            if self
+                .primary_key
+                .iter()
+                .any(|key| key.eq_ignore_ascii_case(&column.name))
+            {
+                column.nullable = false;
+            }
+        }
+        self
+    }
+
+    /// Adds index; its table is set to this table.
+    pub fn with_index(mut self, mut index: IndexDef) -> TableDef {
+        index.table = self.name.clone();
+        self.indexes.push(index);
+        self
+    }
+
+    /// Drops columns of existing table that are not declared; this requires
+    /// `EnsureOptions::allow_destructive`.
+    pub fn with_drop_columns(mut self) -> TableDef {
+        self.drop_columns = true;
+        self
+    }
+
+    /// `CREATE TABLE` statement.
+    pub fn create_query(&self) -> Sql {
+        let mut definitions = self
+            .columns
+            .iter()
+            .map(ColumnDef::definition)
+            .collect::<Vec<_>>();
+        if !self.primary_key.is_empty() {
+            definitions.push(format!("PRIMARY KEY ({})", self.primary_key.join(", ")));
+        }
+        format!("CREATE TABLE {} ({})", self.name, definitions.join(", ")).into()
+    }
+
+    /// Statements bringing table with given existing columns to the definition (see
+    /// `column_diff`).
+    pub fn alter_queries(&self, dialect: Dialect, existing: &[ColumnInfo]) -> Vec<Sql> {
+        column_diff(
+            dialect,
+            &self.name,
+            &self.columns,
+            existing,
+            self.drop_columns,
+        )
+    }
+
+    /// `EnsureSchema` creating or altering the table to match the definition followed by its
+    /// indexes; fails if any of the indexes is not supported in the dialect.
+    pub fn ensure_schema(&self, dialect: Dialect) -> Result<EnsureSchema, Problem> {
+        let table = self.clone();
+        let create = self.create_query();
+        let declared = vec![create.clone()];
+        let schema = EnsureSchema::with_inspection(
+            format!("table {}", self.name),
+            columns_query(&self.name, dialect),
+            move |rows, _database| {
+                if rows.is_empty() {
+                    return Ok(vec![create.clone()]);
+                }
+                Ok(table.alter_queries(dialect, &live_columns(rows)?))
+            },
+        )
+        .with_declared_meet_queries(declared);
+        self.indexes.iter().try_fold(schema, |schema, index| {
+            Ok(schema.then(index.ensure_schema(dialect)?))
+        })
+    }
+
+    /// `EnsureSchema` altering columns of existing table to match the definition without
+    /// creating it; fails if the table does not exist.
+    pub fn ensure_columns(&self, dialect: Dialect) -> EnsureSchema {
+        let table = self.clone();
+        EnsureSchema::with_inspection(
+            format!("columns of {}", self.name),
+            columns_query(&self.name, dialect),
+            move |rows, _database| {
+                if rows.is_empty() {
+                    return problem!("Table {} does not exist", table.name);
+                }
+                Ok(table.alter_queries(dialect, &live_columns(rows)?))
+            },
+        )
+    }
+}
+
+/// Minimal statements bringing live table columns to the desired ones: missing columns are added,
+/// nullability is altered and character or binary columns are widened to the declared length.
+///
+/// Columns are never narrowed and other type changes are not detected as catalog type names differ
+/// from declared ones; undeclared columns are dropped only if `drop` is set.
+pub fn column_diff(
+    dialect: Dialect,
+    table: &str,
+    desired: &[ColumnDef],
+    existing: &[ColumnInfo],
+    drop: bool,
+) -> Vec<Sql> {
+    let mut queries = Vec::new();
+    for column in desired {
+        let current = existing
+            .iter()
+            .find(|current| current.name.eq_ignore_ascii_case(&column.name));
+        let current = match current {
+            Some(current) => current,
+            None => {
+                queries.push(add_column_query(dialect, table, column));
+                continue;
+            }
+        };
+        let widen = match (declared_length(&column.data_type), current_length(current)) {
+            (Some(declared), Some(length)) if declared > length => true,
+            (Some(declared), Some(length)) if declared < length => {
+                warn!(
+                    "Not narrowing column {} of {} to {}",
+                    current, table, column.data_type
+                );
+                false
+            }
+            _ => false,
+        };
+        let nullability = current.nullable != column.nullable;
+        if widen || nullability {
+            queries.extend(alter_column_queries(
+                dialect,
+                table,
+                column,
+                widen,
+                nullability,
+            ));
+        }
+    }
+    if drop {
+        for current in existing {
+            if !desired
+                .iter()
+                .any(|column| column.name.eq_ignore_ascii_case(&current.name))
+            {
+                queries.push(format!("ALTER TABLE {} DROP COLUMN {}", table, current.name).into());
+            }
+        }
+    }
+    queries
+}
+
+fn live_columns(rows: Vec<ValueRow>) -> Result<Vec<ColumnInfo>, Problem> {
+    rows.into_iter().map(column_from_row).collect()
+}
+
+fn add_column_query(dialect: Dialect, table: &str, column: &ColumnDef) -> Sql {
+    match dialect {
+        Dialect::SqlServer => format!("ALTER TABLE {} ADD {}", table, column.definition()),
+        Dialect::Hive => format!(
+            "ALTER TABLE {} ADD COLUMNS ({} {})",
+            table, column.name, column.data_type
+        ),
+        _ => format!("ALTER TABLE {} ADD COLUMN {}", table, column.definition()),
+    }
+    .into()
+}
+
+fn alter_column_queries(
+    dialect: Dialect,
+    table: &str,
+    column: &ColumnDef,
+    widen: bool,
+    nullability: bool,
+) -> Vec<Sql> {
+    let queries = match dialect {
+        Dialect::SqlServer => vec![format!(
+            "ALTER TABLE {} ALTER COLUMN {} {} {}",
+            table,
+            column.name,
+            column.data_type,
+            if column.nullable { "NULL" } else { "NOT NULL" }
+        )],
+        Dialect::MySql => vec![format!(
+            "ALTER TABLE {} MODIFY COLUMN {}",
+            table,
+            column.definition()
+        )],
+        Dialect::Hive => {
+            if nullability {
+                warn!(
+                    "Cannot alter nullability of {}.{} in {:?}",
+                    table, column.name, dialect
+                );
+            }
+            if !widen {
+                return Vec::new();
+            }
+            vec![format!(
+                "ALTER TABLE {} CHANGE {} {} {}",
+                table, column.name, column.name, column.data_type
+            )]
+        }
+        _ => {
+            let mut queries = Vec::new();
+            if widen {
+                queries.push(format!(
+                    "ALTER TABLE {} ALTER COLUMN {} SET DATA TYPE {}",
+                    table, column.name, column.data_type
+                ));
+            }
+            if nullability {
+                queries.push(format!(
+                    "ALTER TABLE {} ALTER COLUMN {} {} NOT NULL",
+                    table,
+                    column.name,
+                    if column.nullable { "DROP" } else { "SET" }
+                ));
+            }
+            queries
+        }
+    };
+    queries.into_iter().map(Sql::from).collect()
+}
+
+/// Declared length of character or binary type (e.g. `VARCHAR(100)`); `MAX` is unbounded.
+fn declared_length(data_type: &str) -> Option<u64> {
+    let captures = DECLARED_LENGTH.captures(data_type)?;
+    let length = &captures[1];
+    if length.eq_ignore_ascii_case("MAX") {
+        Some(u64::MAX)
+    } else {
+        length.parse().ok()
+    }
+}
+
+/// Length of live column; `-1` is reported for unbounded (`MAX`) columns by SQL Server.
+fn current_length(column: &ColumnInfo) -> Option<u64> {
+    match column.max_length.as_deref()?.trim() {
+        "-1" => Some(u64::MAX),
+        length => length.parse().ok(),
+    }
+}
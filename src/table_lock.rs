@@ -0,0 +1,159 @@
+use crate::{Dialect, EnsureSchema, Run, Sql};
+use log::*;
+use odbc_iter::Handle;
+use problem::prelude::*;
+
+/// Mode of `TableLock`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockMode {
+    /// Other sessions can read but not write the table.
+    Shared,
+    /// Other sessions can neither read nor write the table.
+    Exclusive,
+}
+
+/// Explicit lock of a table held while meet statements of an object are executed so that
+/// concurrent writers cannot interfere with convergence logic (e.g. reference data sync).
+///
+/// Locks are taken in a transaction that is committed after the last meet statement, or rolled
+/// back if one fails, so meet statements must be allowed in a transaction in the dialect. In
+/// SQL Server a row is read with locking hints as the lock is only taken once the table is
+/// accessed.
+///
+/// In MySQL `LOCK TABLES` is used instead; it implicitly commits open transaction and is released
+/// with `UNLOCK TABLES` whether meet statements succeed or fail, so they are not rolled back.
+/// While it is held only the locked tables can be accessed by the meet statements.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableLock {
+    pub table: String,
+    pub mode: LockMode,
+}
+
+impl TableLock {
+    pub fn new(table: impl Into<String>, mode: LockMode) -> TableLock {
+        TableLock {
+            table: table.into(),
+            mode,
+        }
+    }
+
+    /// Statement locking the table within a transaction (list item of `LOCK TABLES` in MySQL);
+    /// `None` if not supported in the dialect.
+    fn lock_query(&self, dialect: Dialect) -> Option<Sql> {
+        let query = match (dialect, self.mode) {
+            (Dialect::SqlServer, LockMode::Shared) => format!(
+                "DECLARE @locked INT; SELECT TOP 1 @locked = 1 FROM {} WITH (TABLOCK, HOLDLOCK)",
+                self.table
+            ),
+            (Dialect::SqlServer, LockMode::Exclusive) => format!(
+                "DECLARE @locked INT; SELECT TOP 1 @locked = 1 FROM {} WITH (TABLOCKX, HOLDLOCK)",
+                self.table
+            ),
+            (Dialect::Postgres, LockMode::Shared) => {
+                format!("LOCK TABLE {} IN SHARE MODE", self.table)
+            }
+            (Dialect::Postgres, LockMode::Exclusive) => {
+                format!("LOCK TABLE {} IN ACCESS EXCLUSIVE MODE", self.table)
+            }
+            (Dialect::MySql, LockMode::Shared) => format!("{} READ", self.table),
+            (Dialect::MySql, LockMode::Exclusive) => format!("{} WRITE", self.table),
+            _ => return None,
+        };
+        Some(query.into())
+    }
+}
+
+/// Statements acquiring given locks and releasing them on success and failure; failure releases
+/// with rollback except in MySQL.
+fn lock_queries(dialect: Dialect, locks: &[TableLock]) -> Result<(Vec<Sql>, Sql, Sql), Problem> {
+    let lock_queries = locks
+        .iter()
+        .map(|lock| {
+            lock.lock_query(dialect).ok_or_else(|| {
+                Problem::from_error(format!(
+                    "Lock of table {} is not supported in {:?} dialect",
+                    lock.table, dialect
+                ))
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(match dialect {
+        Dialect::MySql => {
+            let tables = lock_queries
+                .iter()
+                .map(Sql::as_str)
+                .collect::<Vec<_>>()
+                .join(", ");
+            let unlock: Sql = "UNLOCK TABLES".into();
+            (
+                vec![format!("LOCK TABLES {}", tables).into()],
+                unlock.clone(),
+                unlock,
+            )
+        }
+        Dialect::SqlServer => {
+            let mut queries = vec!["BEGIN TRANSACTION".into()];
+            queries.extend(lock_queries);
+            (
+                queries,
+                "COMMIT TRANSACTION".into(),
+                "ROLLBACK TRANSACTION".into(),
+            )
+        }
+        _ => {
+            let mut queries = vec!["BEGIN".into()];
+            queries.extend(lock_queries);
+            (queries, "COMMIT".into(), "ROLLBACK".into())
+        }
+    })
+}
+
+impl EnsureSchema {
+    /// Holds given table lock while meet statements of this object are executed; not taken in
+    /// dry run or when the object is met.
+    ///
+    /// Table locks are part of the fingerprint.
+    pub fn with_table_lock(mut self, lock: TableLock) -> EnsureSchema {
+        self.table_locks.push(lock);
+        self
+    }
+}
+
+impl Run<'_, '_> {
+    /// Executes `f` with table locks of the object held; locks are released after `f` returns
+    /// committing if it succeeded or rolling back otherwise (unlocking in MySQL).
+    pub(crate) fn with_table_locks<T>(
+        &self,
+        database: &mut Handle<'_>,
+        name: &str,
+        locks: &[TableLock],
+        f: impl FnOnce(&mut Handle<'_>) -> Result<T, Problem>,
+    ) -> Result<T, Problem> {
+        if locks.is_empty() {
+            return f(database);
+        }
+        let (lock_queries, commit, rollback) = lock_queries(self.options.dialect, locks)?;
+        for lock_query in &lock_queries {
+            debug!("[=] Locking for {}: {}", name, lock_query);
+            if let Err(err) = self.execute(database, name, lock_query) {
+                self.execute(database, name, &rollback).ok();
+                return Err(err).problem_while_with(|| format!("locking tables for '{}'", name));
+            }
+        }
+        let result = f(database);
+        match &result {
+            Ok(_) => {
+                debug!("[=] Releasing locks of {}: {}", name, commit);
+                self.execute(database, name, &commit)
+                    .problem_while_with(|| format!("releasing table locks of '{}'", name))?;
+            }
+            Err(_) => {
+                debug!("[=] Releasing locks of {}: {}", name, rollback);
+                if let Err(err) = self.execute(database, name, &rollback) {
+                    warn!("Failed to release table locks of '{}': {}", name, err);
+                }
+            }
+        }
+        result
+    }
+}
@@ -0,0 +1,73 @@
+use crate::Sql;
+use lazy_static::lazy_static;
+use problem::prelude::*;
+use regex::{Captures, Regex};
+use std::collections::BTreeMap;
+
+lazy_static! {
+    static ref PLACEHOLDER: Regex = Regex::new(r"\{\{\s*(\w+)\s*\}\}").unwrap();
+}
+
+/// Returns `true` if text contains `{{name}}` placeholder.
+pub(crate) fn has_placeholder(text: &str) -> bool {
+    PLACEHOLDER.is_match(text)
+}
+
+/// Values of template placeholders.
+pub type TemplateContext = BTreeMap<String, String>;
+
+/// SQL statement template with `{{name}}` placeholders substituted with context values.
+///
+/// Values are substituted verbatim so they should be identifiers or already quoted literals.
+///
+/// This is not a Tera or Handlebars template: only plain `{{name}}` placeholders are supported
+/// (no filters, conditionals or loops inside the template text). Loops are expressed by rendering
+/// the template once per value with `render_each`. Integrating a full template engine behind a
+/// Cargo feature is still open.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SqlTemplate {
+    text: String,
+}
+
+impl SqlTemplate {
+    pub fn new(text: impl Into<String>) -> SqlTemplate {
+        SqlTemplate { text: text.into() }
+    }
+
+    /// Renders the template failing if any placeholder has no value in the context.
+    pub fn render(&self, context: &TemplateContext) -> Result<Sql, Problem> {
+        let mut missing = None;
+        let text = PLACEHOLDER.replace_all(&self.text, |captures: &Captures| {
+            let name = &captures[1];
+            match context.get(name) {
+                Some(value) => value.clone(),
+                None => {
+                    missing.get_or_insert_with(|| name.to_string());
+                    String::new()
+                }
+            }
+        });
+        if let Some(name) = missing {
+            return problem!("No value for template placeholder '{}'", name);
+        }
+        Sql::new(text.into_owned())
+    }
+
+    /// Renders one statement per value with placeholder `name` set to the value (e.g. one
+    /// `CREATE TABLE` per configured region).
+    pub fn render_each(
+        &self,
+        context: &TemplateContext,
+        name: &str,
+        values: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Result<Vec<Sql>, Problem> {
+        let mut context = context.clone();
+        values
+            .into_iter()
+            .map(|value| {
+                context.insert(name.to_string(), value.into());
+                self.render(&context)
+            })
+            .collect()
+    }
+}
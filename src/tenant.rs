@@ -0,0 +1,131 @@
+use crate::{EnsureOptions, EnsureReport, EnsureSchema, SchemaState, TemplateContext};
+use log::*;
+use odbc_iter::Handle;
+use std::fmt;
+
+/// Tenant with parameters (e.g. schema name or table prefix) used to instantiate definition tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tenant {
+    pub name: String,
+    pub parameters: TemplateContext,
+}
+
+impl Tenant {
+    pub fn new(name: impl Into<String>) -> Tenant {
+        Tenant {
+            name: name.into(),
+            parameters: TemplateContext::new(),
+        }
+    }
+
+    /// Sets parameter of the tenant.
+    pub fn with_parameter(mut self, name: impl Into<String>, value: impl Into<String>) -> Tenant {
+        self.parameters.insert(name.into(), value.into());
+        self
+    }
+}
+
+/// Outcome of ensuring tree instantiated for single tenant.
+#[derive(Debug)]
+pub struct TenantReport {
+    pub tenant: String,
+    /// State or error message if the run has failed.
+    pub result: Result<SchemaState, String>,
+    pub report: EnsureReport,
+}
+
+/// Consolidated outcome of ensuring tree for each tenant in order.
+#[derive(Debug, Default)]
+pub struct TenantsReport {
+    pub tenants: Vec<TenantReport>,
+}
+
+impl TenantsReport {
+    /// Tenants for which the run has failed.
+    pub fn failed(&self) -> impl Iterator<Item = &TenantReport> {
+        self.tenants.iter().filter(|tenant| tenant.result.is_err())
+    }
+
+    /// Returns `true` if run has failed for any of the tenants.
+    pub fn has_failures(&self) -> bool {
+        self.failed().next().is_some()
+    }
+}
+
+impl fmt::Display for TenantsReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for tenant in &self.tenants {
+            let changed = tenant
+                .report
+                .objects
+                .iter()
+                .filter(|object| !object.statements.is_empty())
+                .count();
+            match &tenant.result {
+                Ok(state) => writeln!(
+                    f,
+                    "{}: {:?} ({} objects changed)",
+                    tenant.tenant, state, changed
+                )?,
+                Err(message) => writeln!(f, "{}: failed: {}", tenant.tenant, message)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Ensures definition tree built for each tenant with the same options.
+///
+/// Object names of the built tree should include tenant name so that state table entries and
+/// locks of tenants do not collide.
+#[derive(Debug, Default, Clone)]
+pub struct TenantRunner {
+    stop_on_failure: bool,
+}
+
+impl TenantRunner {
+    pub fn new() -> TenantRunner {
+        TenantRunner::default()
+    }
+
+    /// If set to `true` remaining tenants are not ensured after run for a tenant has failed.
+    pub fn with_stop_on_failure(mut self, stop_on_failure: bool) -> TenantRunner {
+        self.stop_on_failure = stop_on_failure;
+        self
+    }
+
+    /// Builds tree for each tenant with given function and ensures it.
+    pub fn ensure(
+        &self,
+        database: &mut Handle<'_>,
+        tenants: &[Tenant],
+        options: &EnsureOptions,
+        build: impl Fn(&Tenant) -> EnsureSchema,
+    ) -> TenantsReport {
+        let mut reports = TenantsReport::default();
+        for tenant in tenants {
+            info!("Ensuring schema for tenant: {}", tenant.name);
+            let schema = build(tenant);
+            let mut report = EnsureReport::default();
+            let result = schema
+                .ensure_with_report(database, options, &mut report)
+                .map_err(|err| err.to_string());
+            if let Err(err) = &result {
+                error!(
+                    "Failed to ensure schema for tenant {}: {}",
+                    tenant.name, err
+                );
+            }
+            let failed = result.is_err();
+            reports.tenants.push(TenantReport {
+                tenant: tenant.name.clone(),
+                result,
+                report,
+            });
+            if failed && self.stop_on_failure {
+                break;
+            }
+        }
+        reports
+    }
+}
@@ -0,0 +1,472 @@
+use crate::{EnsureSchema, Node, Require, Sql};
+use problem::prelude::*;
+use std::fmt;
+
+/// Human-readable tree of an object with its group members and requirements (see
+/// `EnsureSchema::tree`).
+#[derive(Debug, Clone, Copy)]
+pub struct Tree<'s>(&'s EnsureSchema);
+
+impl EnsureSchema {
+    /// Displays definition tree with names, nesting and number of declared meet statements.
+    pub fn tree(&self) -> Tree<'_> {
+        Tree(self)
+    }
+}
+
+fn plural(count: usize, what: &str) -> String {
+    format!("{} {}{}", count, what, if count == 1 { "" } else { "s" })
+}
+
+fn write_node(
+    f: &mut fmt::Formatter,
+    schema: &EnsureSchema,
+    depth: usize,
+    prefix: &str,
+) -> fmt::Result {
+    let indent = "  ".repeat(depth);
+    let description = match &schema.node {
+        Node::Query { meet_queries, .. } if meet_queries.is_empty() => {
+            "statements decided on check".to_string()
+        }
+        Node::Query { meet_queries, .. } => plural(meet_queries.len(), "statement"),
+        Node::Group {
+            members,
+            independent,
+        } => format!(
+            "{} of {}",
+            if *independent { "all" } else { "sequence" },
+            plural(members.len(), "member")
+        ),
+        Node::Step(_) => "step".to_string(),
+        Node::Reference => "defined elsewhere".to_string(),
+    };
+    writeln!(f, "{}{}{} [{}]", indent, prefix, schema.name, description)?;
+    for required in &schema.meet_require {
+        match required {
+            Require::Schema(required) => write_node(f, required, depth + 1, "requires ")?,
+            Require::Lazy(_) => writeln!(f, "{}  requires [lazy]", indent)?,
+        }
+    }
+    if let Node::Group { members, .. } = &schema.node {
+        for member in members {
+            write_node(f, member, depth + 1, "")?;
+        }
+    }
+    Ok(())
+}
+
+impl fmt::Display for Tree<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_node(f, self.0, 0, "")
+    }
+}
+
+/// How an object is related to its parent in the definition tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Relation {
+    /// Object the traversal started at.
+    Root,
+    /// Requirement of the parent (see `EnsureSchema::with_meet_require`).
+    Requirement,
+    /// Member of the parent group.
+    Member,
+}
+
+/// Object visited during traversal of the definition tree.
+#[derive(Debug, Clone)]
+pub struct TreeNode<'s> {
+    pub schema: &'s EnsureSchema,
+    pub relation: Relation,
+    /// Names of the ancestors starting with the root object.
+    pub path: Vec<&'s str>,
+}
+
+impl TreeNode<'_> {
+    /// Depth of the object in the tree; root has depth 0.
+    pub fn depth(&self) -> usize {
+        self.path.len()
+    }
+}
+
+/// Receives objects of the definition tree in depth-first order (see `EnsureSchema::accept`).
+///
+/// Visitor can keep references to visited objects for the lifetime of the tree.
+pub trait Visitor<'s> {
+    /// Called when object is entered; if `false` is returned its requirements and members are not
+    /// visited.
+    fn enter(&mut self, node: &TreeNode<'s>) -> bool;
+
+    /// Called after requirements and members of the object were visited.
+    fn leave(&mut self, _node: &TreeNode<'s>) {}
+}
+
+impl EnsureSchema {
+    /// Visits this object, then its requirements and then group members depth first; lazy
+    /// requirements are not visited as they are not built.
+    pub fn accept<'s>(&'s self, visitor: &mut dyn Visitor<'s>) {
+        fn visit<'s>(
+            schema: &'s EnsureSchema,
+            relation: Relation,
+            path: &mut Vec<&'s str>,
+            visitor: &mut dyn Visitor<'s>,
+        ) {
+            let node = TreeNode {
+                schema,
+                relation,
+                path: path.clone(),
+            };
+            if visitor.enter(&node) {
+                path.push(&schema.name);
+                for required in &schema.meet_require {
+                    if let Require::Schema(required) = required {
+                        visit(required, Relation::Requirement, path, visitor);
+                    }
+                }
+                if let Node::Group { members, .. } = &schema.node {
+                    for member in members {
+                        visit(member, Relation::Member, path, visitor);
+                    }
+                }
+                path.pop();
+            }
+            visitor.leave(&node);
+        }
+
+        visit(self, Relation::Root, &mut Vec::new(), visitor)
+    }
+
+    /// Iterates over this object and all objects below it in order of `accept`.
+    pub fn iter(&self) -> impl Iterator<Item = TreeNode<'_>> {
+        struct Collect<'s>(Vec<TreeNode<'s>>);
+
+        impl<'s> Visitor<'s> for Collect<'s> {
+            fn enter(&mut self, node: &TreeNode<'s>) -> bool {
+                self.0.push(node.clone());
+                true
+            }
+        }
+
+        let mut collect = Collect(Vec::new());
+        self.accept(&mut collect);
+        collect.0.into_iter()
+    }
+
+    /// Check query of this object; `None` for groups and steps.
+    pub fn check_query(&self) -> Option<&Sql> {
+        match &self.node {
+            Node::Query { check_query, .. } => Some(check_query),
+            _ => None,
+        }
+    }
+
+    /// Meet queries known upfront (e.g. these of `with_bool_check`); queries decided by ensure
+    /// function on check are not known.
+    pub fn declared_meet_queries(&self) -> &[Sql] {
+        match &self.node {
+            Node::Query { meet_queries, .. } => meet_queries,
+            _ => &[],
+        }
+    }
+
+    /// Returns `true` if this object is a group created with `sequence` or `all`.
+    pub fn is_group(&self) -> bool {
+        matches!(self.node, Node::Group { .. })
+    }
+
+    /// Returns `true` if this object is a custom step.
+    pub fn is_step(&self) -> bool {
+        matches!(self.node, Node::Step(_))
+    }
+
+    /// Returns `true` if this object only refers to object of the same name defined elsewhere in
+    /// the tree (see `merge`).
+    pub fn is_reference(&self) -> bool {
+        matches!(self.node, Node::Reference)
+    }
+}
+
+impl EnsureSchema {
+    /// Finds first object of given name in order of `accept`; references are skipped.
+    pub fn find(&self, name: &str) -> Option<&EnsureSchema> {
+        self.iter()
+            .find(|node| node.schema.name == name && !node.schema.is_reference())
+            .map(|node| node.schema)
+    }
+
+    /// Finds first object of given name in order of `accept` for modification; references are
+    /// skipped.
+    pub fn find_mut(&mut self, name: &str) -> Option<&mut EnsureSchema> {
+        if self.name == name && !self.is_reference() {
+            return Some(self);
+        }
+        for required in &mut self.meet_require {
+            if let Require::Schema(required) = required {
+                if let Some(found) = required.find_mut(name) {
+                    return Some(found);
+                }
+            }
+        }
+        if let Node::Group { members, .. } = &mut self.node {
+            for member in members {
+                if let Some(found) = member.find_mut(name) {
+                    return Some(found);
+                }
+            }
+        }
+        None
+    }
+
+    /// Replaces object of given name with the result of given function called with that object
+    /// (e.g. to add requirements with `with_meet_require`); returns `false` if not found.
+    ///
+    /// This allows to customize definition tree shipped by a library without rebuilding it.
+    pub fn wrap(&mut self, name: &str, wrap: impl FnOnce(EnsureSchema) -> EnsureSchema) -> bool {
+        match self.find_mut(name) {
+            Some(found) => {
+                let schema = std::mem::replace(found, EnsureSchema::sequence("", Vec::new()));
+                *found = wrap(schema);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Replaces object of given name returning the replaced one.
+    pub fn replace(&mut self, name: &str, replacement: EnsureSchema) -> Option<EnsureSchema> {
+        self.find_mut(name)
+            .map(|found| std::mem::replace(found, replacement))
+    }
+
+    /// Removes object of given name (other than this one) nearest to this one from requirements
+    /// or group members of its parent returning it.
+    pub fn remove(&mut self, name: &str) -> Option<EnsureSchema> {
+        if let Some(index) = self.meet_require.iter().position(
+            |required| matches!(required, Require::Schema(required) if required.name == name),
+        ) {
+            if let Require::Schema(removed) = self.meet_require.remove(index) {
+                return Some(*removed);
+            }
+        }
+        if let Node::Group { members, .. } = &mut self.node {
+            if let Some(index) = members.iter().position(|member| member.name == name) {
+                return Some(members.remove(index));
+            }
+        }
+        for required in &mut self.meet_require {
+            if let Require::Schema(required) = required {
+                if let Some(removed) = required.remove(name) {
+                    return Some(removed);
+                }
+            }
+        }
+        if let Node::Group { members, .. } = &mut self.node {
+            for member in members {
+                if let Some(removed) = member.remove(name) {
+                    return Some(removed);
+                }
+            }
+        }
+        None
+    }
+}
+
+/// How conflicting definitions of the same name are handled by `EnsureSchema::merge`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergePolicy {
+    /// Fail listing all conflicts.
+    Error,
+    /// Definition of the merged tree replaces the one of this tree.
+    Override,
+}
+
+impl EnsureSchema {
+    /// Names of objects defined in both trees with different definitions (see `fingerprint`).
+    pub fn conflicts(&self, other: &EnsureSchema) -> Vec<String> {
+        let mut conflicts: Vec<String> = Vec::new();
+        for node in other.iter() {
+            if let Some(found) = self.find(&node.schema.name) {
+                if found.fingerprint() != node.schema.fingerprint()
+                    && !conflicts.contains(&node.schema.name)
+                {
+                    conflicts.push(node.schema.name.clone());
+                }
+            }
+        }
+        conflicts
+    }
+
+    /// Merges other tree (e.g. of a plugin) to be ensured after this one.
+    ///
+    /// Objects defined identically in both trees are kept in both so that requirements of either
+    /// tree still hold; they are ensured once per run. Objects of the same name with different
+    /// definitions are handled accordingly to the policy; with `MergePolicy::Override` definition
+    /// of this tree is replaced with a reference to the one of the other tree. If the root of the
+    /// other tree is already in this tree its requirements and members are added to the existing
+    /// object instead.
+    pub fn merge(
+        mut self,
+        mut other: EnsureSchema,
+        policy: MergePolicy,
+    ) -> Result<EnsureSchema, Problem> {
+        let conflicts = self.conflicts(&other);
+        if policy == MergePolicy::Error && !conflicts.is_empty() {
+            return problem!(
+                "Conflicting definitions of schema objects: {}",
+                conflicts.join(", ")
+            );
+        }
+        for name in conflicts.iter().filter(|name| **name != other.name) {
+            while self
+                .replace(name, EnsureSchema::reference(name.clone()))
+                .is_some()
+            {}
+        }
+
+        let existing = match self.find_mut(&other.name) {
+            Some(existing) => existing,
+            None => return Ok(self.then(other)),
+        };
+        let mut requirements = std::mem::take(&mut existing.meet_require);
+        requirements.append(&mut other.meet_require);
+        let mut members = match &mut existing.node {
+            Node::Group { members, .. } => std::mem::take(members),
+            _ => Vec::new(),
+        };
+        if let Node::Group {
+            members: other_members,
+            ..
+        } = &mut other.node
+        {
+            members.append(other_members);
+        }
+        if conflicts.contains(&other.name) {
+            *existing = other;
+        }
+        existing.meet_require = requirements;
+        if let Node::Group {
+            members: existing_members,
+            ..
+        } = &mut existing.node
+        {
+            *existing_members = members;
+        }
+        Ok(self)
+    }
+}
+
+impl EnsureSchema {
+    /// Object standing for object of given name defined elsewhere in the tree.
+    fn reference(name: String) -> EnsureSchema {
+        EnsureSchema::base(name, Node::Reference)
+    }
+}
+
+fn location(node: &TreeNode<'_>) -> String {
+    let mut path = node.path.clone();
+    path.push(&node.schema.name);
+    path.join(" > ")
+}
+
+/// Fails if two objects with different definitions (see `EnsureSchema::fingerprint`) share the
+/// same name anywhere in given trees listing locations of both.
+///
+/// Name collisions break reporting and state tracking as objects are identified by name.
+pub fn validate_names<'s>(
+    trees: impl IntoIterator<Item = &'s EnsureSchema>,
+) -> Result<(), Problem> {
+    let mut seen: Vec<(TreeNode<'s>, String)> = Vec::new();
+    let mut duplicates = Vec::new();
+    for tree in trees {
+        for node in tree.iter() {
+            let fingerprint = node.schema.fingerprint();
+            match seen
+                .iter()
+                .find(|(seen, _)| seen.schema.name == node.schema.name)
+            {
+                Some((seen, seen_fingerprint)) if *seen_fingerprint != fingerprint => {
+                    duplicates.push(format!(
+                        "'{}' defined at {} and {}",
+                        node.schema.name,
+                        location(seen),
+                        location(&node)
+                    ));
+                }
+                Some(_) => (),
+                None => seen.push((node, fingerprint)),
+            }
+        }
+    }
+    if !duplicates.is_empty() {
+        return problem!("Duplicate schema object names: {}", duplicates.join("; "));
+    }
+    Ok(())
+}
+
+impl EnsureSchema {
+    /// Fails if two objects of this tree share the same name (see `validate_names`).
+    pub fn validate_names(&self) -> Result<(), Problem> {
+        validate_names(Some(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(name: &str, create: &str) -> EnsureSchema {
+        EnsureSchema::with_bool_check(name, "SELECT 0", vec![create])
+    }
+
+    fn requirements<'s>(tree: &'s EnsureSchema, name: &str) -> Vec<&'s str> {
+        tree.iter()
+            .filter(|node| {
+                node.relation == Relation::Requirement && node.path.last() == Some(&name)
+            })
+            .map(|node| node.schema.name.as_str())
+            .collect()
+    }
+
+    fn base() -> EnsureSchema {
+        EnsureSchema::sequence(
+            "base",
+            vec![table("shared", "CREATE TABLE shared (id INT)")],
+        )
+    }
+
+    #[test]
+    fn merge_keeps_requirement_on_shared_object() {
+        // Shared object is not met yet when the plugin table is ensured so it has to stay a
+        // requirement of the plugin table
+        let plugin = table("plugin", "CREATE TABLE plugin (id INT)")
+            .with_meet_require(table("shared", "CREATE TABLE shared (id INT)"));
+
+        let merged = base().merge(plugin, MergePolicy::Error).unwrap();
+        assert_eq!(requirements(&merged, "plugin"), vec!["shared"]);
+        assert!(merged.iter().all(|node| !node.schema.is_reference()));
+    }
+
+    #[test]
+    fn merge_override_references_merged_definition() {
+        let plugin = || {
+            table("plugin", "CREATE TABLE plugin (id INT)")
+                .with_meet_require(table("shared", "CREATE TABLE shared (id BIGINT)"))
+        };
+        assert!(base().merge(plugin(), MergePolicy::Error).is_err());
+
+        let merged = base().merge(plugin(), MergePolicy::Override).unwrap();
+        assert_eq!(requirements(&merged, "plugin"), vec!["shared"]);
+        assert_eq!(
+            merged.find("shared").unwrap().fingerprint(),
+            table("shared", "CREATE TABLE shared (id BIGINT)").fingerprint()
+        );
+        assert_eq!(
+            merged
+                .iter()
+                .filter(|node| node.schema.is_reference())
+                .map(|node| node.path.clone())
+                .collect::<Vec<_>>(),
+            vec![vec!["base then plugin"]]
+        );
+    }
+}
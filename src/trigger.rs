@@ -0,0 +1,167 @@
+use crate::hash::definition_marker;
+use crate::state::{collect_rows, quote_literal};
+use crate::{Dialect, EnsureSchema, Sql};
+use lazy_static::lazy_static;
+use problem::prelude::*;
+use regex::{NoExpand, Regex};
+
+lazy_static! {
+    static ref LEADING_BEGIN: Regex = Regex::new(r"(?i)^\s*BEGIN\b").unwrap();
+}
+
+/// Trigger (re)created when missing or when its body has changed since it was deployed.
+///
+/// Hash of the normalized body (see `normalize_sql`) is embedded in the deployed definition as a
+/// comment and the catalog definition is searched for it, so cosmetic edits do not redeploy the
+/// trigger. Each generated meet query is a single statement so bodies containing `;` are not
+/// split; use `Dialect::script_statement` when writing them to a script.
+///
+/// MySQL cannot replace triggers so redeploying drops the trigger first which requires
+/// `EnsureOptions::allow_destructive`; Postgres 14 or newer is required.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TriggerDef {
+    pub name: String,
+    pub table: String,
+    /// When the trigger fires (e.g. `AFTER INSERT` or `BEFORE UPDATE`).
+    pub timing: String,
+    /// Trigger body: T-SQL statements for SQL Server, statement or `BEGIN .. END` block for MySQL
+    /// and PL/pgSQL function body for Postgres.
+    pub body: String,
+}
+
+impl TriggerDef {
+    pub fn new(
+        name: impl Into<String>,
+        table: impl Into<String>,
+        timing: impl Into<String>,
+        body: impl Into<String>,
+    ) -> TriggerDef {
+        TriggerDef {
+            name: name.into(),
+            table: table.into(),
+            timing: timing.into(),
+            body: body.into(),
+        }
+    }
+
+    /// Query producing deployed definition of the trigger (no rows or `NULL` if it does not
+    /// exist); `None` if not supported in the dialect.
+    pub fn check_query(&self, dialect: Dialect) -> Option<Sql> {
+        let query = match dialect {
+            Dialect::SqlServer => format!(
+                "SELECT OBJECT_DEFINITION(OBJECT_ID({}))",
+                quote_literal(&self.name)
+            ),
+            Dialect::MySql => format!(
+                "SELECT action_statement FROM information_schema.triggers WHERE trigger_schema = DATABASE() AND trigger_name = {}",
+                quote_literal(&self.name)
+            ),
+            Dialect::Postgres => format!(
+                "SELECT p.prosrc FROM pg_trigger t JOIN pg_proc p ON p.oid = t.tgfoid WHERE UPPER(t.tgname) = UPPER({})",
+                quote_literal(&self.name)
+            ),
+            _ => return None,
+        };
+        Some(query.into())
+    }
+
+    /// Statements (re)creating the trigger; row level triggers are created in MySQL and Postgres.
+    pub fn create_queries(&self, dialect: Dialect) -> Result<Vec<Sql>, Problem> {
+        let marker = definition_marker(&self.body);
+        let queries = match dialect {
+            Dialect::SqlServer => vec![format!(
+                "CREATE OR ALTER TRIGGER {} ON {} {} AS\n{}\n{}",
+                self.name, self.table, self.timing, marker, self.body
+            )],
+            Dialect::MySql => {
+                let body = mysql_marked_body(&self.body, &marker);
+                vec![
+                    format!("DROP TRIGGER IF EXISTS {}", self.name),
+                    format!(
+                        "CREATE TRIGGER {} {} ON {} FOR EACH ROW {}",
+                        self.name, self.timing, self.table, body
+                    ),
+                ]
+            }
+            Dialect::Postgres => {
+                let function = format!("{}_fn", self.name);
+                vec![
+                    format!(
+                        "CREATE OR REPLACE FUNCTION {}() RETURNS trigger LANGUAGE plpgsql AS $body$\n{}\n{}\n$body$",
+                        function, marker, self.body
+                    ),
+                    format!(
+                        "CREATE OR REPLACE TRIGGER {} {} ON {} FOR EACH ROW EXECUTE FUNCTION {}()",
+                        self.name, self.timing, self.table, function
+                    ),
+                ]
+            }
+            _ => {
+                return problem!(
+                    "Trigger {} cannot be created in {:?} dialect",
+                    self.name,
+                    dialect
+                )
+            }
+        };
+        Ok(queries.into_iter().map(Sql::from).collect())
+    }
+
+    /// `EnsureSchema` (re)creating the trigger if it is missing or its deployed definition does
+    /// not carry hash of the current body.
+    pub fn ensure_schema(&self, dialect: Dialect) -> Result<EnsureSchema, Problem> {
+        let check_query = self.check_query(dialect).ok_or_else(|| {
+            Problem::from_error(format!(
+                "Trigger {} cannot be checked in {:?} dialect",
+                self.name, dialect
+            ))
+        })?;
+        Ok(definition_schema(
+            format!("trigger {}", self.name),
+            check_query,
+            definition_marker(&self.body),
+            self.create_queries(dialect)?,
+        ))
+    }
+}
+
+/// MySQL routine body carrying the marker: it is put after leading `BEGIN` or the body is wrapped
+/// in `BEGIN .. END` block as comments outside of it are not kept in the catalog.
+pub(crate) fn mysql_marked_body(body: &str, marker: &str) -> String {
+    if LEADING_BEGIN.is_match(body) {
+        let begin = format!("BEGIN {}", marker);
+        LEADING_BEGIN.replace(body, NoExpand(&begin)).into_owned()
+    } else {
+        format!(
+            "BEGIN {} {}; END",
+            marker,
+            body.trim_end().trim_end_matches(';')
+        )
+    }
+}
+
+/// `EnsureSchema` running `create` unless the definition produced by `check_query` contains
+/// `marker`.
+pub(crate) fn definition_schema(
+    name: String,
+    check_query: Sql,
+    marker: String,
+    create: Vec<Sql>,
+) -> EnsureSchema {
+    let meet_queries = create.clone();
+    EnsureSchema::new(name, check_query, move |rows| {
+        let deployed = collect_rows(rows)?
+            .into_iter()
+            .next()
+            .and_then(|row| row.into_iter().next().flatten())
+            .map(|definition| definition.to_string());
+        Ok(
+            if deployed.is_some_and(|definition| definition.contains(&marker)) {
+                vec![]
+            } else {
+                create.clone()
+            },
+        )
+    })
+    .with_declared_meet_queries(meet_queries)
+}
@@ -0,0 +1,196 @@
+use crate::template::has_placeholder;
+use crate::{Dialect, EnsureSchema, Sql};
+use problem::prelude::*;
+
+/// Basic sanity check of statement text: it is not empty, has no unresolved template
+/// placeholders, its quotes and parentheses are balanced and, if dialect is given, it does not
+/// use backtick identifier quoting unless supported; text of comments and quoted literals and
+/// identifiers is ignored.
+///
+/// Quoting follows the dialect: `[..]` identifiers are recognized in SQL Server (or if dialect is
+/// not given) and backslash escapes in literals in MySQL, Hive and Snowflake.
+pub fn validate_statement(statement: &str, dialect: Option<Dialect>) -> Result<(), String> {
+    if statement.trim().is_empty() {
+        return Err("empty statement".to_string());
+    }
+    let brackets = matches!(dialect, Some(Dialect::SqlServer) | None);
+    let backslash_escapes = matches!(
+        dialect,
+        Some(Dialect::MySql) | Some(Dialect::Hive) | Some(Dialect::Snowflake)
+    );
+    let mut depth = 0i32;
+    let mut quote: Option<(char, char)> = None;
+    let mut backticks = false;
+    // Statement text outside of comments and quotes
+    let mut code = String::with_capacity(statement.len());
+    let mut chars = statement.chars().peekable();
+    while let Some(c) = chars.next() {
+        match quote {
+            Some((_, close)) if c == close => quote = None,
+            Some(('\'', _)) | Some(('"', _)) if c == '\\' && backslash_escapes => {
+                chars.next();
+            }
+            Some(_) => (),
+            None => match c {
+                '\'' | '"' => quote = Some((c, c)),
+                '`' => {
+                    backticks = true;
+                    quote = Some((c, c))
+                }
+                '[' if brackets => quote = Some(('[', ']')),
+                '-' if chars.peek() == Some(&'-') => {
+                    // Line comment
+                    chars.by_ref().find(|c| *c == '\n');
+                    code.push(' ');
+                }
+                '/' if chars.peek() == Some(&'*') => {
+                    // Block comment
+                    chars.next();
+                    let mut last = None;
+                    let closed = chars.by_ref().any(|c| {
+                        let end = last == Some('*') && c == '/';
+                        last = Some(c);
+                        end
+                    });
+                    if !closed {
+                        return Err("unterminated block comment".to_string());
+                    }
+                    code.push(' ');
+                }
+                '(' => depth += 1,
+                ')' => {
+                    depth -= 1;
+                    if depth < 0 {
+                        return Err("unbalanced parentheses".to_string());
+                    }
+                }
+                _ => code.push(c),
+            },
+        }
+    }
+    if let Some((open, _)) = quote {
+        return Err(format!("unterminated {} quote", open));
+    }
+    if depth != 0 {
+        return Err("unbalanced parentheses".to_string());
+    }
+    if has_placeholder(&code) {
+        return Err("unresolved template placeholder".to_string());
+    }
+    match dialect {
+        Some(Dialect::MySql) | Some(Dialect::Hive) | None => (),
+        Some(dialect) if backticks => {
+            return Err(format!(
+                "backtick quoting is not supported by {:?}",
+                dialect
+            ))
+        }
+        _ => (),
+    }
+    Ok(())
+}
+
+impl EnsureSchema {
+    /// Validates check and declared meet queries of all objects in the tree (see
+    /// `validate_statement`) so that problems are found before a run hits the database.
+    ///
+    /// This is done at the start of every run if enabled with `EnsureOptions::validate_sql`;
+    /// queries decided by ensure functions on check cannot be validated upfront.
+    pub fn validate_sql(&self, dialect: Option<Dialect>) -> Result<(), Problem> {
+        let mut problems = Vec::new();
+        for node in self.iter() {
+            let statements = node
+                .schema
+                .check_query()
+                .into_iter()
+                .chain(node.schema.declared_meet_queries());
+            for statement in statements {
+                if let Err(problem) = validate_statement(statement, dialect) {
+                    problems.push(format!(
+                        "{} in '{}': {}",
+                        problem,
+                        node.schema.name,
+                        Sql::redacted(statement)
+                    ));
+                }
+            }
+        }
+        if !problems.is_empty() {
+            return problem!("Invalid SQL: {}", problems.join("; "));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn comments_are_ignored() {
+        assert_eq!(
+            validate_statement("SELECT 1 /* it's ( */ FROM foo -- it's (\n", None),
+            Ok(())
+        );
+        assert_eq!(
+            validate_statement("SELECT 1 /* unterminated", None),
+            Err("unterminated block comment".to_string())
+        );
+    }
+
+    #[test]
+    fn unbalanced() {
+        assert!(validate_statement("SELECT 'foo", None).is_err());
+        assert!(validate_statement("SELECT (1", None).is_err());
+        assert!(validate_statement("SELECT 1)", None).is_err());
+        assert!(validate_statement("SELECT {{ column }}", None).is_err());
+        assert!(validate_statement("  ", None).is_err());
+    }
+
+    #[test]
+    fn quoted_text_is_ignored() {
+        assert_eq!(
+            validate_statement("SELECT [it's; (] FROM foo", Some(Dialect::SqlServer)),
+            Ok(())
+        );
+        assert_eq!(validate_statement("SELECT [a]] b] FROM foo", None), Ok(()));
+        assert_eq!(
+            validate_statement(r"SELECT 'it\'s (' FROM foo", Some(Dialect::MySql)),
+            Ok(())
+        );
+        assert_eq!(
+            validate_statement("SELECT 'it''s (' FROM foo", Some(Dialect::Postgres)),
+            Ok(())
+        );
+        assert_eq!(
+            validate_statement("SELECT '{{ not_a_placeholder }}' FROM foo", None),
+            Ok(())
+        );
+        assert_eq!(
+            validate_statement("SELECT 1 -- {{ comment }}\nFROM foo", None),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn quoting_follows_dialect() {
+        // Backslash is not an escape in standard SQL so the literal ends there
+        assert_eq!(
+            validate_statement(r"SELECT 'C:\' FROM foo", Some(Dialect::Postgres)),
+            Ok(())
+        );
+        assert!(validate_statement(r"SELECT 'C:\' FROM foo", Some(Dialect::MySql)).is_err());
+        // Brackets are array subscripts in Postgres
+        assert_eq!(
+            validate_statement("SELECT a[1] FROM foo", Some(Dialect::Postgres)),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn backticks() {
+        assert!(validate_statement("SELECT `id` FROM foo", Some(Dialect::MySql)).is_ok());
+        assert!(validate_statement("SELECT `id` FROM foo", Some(Dialect::Postgres)).is_err());
+        assert!(validate_statement("SELECT '`' FROM foo", Some(Dialect::Postgres)).is_ok());
+    }
+}
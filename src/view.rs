@@ -0,0 +1,64 @@
+use crate::state::collect_rows;
+use crate::{Dialect, EnsureSchema, Sql};
+
+/// Statement (re)defining view with given `SELECT` query.
+///
+/// `CREATE OR REPLACE VIEW` is used where supported, `CREATE OR ALTER VIEW` for SQL Server and
+/// plain `CREATE VIEW` otherwise.
+pub fn create_view_query(dialect: Dialect, view: &str, select: &str) -> Sql {
+    let create = if dialect.supports_create_or_replace() {
+        "CREATE OR REPLACE VIEW"
+    } else if dialect == Dialect::SqlServer {
+        "CREATE OR ALTER VIEW"
+    } else {
+        "CREATE VIEW"
+    };
+    format!("{} {} AS {}", create, view, select).into()
+}
+
+/// Statement (re)defining procedure given everything following the procedure name (parameters,
+/// options and body).
+pub fn create_procedure_query(dialect: Dialect, procedure: &str, definition: &str) -> Sql {
+    let create = if dialect.supports_create_or_replace() {
+        "CREATE OR REPLACE PROCEDURE"
+    } else if dialect == Dialect::SqlServer {
+        "CREATE OR ALTER PROCEDURE"
+    } else {
+        "CREATE PROCEDURE"
+    };
+    format!("{} {} {}", create, procedure, definition).into()
+}
+
+/// `EnsureSchema` that creates view if it does not exist.
+///
+/// Changes to the view query are not detected by the check; use `StateTable` fingerprint or force
+/// option to redefine the view as the statement replaces existing definition where supported.
+pub fn view_schema(dialect: Dialect, view: &str, select: &str) -> EnsureSchema {
+    exists_schema(
+        format!("view {}", view),
+        dialect.view_exists_query(view),
+        create_view_query(dialect, view, select),
+    )
+}
+
+/// `EnsureSchema` that creates procedure if it does not exist (see `view_schema` on changes).
+pub fn procedure_schema(dialect: Dialect, procedure: &str, definition: &str) -> EnsureSchema {
+    exists_schema(
+        format!("procedure {}", procedure),
+        dialect.procedure_exists_query(procedure),
+        create_procedure_query(dialect, procedure, definition),
+    )
+}
+
+/// `EnsureSchema` running `create` if `exists_query` produces no rows.
+pub(crate) fn exists_schema(name: String, exists_query: Sql, create: Sql) -> EnsureSchema {
+    let meet_queries = vec![create.clone()];
+    EnsureSchema::new(name, exists_query, move |rows| {
+        Ok(if collect_rows(rows)?.is_empty() {
+            vec![create.clone()]
+        } else {
+            vec![]
+        })
+    })
+    .with_declared_meet_queries(meet_queries)
+}
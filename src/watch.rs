@@ -0,0 +1,89 @@
+use crate::{EnsureOptions, EnsureSchema, SchemaState, SchemaStateError};
+use log::*;
+use odbc_iter::Handle;
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Controls how often schema is re-ensured in watch mode.
+#[derive(Debug, Clone)]
+pub struct WatchOptions {
+    interval: Duration,
+    jitter: Duration,
+}
+
+impl WatchOptions {
+    /// Creates `WatchOptions` that re-ensure schema every `interval`.
+    pub fn new(interval: Duration) -> WatchOptions {
+        WatchOptions {
+            interval,
+            jitter: Duration::from_secs(0),
+        }
+    }
+
+    /// Adds random delay of up to `jitter` to each interval so that many instances don't hit the
+    /// database at the same time.
+    pub fn with_jitter(mut self, jitter: Duration) -> WatchOptions {
+        self.jitter = jitter;
+        self
+    }
+}
+
+/// Simple xorshift generator; jitter does not need good randomness.
+struct Jitter(u64);
+
+impl Jitter {
+    fn new() -> Jitter {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|now| now.subsec_nanos() as u64 ^ now.as_secs())
+            .unwrap_or(0)
+            ^ u64::from(std::process::id());
+        Jitter(seed | 1)
+    }
+
+    fn next(&mut self, max: Duration) -> Duration {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        let max = max.as_millis() as u64;
+        if max == 0 {
+            Duration::from_secs(0)
+        } else {
+            Duration::from_millis(self.0 % max)
+        }
+    }
+}
+
+impl EnsureSchema {
+    /// Periodically re-ensures the schema until stop signal is received (or its sender is
+    /// dropped) or cancellation token of `options` is cancelled; result of each round is passed
+    /// to `on_result`.
+    ///
+    /// Use `EnsureOptions::dry_run` to only re-verify the schema without meeting it. Checks are
+    /// always run (see `EnsureOptions::full_check`) so that drifted objects are noticed.
+    pub fn watch(
+        &self,
+        database: &mut Handle<'_>,
+        options: &EnsureOptions,
+        watch: &WatchOptions,
+        stop: &Receiver<()>,
+        mut on_result: impl FnMut(Result<SchemaState, SchemaStateError>),
+    ) {
+        let options = options.clone().full_check(true);
+        let mut jitter = Jitter::new();
+        loop {
+            on_result(self.ensure_with_options(database, &options));
+            if options.is_cancelled() {
+                break;
+            }
+
+            let delay = watch.interval + jitter.next(watch.jitter);
+            debug!("Re-ensuring schema {} in {:?}", self.name, delay);
+            match stop.recv_timeout(delay) {
+                Err(RecvTimeoutError::Timeout) => continue,
+                Ok(()) | Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+        info!("Stopped watching schema: {}", self.name);
+    }
+}